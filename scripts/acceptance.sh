@@ -69,7 +69,7 @@ check_schema_and_kpis() {
     if [ "$suspects" -gt 0 ] && [ "$ocr_run" -eq 0 ]; then echo "[FAIL] OCRNoRunPages: $doc_id"; return 1; fi
     if [ "$suspects" -gt 0 ] && [ "$covp" != "1" ] && [ "$covp" != "1.0" ]; then echo "[FAIL] CoveragePagesLow: $doc_id ($covp)"; return 1; fi
     if [ "$ARTIFACTS" = "--artifacts=on" ]; then
-      local pngs=$(ls -1 "$d/artifacts/ocr"/page-*.png 2>/dev/null | wc -l | tr -d ' ')
+      local pngs=$(ls -1 "$d/artifacts/ocr"/*-page-*.png 2>/dev/null | wc -l | tr -d ' ')
       if [ "$ocr_run" -gt 0 ] && [ "$pngs" -lt "$ocr_run" ]; then echo "[FAIL] OCRArtifactsMismatch: $doc_id ($pngs < $ocr_run)"; return 1; fi
     fi
   done
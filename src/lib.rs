@@ -15,8 +15,15 @@ pub struct DepsResult {
 /// Check required/optional CLI dependencies.
 /// - Required: pdftotext (Poppler)
 /// - Optional: tesseract (OCR)
+///
 /// Returns a DepsResult. `ok` is true iff required deps are present.
 pub fn check_deps() -> DepsResult {
+    check_deps_with(false)
+}
+
+/// `check_deps`, also reporting the DjVu tools (`djvused`, `djvutxt`, `ddjvu`) as optional when `djvu` is
+/// set. They never affect `ok`: without them only DjVu inputs fail.
+pub fn check_deps_with(djvu: bool) -> DepsResult {
     let mut missing = Vec::new();
 
     // required
@@ -34,10 +41,43 @@ pub fn check_deps() -> DepsResult {
     if which::which("tesseract").is_err() {
         missing.push("tesseract".to_string());
     }
+    if djvu {
+        for bin in ["djvused", "djvutxt", "ddjvu"] {
+            if which::which(bin).is_err() {
+                missing.push(bin.to_string());
+            }
+        }
+    }
 
     DepsResult { ok: has_pdftotext && has_pdftoppm, missing }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub crate_version: String,
+    /// `None` when the tool is missing or its version output is unrecognized.
+    pub pdftotext: Option<String>,
+    pub tesseract: Option<String>,
+}
+
+/// Version of an external tool: the first dotted number in its version banner.
+/// pdftotext prints to stderr, tesseract to stdout (older builds to stderr); both are scanned.
+fn tool_version(bin: &str, flag: &str) -> Option<String> {
+    let out = Command::new(bin).arg(flag).output().ok()?;
+    let re = Regex::new(r"\d+(?:\.\d+)+").unwrap();
+    let text = format!("{}\n{}", String::from_utf8_lossy(&out.stdout), String::from_utf8_lossy(&out.stderr));
+    re.find(&text).map(|m| m.as_str().to_string())
+}
+
+/// Crate version plus detected Poppler/Tesseract versions, for `--version` and bug reports.
+pub fn version_report() -> VersionInfo {
+    VersionInfo {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        pdftotext: tool_version("pdftotext", "-v"),
+        tesseract: tool_version("tesseract", "--version"),
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum EnumerateError {
     #[error("NoFilesFound")]
@@ -47,13 +87,11 @@ pub enum EnumerateError {
 /// Enumerate PDFs using a glob pattern (e.g., "./input/**/*.pdf").
 /// Returns a sorted list of paths.
 pub fn enumerate_pdfs(glob_pattern: &str) -> Result<Vec<PathBuf>, EnumerateError> {
-    let root = if Path::new(glob_pattern).is_absolute() { "/" } else { "." };
-    let mut pat = glob_pattern.to_string();
-    if pat.starts_with("./") { pat = pat.trim_start_matches("./").to_string(); }
-    let mut paths: Vec<PathBuf> = GlobWalkerBuilder::from_patterns(root, &[pat.as_str()])
+    let (root, pat) = split_glob_root(glob_pattern);
+    let mut paths: Vec<PathBuf> = GlobWalkerBuilder::from_patterns(&root, &[pat.as_str()])
         .case_insensitive(false)
         .follow_links(false)
-        .max_depth(std::usize::MAX)
+        .max_depth(usize::MAX)
         .build()
         .map_err(|_| EnumerateError::NoFilesFound { guidance: folder_guidance() })?
         .filter_map(|e| e.ok())
@@ -70,6 +108,202 @@ pub fn enumerate_pdfs(glob_pattern: &str) -> Result<Vec<PathBuf>, EnumerateError
     Ok(paths)
 }
 
+/// True for `.djvu`/`.djv` paths, which `--djvu` routes to `djvu_extract` instead of Poppler.
+pub fn is_djvu(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("djvu") || e.eq_ignore_ascii_case("djv"))
+}
+
+/// The DjVu counterpart of a PDF glob (`./input/**/*.pdf` -> `./input/**/*.{djvu,djv}`), so both
+/// extensions `is_djvu` accepts are enumerated alongside it under `--djvu`; `None` when the glob
+/// does not end in `.pdf`.
+pub fn djvu_glob(glob_pattern: &str) -> Option<String> {
+    let stem_len = glob_pattern.len().checked_sub(4)?;
+    let (stem, ext) = (glob_pattern.get(..stem_len)?, &glob_pattern[stem_len..]);
+    ext.eq_ignore_ascii_case(".pdf").then(|| format!("{}.{{djvu,djv}}", stem))
+}
+
+/// Split a glob into the directory to walk and the `/`-joined pattern below it, by `Path`
+/// components, so absolute roots work on any OS (`/srv/corpus`, `C:\corpus`, `\\server\share`):
+/// the walk starts at the longest wildcard-free prefix (`./input/**/*.pdf` walks `input`, a
+/// leading `./` is dropped), the parent for a plain file path, and `.` when the glob starts
+/// with a wildcard.
+pub fn split_glob_root(glob_pattern: &str) -> (PathBuf, String) {
+    use std::path::Component;
+    let is_glob = |c: &Component| c.as_os_str().to_string_lossy().contains(['*', '?', '[', '{']);
+    let comps: Vec<Component> = Path::new(glob_pattern).components().filter(|c| *c != Component::CurDir).collect();
+    let join = |cs: &[Component]| cs.iter().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+    let fixed = comps.iter().take_while(|c| !is_glob(c)).count().min(comps.len().saturating_sub(1));
+    if fixed == 0 {
+        return (PathBuf::from("."), join(&comps));
+    }
+    (comps[..fixed].iter().collect(), join(&comps[fixed..]))
+}
+
+/// Drop paths matching any of the exclude globs (e.g. `**/lampiran/**`); a leading `./` is ignored.
+/// Errors on a pattern that does not compile.
+pub fn exclude_paths(paths: Vec<PathBuf>, patterns: &[String]) -> Result<Vec<PathBuf>, globset::Error> {
+    let mut builder = globset::GlobSetBuilder::new();
+    for p in patterns {
+        builder.add(globset::Glob::new(p.trim_start_matches("./"))?);
+    }
+    let set = builder.build()?;
+    Ok(paths.into_iter().filter(|p| !set.is_match(p.strip_prefix(".").unwrap_or(p))).collect())
+}
+
+/// Name of the optional gitignore-style file read from each input root.
+pub const IGNORE_FILE_NAME: &str = ".lpdfignore";
+
+/// Directory a glob enumerates from (see `split_glob_root`); `.lpdfignore` is read from here.
+pub fn glob_input_root(glob_pattern: &str) -> PathBuf {
+    split_glob_root(glob_pattern).0
+}
+
+/// Drop paths matched by `<root>/.lpdfignore` (gitignore syntax: `lampiran/`, `*-draft.pdf`,
+/// `!keep.pdf`, ...), matched relative to `root`. A missing file is a no-op. Returns the kept
+/// paths and how many were ignored; errors when the file cannot be read or parsed.
+pub fn apply_ignore_file(paths: Vec<PathBuf>, root: &Path) -> Result<(Vec<PathBuf>, usize), ignore::Error> {
+    use std::path::Component;
+    let file = root.join(IGNORE_FILE_NAME);
+    if !file.is_file() {
+        return Ok((paths, 0));
+    }
+    let mut builder = ignore::gitignore::GitignoreBuilder::new(root);
+    if let Some(e) = builder.add(&file) {
+        return Err(e);
+    }
+    let rules = builder.build()?;
+    let normalize = |p: &Path| p.components().filter(|c| *c != Component::CurDir).collect::<PathBuf>();
+    let root = normalize(root);
+    let before = paths.len();
+    let kept: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|p| {
+            let full = normalize(p);
+            let rel = full.strip_prefix(&root).unwrap_or(&full);
+            !rules.matched_path_or_any_parents(rel, false).is_ignore()
+        })
+        .collect();
+    let ignored = before - kept.len();
+    Ok((kept, ignored))
+}
+
+/// Split `paths` into first occurrences and byte-identical copies by SHA-256 of the content.
+/// The earliest path in the given order wins; copies come back as `(skipped, duplicate_of)`.
+/// Unreadable files are kept so the pipeline reports them.
+pub fn dedup_by_content(paths: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+    let mut first_by_hash: std::collections::HashMap<String, PathBuf> = std::collections::HashMap::new();
+    let mut kept = Vec::new();
+    let mut duplicates = Vec::new();
+    for path in paths {
+        let Ok(bytes) = std::fs::read(&path) else {
+            kept.push(path);
+            continue;
+        };
+        match first_by_hash.entry(sha256_hex(&bytes)) {
+            std::collections::hash_map::Entry::Occupied(first) => duplicates.push((path, first.get().clone())),
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(path.clone());
+                kept.push(path);
+            }
+        }
+    }
+    (kept, duplicates)
+}
+
+/// Entries of an `--input-list` file, in file order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputList {
+    /// Existing `.pdf` files, duplicates dropped.
+    pub paths: Vec<PathBuf>,
+    /// Listed paths that do not exist.
+    pub missing: Vec<PathBuf>,
+    /// Listed paths that exist but are not `.pdf` files.
+    pub not_pdf: Vec<PathBuf>,
+}
+
+/// Read newline-separated paths; blank lines and `#` comments are skipped. Relative paths
+/// resolve against the working directory, like globs. No sorting: list order is kept.
+pub fn read_input_list(list: &Path) -> std::io::Result<InputList> {
+    let text = std::fs::read_to_string(list)?;
+    let mut out = InputList::default();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let path = PathBuf::from(line);
+        let is_pdf = path.extension().map(|e| e.eq_ignore_ascii_case("pdf")).unwrap_or(false);
+        if !path.exists() {
+            out.missing.push(path);
+        } else if !is_pdf || !path.is_file() {
+            out.not_pdf.push(path);
+        } else if !out.paths.contains(&path) {
+            out.paths.push(path);
+        }
+    }
+    Ok(out)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's `days_from_civil`).
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Cutoff for `--since`: a duration back from `now` (`30s`, `90m`, `24h`, `7d`, `2w`) or a UTC
+/// date `YYYY-MM-DD` (midnight) / `YYYY-MM-DDTHH:MM[:SS]`.
+pub fn parse_since(spec: &str, now: std::time::SystemTime) -> Result<std::time::SystemTime, String> {
+    use std::time::{Duration, UNIX_EPOCH};
+    let spec = spec.trim();
+    let dur_re = Regex::new(r"^(\d+)([smhdw])$").unwrap();
+    if let Some(c) = dur_re.captures(spec) {
+        let n: u64 = c[1].parse().map_err(|_| format!("invalid duration: {}", spec))?;
+        let unit = match &c[2] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3_600,
+            "d" => 86_400,
+            _ => 604_800,
+        };
+        let back = Duration::from_secs(n.saturating_mul(unit));
+        return Ok(now.checked_sub(back).unwrap_or(UNIX_EPOCH));
+    }
+    let date_re = Regex::new(r"^(\d{4})-(\d{2})-(\d{2})(?:[T ](\d{2}):(\d{2})(?::(\d{2}))?)?$").unwrap();
+    let c = date_re
+        .captures(spec)
+        .ok_or_else(|| format!("expected a duration like 24h/7d or a date like 2024-01-01, got {}", spec))?;
+    let num = |i: usize| c.get(i).map_or(0, |m| m.as_str().parse::<u32>().unwrap_or(0));
+    let (year, month, day, hour, min, sec) = (num(1), num(2), num(3), num(4), num(5), num(6));
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if !(1..=12).contains(&month) || !(1..=month_days).contains(&day) || hour > 23 || min > 59 || sec > 59 {
+        return Err(format!("invalid date: {}", spec));
+    }
+    let secs = days_from_civil(year as i64, month, day) * 86_400 + (hour * 3_600 + min * 60 + sec) as i64;
+    if secs < 0 {
+        return Err(format!("date before 1970: {}", spec));
+    }
+    Ok(UNIX_EPOCH + Duration::from_secs(secs as u64))
+}
+
+/// Keep paths modified after `cutoff`; returns them with the number skipped as up to date.
+/// A file whose mtime cannot be read is kept, so it is processed rather than silently dropped.
+pub fn filter_modified_since(paths: Vec<PathBuf>, cutoff: std::time::SystemTime) -> (Vec<PathBuf>, usize) {
+    let before = paths.len();
+    let kept: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|p| std::fs::metadata(p).and_then(|m| m.modified()).map(|t| t > cutoff).unwrap_or(true))
+        .collect();
+    let skipped = before - kept.len();
+    (kept, skipped)
+}
+
 fn folder_guidance() -> String {
     // Keep concise, actionable guide per PRD
     let guide = r#"Tidak ada PDF pada pola ./input/**/*.pdf
@@ -91,6 +325,12 @@ pub struct PrdRoot {
     pub datasources: Option<Vec<PrdDatasource>>, // supports new schema
     #[serde(default)]
     pub outputs: Option<PrdOutputs>,
+    #[serde(default)]
+    pub cleanup: Option<PrdCleanup>,
+    /// Directory that relative `datasources[].path` globs are resolved against; itself relative
+    /// to the PRD file. Defaults to the PRD file's directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_dir: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -111,37 +351,109 @@ pub struct PrdOutputs {
     pub artifacts_dir: Option<String>,
 }
 
+/// Extra per-line regexes merged with the built-in header/footer patterns.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrdCleanup {
+    #[serde(default)]
+    pub header_patterns: Vec<String>,
+    #[serde(default)]
+    pub footer_patterns: Vec<String>,
+}
+
 #[derive(Debug, Error)]
 pub enum PrdError {
-    #[error("Failed to read prd.yaml: {0}")]
+    #[error("Failed to read PRD config: {0}")]
     Read(String),
-    #[error("Failed to parse prd.yaml: {0}")]
+    #[error("Failed to parse PRD config: {0}")]
     Parse(String),
     #[error("Invalid PRD: {0}")]
     Invalid(String),
 }
 
+/// Expand `${VAR}` and `$VAR` references from the environment. A `$` not followed by a
+/// variable name is kept literally; an unset variable is an error naming it.
+pub fn expand_env(input: &str) -> Result<String, String> {
+    let re = Regex::new(r"\$(?:\{([A-Za-z_][A-Za-z0-9_]*)\}|([A-Za-z_][A-Za-z0-9_]*))").unwrap();
+    let mut out = String::with_capacity(input.len());
+    let mut last = 0;
+    for cap in re.captures_iter(input) {
+        let whole = cap.get(0).unwrap();
+        let name = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
+        let val = std::env::var(name).map_err(|_| format!("environment variable {} is not set", name))?;
+        out.push_str(&input[last..whole.start()]);
+        out.push_str(&val);
+        last = whole.end();
+    }
+    out.push_str(&input[last..]);
+    Ok(out)
+}
+
+/// Join a relative glob onto `base` (dropping a leading `./`); absolute globs and an empty base
+/// leave it unchanged.
+pub fn resolve_glob(glob: &str, base: &Path) -> String {
+    if base.as_os_str().is_empty() || Path::new(glob).is_absolute() {
+        return glob.to_string();
+    }
+    base.join(glob.strip_prefix("./").unwrap_or(glob)).to_string_lossy().into_owned()
+}
+
 /// Minimal validation for prd.yaml according to provided spec.
+/// Environment references in `datasources[].path`, `outputs.dir` and `outputs.artifacts_dir` are expanded here.
+/// Relative datasource globs are then resolved against `base_dir` (default: the PRD file's
+/// directory), so a run from another working directory finds the same files.
 pub fn validate_prd(prd_path: &Path) -> Result<PrdRoot, PrdError> {
     let raw = std::fs::read_to_string(prd_path).map_err(|e| PrdError::Read(e.to_string()))?;
-    let prd: PrdRoot = serde_yaml::from_str(&raw).map_err(|e| PrdError::Parse(e.to_string()))?;
+    let mut prd: PrdRoot = serde_yaml::from_str(&raw).map_err(|e| PrdError::Parse(e.to_string()))?;
+
+    let expand = |field: &str, v: &mut Option<String>| -> Result<(), PrdError> {
+        if let Some(s) = v.as_mut() {
+            *s = expand_env(s).map_err(|e| PrdError::Invalid(format!("{}: {}", field, e)))?;
+        }
+        Ok(())
+    };
+    for (i, ds) in prd.datasources.iter_mut().flatten().enumerate() {
+        expand(&format!("datasources[{}].path", i), &mut ds.path)?;
+    }
+    if let Some(o) = prd.outputs.as_mut() {
+        expand("outputs.dir", &mut o.dir)?;
+        expand("outputs.artifacts_dir", &mut o.artifacts_dir)?;
+    }
+    expand("base_dir", &mut prd.base_dir)?;
+    let prd_dir = prd_path.parent().unwrap_or(Path::new(""));
+    let base = match prd.base_dir.as_deref() {
+        Some(dir) => PathBuf::from(resolve_glob(dir, prd_dir)),
+        None => prd_dir.to_path_buf(),
+    };
+    for ds in prd.datasources.iter_mut().flatten() {
+        if let Some(path) = ds.path.as_mut().filter(|p| !p.trim().is_empty()) {
+            *path = resolve_glob(path, &base);
+        }
+    }
 
     if prd.id.trim().is_empty() {
         return Err(PrdError::Invalid("missing id".into()));
     }
 
     // Accept either legacy file_patterns (not present in current PRD) or new schema (datasources + outputs)
-    let has_ds_glob = prd
-        .datasources
-        .as_ref()
-        .and_then(|ds| ds.first())
-        .and_then(|d| d.path.clone())
-        .is_some();
+    let datasources = prd.datasources.clone().unwrap_or_default();
     let has_out_dir = prd.outputs.as_ref().and_then(|o| o.dir.clone()).is_some();
-    if !has_ds_glob || !has_out_dir {
+    if datasources.is_empty() || !has_out_dir {
         // Don't fail hard; mark invalid for visibility
         return Err(PrdError::Invalid("missing datasources.path or outputs.dir".into()));
     }
+    // Every datasource must carry a path glob
+    let missing: Vec<String> = datasources
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| d.path.as_deref().map(|p| p.trim().is_empty()).unwrap_or(true))
+        .map(|(i, _)| i.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(PrdError::Invalid(format!("missing datasources.path at index {}", missing.join(", "))));
+    }
+
+    // Custom cleanup patterns must compile
+    prd.cleanup_config()?;
 
     // Ensure tools contain check_deps and enumerate_pdfs
     let tools = prd.tools.clone().unwrap_or_default();
@@ -156,6 +468,28 @@ pub fn validate_prd(prd_path: &Path) -> Result<PrdRoot, PrdError> {
 }
 
 impl PrdRoot {
+    /// Minimal in-memory config for config-free runs: one `cli` datasource and an output dir.
+    pub fn from_cli(input_glob: &str, output_dir: &str) -> PrdRoot {
+        PrdRoot {
+            id: "cli".to_string(),
+            tools: Some(["check_deps", "enumerate_pdfs"].iter().map(|n| PrdTool { name: n.to_string() }).collect()),
+            datasources: Some(vec![PrdDatasource { name: Some("cli".to_string()), path: Some(input_glob.to_string()) }]),
+            outputs: Some(PrdOutputs { dir: Some(output_dir.to_string()), artifacts_dir: None }),
+            cleanup: None,
+            base_dir: None,
+        }
+    }
+    /// Apply CLI overrides: an input glob replaces all datasources, an output dir replaces `outputs.dir`.
+    pub fn apply_overrides(&mut self, input_glob: Option<&str>, output_dir: Option<&str>) {
+        if let Some(glob) = input_glob {
+            self.datasources = Some(vec![PrdDatasource { name: Some("cli".to_string()), path: Some(glob.to_string()) }]);
+        }
+        if let Some(dir) = output_dir {
+            let outputs = self.outputs.get_or_insert(PrdOutputs { dir: None, artifacts_dir: None });
+            outputs.dir = Some(dir.to_string());
+        }
+    }
+    /// Glob of the first datasource (compatibility); see `input_globs` for all of them.
     pub fn input_glob(&self) -> String {
         self.datasources
             .as_ref()
@@ -163,12 +497,148 @@ impl PrdRoot {
             .and_then(|d| d.path.clone())
             .unwrap_or_else(|| "./input/**/*.pdf".to_string())
     }
+    /// Globs of every datasource, in declaration order.
+    pub fn input_globs(&self) -> Vec<String> {
+        self.datasources
+            .as_ref()
+            .map(|ds| ds.iter().filter_map(|d| d.path.clone()).collect::<Vec<_>>())
+            .filter(|globs| !globs.is_empty())
+            .unwrap_or_else(|| vec![self.input_glob()])
+    }
     pub fn output_dir(&self) -> String {
         self.outputs
             .as_ref()
             .and_then(|o| o.dir.clone())
             .unwrap_or_else(|| "./output".to_string())
     }
+    /// Compile `cleanup.header_patterns`/`cleanup.footer_patterns`; an invalid regex is reported with its pattern.
+    pub fn cleanup_config(&self) -> Result<CleanupConfig, PrdError> {
+        let compile = |pats: &[String]| -> Result<Vec<Regex>, PrdError> {
+            pats.iter()
+                .map(|p| Regex::new(p).map_err(|e| PrdError::Invalid(format!("invalid cleanup pattern {:?}: {}", p, e))))
+                .collect()
+        };
+        let c = self.cleanup.clone().unwrap_or_default();
+        Ok(CleanupConfig {
+            header_patterns: compile(&c.header_patterns)?,
+            footer_patterns: compile(&c.footer_patterns)?,
+            ..CleanupConfig::default()
+        })
+    }
+}
+
+/// Maximum length of a slug produced by `slugify`.
+pub const SLUG_MAX_LEN: usize = 80;
+
+fn transliterate(ch: char) -> Option<&'static str> {
+    let s = match ch {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ß' => "ss",
+        'ś' | 'š' | 'ş' => "s",
+        'ť' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(s)
+}
+
+/// Build a file-system friendly doc_id: lowercase ASCII alphanumerics separated by single
+/// dashes. Common accented letters are transliterated (`é` → `e`), everything else becomes a
+/// separator, and the result is capped at `SLUG_MAX_LEN`. Empty input yields `doc`.
+pub fn slugify(base: &str) -> String {
+    let lower = base.to_lowercase();
+    let mut s = String::with_capacity(lower.len());
+    for ch in lower.chars() {
+        if ch.is_ascii_alphanumeric() {
+            s.push(ch);
+        } else if let Some(t) = transliterate(ch) {
+            s.push_str(t);
+        } else {
+            s.push('-');
+        }
+    }
+    let mut collapsed = String::with_capacity(s.len());
+    let mut prev_dash = true; // drops leading dashes
+    for ch in s.chars() {
+        if ch == '-' {
+            if !prev_dash {
+                collapsed.push(ch);
+            }
+            prev_dash = true;
+        } else {
+            prev_dash = false;
+            collapsed.push(ch);
+        }
+    }
+    collapsed.truncate(SLUG_MAX_LEN); // ASCII only, so any index is a char boundary
+    let trimmed = collapsed.trim_end_matches('-');
+    if trimmed.is_empty() {
+        "doc".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DocIdentifier {
+    pub nomor: String,
+    pub tahun: u16,
+}
+
+/// Parse regulation number and year from a `NOMOR-TAHUN.pdf`-style file name.
+/// Handles `13-2003.pdf`, `uu-13-2003.pdf` and `PP_No_5_Tahun_2021.pdf`; returns None rather than guessing.
+pub fn parse_doc_identifier(filename: &str) -> Option<DocIdentifier> {
+    let stem = Path::new(filename).file_stem().and_then(|s| s.to_str()).unwrap_or(filename);
+    let re_named = Regex::new(r"(?i)(?:^|[^a-z])(?:nomor|no)[._ -]*(\d+)[._ -]*tahun[._ -]*(\d{4})(?:\D|$)").unwrap();
+    let re_plain = Regex::new(r"(?:^|\D)(\d+)[-_ ](\d{4})(?:\D|$)").unwrap();
+    let cap = re_named.captures(stem).or_else(|| re_plain.captures(stem))?;
+    let tahun = cap[2].parse::<u16>().ok()?;
+    Some(DocIdentifier { nomor: cap[1].to_string(), tahun })
+}
+
+/// Check a `--name-template`: placeholders must be `{doc_id}`, `{slug}`, `{nomor}` or `{tahun}`,
+/// and the literal text around them may only use `A-Za-z0-9._-` (no leading dot), so every
+/// expansion is a safe file stem.
+pub fn validate_name_template(template: &str) -> Result<(), String> {
+    let re_placeholder = Regex::new(r"\{([^{}]*)\}").unwrap();
+    for cap in re_placeholder.captures_iter(template) {
+        if !matches!(&cap[1], "doc_id" | "slug" | "nomor" | "tahun") {
+            return Err(format!("unknown placeholder {{{}}} (use {{doc_id}}, {{slug}}, {{nomor}}, {{tahun}})", &cap[1]));
+        }
+    }
+    let literal = re_placeholder.replace_all(template, "");
+    if let Some(c) = literal.chars().find(|c| !c.is_ascii_alphanumeric() && !"._-".contains(*c)) {
+        return Err(format!("character {:?} is not allowed in a file name template", c));
+    }
+    if template.is_empty() || template.starts_with('.') {
+        return Err("template must not be empty or start with '.'".to_string());
+    }
+    Ok(())
+}
+
+/// Expand a validated name template for one document. `{doc_id}` and `{slug}` are the
+/// slugified file stem; `{nomor}`/`{tahun}` come from `parse_doc_identifier` and are an error
+/// when the file name carries no number/year.
+pub fn expand_name_template(template: &str, slug: &str, ident: Option<&DocIdentifier>) -> Result<String, String> {
+    let mut out = template.replace("{doc_id}", slug).replace("{slug}", slug);
+    if out.contains("{nomor}") || out.contains("{tahun}") {
+        let id = ident.ok_or_else(|| "file name has no nomor/tahun for {nomor}/{tahun}".to_string())?;
+        out = out.replace("{nomor}", &id.nomor).replace("{tahun}", &id.tahun.to_string());
+    }
+    Ok(out)
 }
 
 /// Render Nala installation help for missing deps.
@@ -184,6 +654,9 @@ pub fn nala_help_for(missing: &[String]) -> String {
         pkgs.push("tesseract-ocr");
         pkgs.push("tesseract-ocr-ind");
     }
+    if missing.iter().any(|m| m == "djvused" || m == "djvutxt" || m == "ddjvu") {
+        pkgs.push("djvulibre-bin");
+    }
 
     if pkgs.is_empty() {
         return String::new();
@@ -201,19 +674,313 @@ pub enum PopplerError {
     FileNotFound(String),
     #[error("EncryptedPDF: {0}")]
     EncryptedPDF(String),
+    /// `pdfinfo` reports `Pages: 0`, or pdftotext produced no page at all.
+    #[error("EmptyDocument: {0}")]
+    EmptyDocument(String),
     #[error("PopplerError: {0}")]
     Other(String),
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    pub layout: bool,
+    pub nopgbrk: bool,
+    /// Re-extract pages whose `-bbox` word layout splits into two columns, left column first.
+    pub two_column: bool,
+    /// Restrict extraction to a 1-based inclusive page range `(first, last)`.
+    pub pages: Option<(usize, usize)>,
+    /// Re-extract near-empty `-layout` pages in raw mode and keep whichever has more text.
+    pub retry_raw: bool,
+}
+
+/// Pages with fewer non-whitespace characters than this are retried without `-layout`.
+pub const RETRY_RAW_MIN_CHARS: usize = 64;
+
+/// Result of `poppler_extract_info`.
+#[derive(Debug, Clone, Default)]
+pub struct Extraction {
+    pub pages: Vec<String>,
+    pub pdf_info: PdfInfo,
+    /// 0-based indices of pages whose raw-mode retry (`retry_raw`) produced more text and was kept.
+    pub raw_fallback_pages: Vec<usize>,
+    /// 0-based indices of pages `pdftotext` failed on; their text is empty so indices stay aligned.
+    pub failed_pages: Vec<usize>,
+    /// Distinct `pdftotext` stderr diagnostics ("Syntax Warning: ..."), byte offsets stripped,
+    /// in first-seen order and capped at `MAX_POPPLER_WARNINGS`.
+    pub poppler_warnings: Vec<String>,
+}
+
+/// Cap on `Extraction::poppler_warnings`, so a badly damaged file cannot bloat its meta.
+pub const MAX_POPPLER_WARNINGS: usize = 20;
+
+/// Add the warning/error lines of a `pdftotext` stderr to `into`, deduplicated and capped.
+fn collect_poppler_warnings(stderr: &[u8], into: &mut Vec<String>) {
+    let offset = Regex::new(r"^((?:Syntax |Internal |Command Line |I/O )?(?:Warning|Error))\s*\(\d+\)").unwrap();
+    for line in String::from_utf8_lossy(stderr).lines() {
+        let line = line.trim();
+        if !(line.contains("Warning") || line.contains("Error")) {
+            continue;
+        }
+        let line = offset.replace(line, "$1").into_owned();
+        if into.len() >= MAX_POPPLER_WARNINGS {
+            return;
+        }
+        if !into.contains(&line) {
+            into.push(line);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BboxWord {
+    pub x_min: f64,
+    pub y_min: f64,
+    pub x_max: f64,
+    pub y_max: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BboxPage {
+    pub width: f64,
+    pub height: f64,
+    pub words: Vec<BboxWord>,
+}
+
+/// Parse the first `<page>` of `pdftotext -bbox` XHTML output.
+pub fn parse_bbox_page(xml: &str) -> Option<BboxPage> {
+    let re_page = Regex::new(r#"<page width="([0-9.]+)" height="([0-9.]+)">"#).unwrap();
+    let re_word = Regex::new(r#"<word xMin="([0-9.]+)" yMin="([0-9.]+)" xMax="([0-9.]+)" yMax="([0-9.]+)">([^<]*)</word>"#).unwrap();
+    let page = re_page.captures(xml)?;
+    let start = page.get(0).unwrap().end();
+    let end = xml[start..].find("</page>").map(|i| start + i).unwrap_or(xml.len());
+    let num = |s: &str| s.parse::<f64>().unwrap_or(0.0);
+    let words = re_word
+        .captures_iter(&xml[start..end])
+        .map(|c| BboxWord { x_min: num(&c[1]), y_min: num(&c[2]), x_max: num(&c[3]), y_max: num(&c[4]), text: c[5].to_string() })
+        .collect();
+    Some(BboxPage { width: num(&page[1]), height: num(&page[2]), words })
+}
+
+/// Detect a two-column page: a vertical gutter (>= 8pt wide, within the middle 35–65% of the
+/// page) that no word box crosses, with at least a quarter of the words on each side.
+/// Returns the gutter's x center, or None for single-column (or too sparse) pages.
+pub fn detect_two_column(page: &BboxPage) -> Option<f64> {
+    const MIN_WORDS: usize = 20;
+    const MIN_GUTTER_PT: f64 = 8.0;
+    if page.words.len() < MIN_WORDS || page.width <= 0.0 {
+        return None;
+    }
+    let lo = (page.width * 0.35).floor() as i64;
+    let hi = (page.width * 0.65).ceil() as i64;
+    let crosses = |x: f64| page.words.iter().any(|w| w.x_min < x && w.x_max > x);
+    // widest run of uncrossed x positions
+    let mut best: Option<(i64, i64)> = None;
+    let mut run_start: Option<i64> = None;
+    for x in lo..=hi + 1 {
+        let free = x <= hi && !crosses(x as f64);
+        match (free, run_start) {
+            (true, None) => run_start = Some(x),
+            (false, Some(st)) => {
+                if best.map(|(a, b)| x - 1 - st > b - a).unwrap_or(true) {
+                    best = Some((st, x - 1));
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    let (a, b) = best?;
+    if ((b - a) as f64) < MIN_GUTTER_PT {
+        return None;
+    }
+    let split = (a + b) as f64 / 2.0;
+    let left = page.words.iter().filter(|w| w.x_max <= split).count();
+    let right = page.words.len() - left;
+    let min_side = page.words.len() / 4;
+    if left < min_side || right < min_side {
+        return None;
+    }
+    Some(split)
+}
+
+/// Re-extract one page as left column then right column using pdftotext's crop area.
+fn extract_two_column_page(path: &Path, page_no: usize, layout: bool) -> Option<String> {
+    let out = Command::new("pdftotext")
+        .arg("-bbox").arg("-q")
+        .arg("-f").arg(page_no.to_string())
+        .arg("-l").arg(page_no.to_string())
+        .arg(path).arg("-")
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let page = parse_bbox_page(&String::from_utf8_lossy(&out.stdout))?;
+    let split = detect_two_column(&page)?;
+    let crop = |x: f64, w: f64| -> Option<String> {
+        let mut cmd = Command::new("pdftotext");
+        if layout {
+            cmd.arg("-layout");
+        }
+        let o = cmd
+            .arg("-q").arg("-nopgbrk")
+            .arg("-f").arg(page_no.to_string())
+            .arg("-l").arg(page_no.to_string())
+            .arg("-x").arg((x.floor() as i64).to_string())
+            .arg("-y").arg("0")
+            .arg("-W").arg((w.ceil() as i64).to_string())
+            .arg("-H").arg((page.height.ceil() as i64).to_string())
+            .arg(path).arg("-")
+            .output()
+            .ok()?;
+        if o.status.success() { Some(String::from_utf8_lossy(&o.stdout).to_string()) } else { None }
+    };
+    let left = crop(0.0, split)?;
+    let right = crop(split, page.width - split)?;
+    Some(format!("{}\n{}", left.trim_end(), right))
+}
+
 /// Extract text pages using Poppler's pdftotext.
 /// Prefers per-page extraction with -layout -nopgbrk when pdfinfo is available for page count.
 /// Falls back to single pass without -nopgbrk and split on form feed when pdfinfo is missing.
 pub fn poppler_extract(path: &Path, layout: bool, nopgbrk: bool) -> Result<Vec<String>, PopplerError> {
+    poppler_extract_with(path, &ExtractOptions { layout, nopgbrk, ..ExtractOptions::default() })
+}
+
+/// `poppler_extract` with extended options (two-column reordering, ...).
+pub fn poppler_extract_with(path: &Path, opts: &ExtractOptions) -> Result<Vec<String>, PopplerError> {
+    poppler_extract_info(path, opts).map(|x| x.pages)
+}
+
+/// Document metadata from `pdfinfo`; fields the PDF does not set are `None` and omitted in JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PdfInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub producer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creation_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mod_date: Option<String>,
+}
+
+/// Parse `pdfinfo` stdout (`Key:   value` lines) into `PdfInfo`; blank values count as absent.
+pub fn parse_pdfinfo(out: &str) -> PdfInfo {
+    let mut info = PdfInfo::default();
+    for line in out.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        let slot = match key.trim() {
+            "Title" => &mut info.title,
+            "Author" => &mut info.author,
+            "Producer" => &mut info.producer,
+            "CreationDate" => &mut info.creation_date,
+            "ModDate" => &mut info.mod_date,
+            _ => continue,
+        };
+        *slot = Some(value.to_string());
+    }
+    info
+}
+
+/// `poppler_extract_with` that also returns the `pdfinfo` metadata it read for the page count
+/// (empty when `pdfinfo` is unavailable) and which pages fell back to raw mode.
+pub fn poppler_extract_info(path: &Path, opts: &ExtractOptions) -> Result<Extraction, PopplerError> {
+    let (layout, nopgbrk) = (opts.layout, opts.nopgbrk);
+    let Extraction { mut pages, pdf_info, failed_pages, poppler_warnings, .. } = poppler_extract_pages(path, layout, nopgbrk, opts.pages)?;
+    let first = opts.pages.map_or(1, |(f, _)| f);
+    if opts.two_column {
+        for (i, page) in pages.iter_mut().enumerate() {
+            if let Some(text) = extract_two_column_page(path, first + i, layout) {
+                *page = text;
+            }
+        }
+    }
+    let mut raw_fallback_pages = Vec::new();
+    if opts.retry_raw && layout {
+        let chars = |t: &str| t.chars().filter(|c| !c.is_whitespace()).count();
+        for (i, page) in pages.iter_mut().enumerate() {
+            let have = chars(page);
+            if have >= RETRY_RAW_MIN_CHARS {
+                continue;
+            }
+            if let Some(raw) = extract_single_page(path, first + i, false, nopgbrk).filter(|raw| chars(raw) > have) {
+                *page = raw;
+                raw_fallback_pages.push(i);
+            }
+        }
+    }
+    let mut failed_pages = failed_pages;
+    failed_pages.retain(|i| !raw_fallback_pages.contains(i));
+    Ok(Extraction { pages, pdf_info, raw_fallback_pages, failed_pages, poppler_warnings })
+}
+
+/// One page (1-based) via `pdftotext -f N -l N`; `None` when pdftotext fails.
+fn extract_single_page(path: &Path, page_no: usize, layout: bool, nopgbrk: bool) -> Option<String> {
+    let mut cmd = Command::new("pdftotext");
+    if layout {
+        cmd.arg("-layout");
+    }
+    if nopgbrk {
+        cmd.arg("-nopgbrk");
+    }
+    let out = cmd
+        .arg("-q")
+        .arg("-f").arg(page_no.to_string())
+        .arg("-l").arg(page_no.to_string())
+        .arg(path)
+        .arg("-")
+        .output()
+        .ok()?;
+    out.status.success().then(|| String::from_utf8_lossy(&out.stdout).to_string())
+}
+
+/// Classify a finished `pdftotext` run. pdftotext prints "Syntax Warning/Error" lines on stderr
+/// for many readable PDFs and may exit non-zero after still writing the text, so only a failed
+/// status with no text on stdout is an error (encrypted when stderr says so).
+fn pdftotext_failure(path: &Path, out: &std::process::Output, msg: impl FnOnce() -> String) -> Option<PopplerError> {
+    let has_text = out.stdout.iter().any(|b| !b.is_ascii_whitespace() && *b != 0x0C);
+    if out.status.success() || has_text {
+        return None;
+    }
+    let err = String::from_utf8_lossy(&out.stderr).to_lowercase();
+    if err.contains("encrypt") || err.contains("password") {
+        return Some(PopplerError::EncryptedPDF(path.display().to_string()));
+    }
+    Some(PopplerError::Other(msg()))
+}
+
+/// Validate a 1-based inclusive page range against the document's page count.
+fn check_page_range(range: (usize, usize), n_pages: usize) -> Result<(), PopplerError> {
+    let (first, last) = range;
+    if first == 0 || first > last || last > n_pages {
+        return Err(PopplerError::Other(format!(
+            "page range {}-{} outside document ({} pages)",
+            first, last, n_pages
+        )));
+    }
+    Ok(())
+}
+
+fn poppler_extract_pages(
+    path: &Path,
+    layout: bool,
+    nopgbrk: bool,
+    range: Option<(usize, usize)>,
+) -> Result<Extraction, PopplerError> {
     if !path.exists() {
         return Err(PopplerError::FileNotFound(path.display().to_string()));
     }
 
     let use_pdfinfo = which::which("pdfinfo").is_ok();
+    let mut info = PdfInfo::default();
     let pages_count = if use_pdfinfo {
         match Command::new("pdfinfo").arg(path).output() {
             Ok(out) => {
@@ -225,6 +992,7 @@ pub fn poppler_extract(path: &Path, layout: bool, nopgbrk: bool) -> Result<Vec<S
                     None
                 } else {
                     let s = String::from_utf8_lossy(&out.stdout);
+                    info = parse_pdfinfo(&s);
                     let mut pages: Option<usize> = None;
                     for line in s.lines() {
                         if let Some(rest) = line.strip_prefix("Pages:") {
@@ -241,10 +1009,25 @@ pub fn poppler_extract(path: &Path, layout: bool, nopgbrk: bool) -> Result<Vec<S
         None
     };
 
+    if pages_count == Some(0) {
+        return Err(PopplerError::EmptyDocument(path.display().to_string()));
+    }
     if let Some(n_pages) = pages_count {
         // Per-page extraction using -f i -l i
-        let mut pages: Vec<String> = Vec::with_capacity(n_pages);
-        for i in 1..=n_pages {
+        let (first, last) = match range {
+            Some(r) => {
+                check_page_range(r, n_pages)?;
+                r
+            }
+            None => (1, n_pages),
+        };
+        let mut pages: Vec<String> = Vec::with_capacity(last + 1 - first);
+        // A page pdftotext fails on becomes empty (and OCR-suspect); only encryption, or every
+        // page failing, fails the document
+        let mut failed: Vec<usize> = Vec::new();
+        let mut first_err: Option<PopplerError> = None;
+        let mut warnings: Vec<String> = Vec::new();
+        for i in first..=last {
             let mut cmd = Command::new("pdftotext");
             if layout {
                 cmd.arg("-layout");
@@ -252,24 +1035,30 @@ pub fn poppler_extract(path: &Path, layout: bool, nopgbrk: bool) -> Result<Vec<S
             if nopgbrk {
                 cmd.arg("-nopgbrk");
             }
-            cmd.arg("-q");
+            // no -q: stderr diagnostics are kept as `poppler_warnings`
             cmd.arg("-f").arg(i.to_string());
             cmd.arg("-l").arg(i.to_string());
             cmd.arg(path);
             cmd.arg("-"); // write to stdout
 
             let out = cmd.output().map_err(|e| PopplerError::Other(e.to_string()))?;
-            if !out.status.success() {
-                let err = String::from_utf8_lossy(&out.stderr).to_lowercase();
-                if err.contains("encrypt") || err.contains("password") {
-                    return Err(PopplerError::EncryptedPDF(path.display().to_string()));
+            collect_poppler_warnings(&out.stderr, &mut warnings);
+            match pdftotext_failure(path, &out, || format!("pdftotext failed on page {}", i)) {
+                Some(err @ PopplerError::EncryptedPDF(_)) => return Err(err),
+                Some(err) => {
+                    failed.push(i - first);
+                    first_err.get_or_insert(err);
+                    pages.push(String::new());
                 }
-                return Err(PopplerError::Other(format!("pdftotext failed on page {}", i)));
+                None => pages.push(String::from_utf8_lossy(&out.stdout).to_string()),
+            }
+        }
+        if failed.len() == pages.len() {
+            if let Some(err) = first_err {
+                return Err(err);
             }
-            let text = String::from_utf8_lossy(&out.stdout).to_string();
-            pages.push(text);
         }
-        Ok(pages)
+        Ok(Extraction { pages, pdf_info: info, failed_pages: failed, poppler_warnings: warnings, ..Extraction::default() })
     } else {
         // Fallback: single pass, split by form feed (\x0c), do not use -nopgbrk so page breaks exist
         let mut cmd = Command::new("pdftotext");
@@ -277,25 +1066,73 @@ pub fn poppler_extract(path: &Path, layout: bool, nopgbrk: bool) -> Result<Vec<S
             cmd.arg("-layout");
         }
         // Intentionally not adding -nopgbrk so we can split by page breaks
-        cmd.arg("-q");
         cmd.arg(path);
         cmd.arg("-");
         let out = cmd.output().map_err(|e| PopplerError::Other(e.to_string()))?;
-        if !out.status.success() {
-            let err = String::from_utf8_lossy(&out.stderr).to_lowercase();
-            if err.contains("encrypt") || err.contains("password") {
-                return Err(PopplerError::EncryptedPDF(path.display().to_string()));
-            }
-            return Err(PopplerError::Other("pdftotext failed".into()));
+        let mut warnings: Vec<String> = Vec::new();
+        collect_poppler_warnings(&out.stderr, &mut warnings);
+        if let Some(err) = pdftotext_failure(path, &out, || "pdftotext failed".to_string()) {
+            return Err(err);
+        }
+        // every page, even a blank one, ends with \x0c: no output at all means no pages
+        if out.stdout.is_empty() {
+            return Err(PopplerError::EmptyDocument(path.display().to_string()));
         }
         let s = String::from_utf8_lossy(&out.stdout);
         let mut pages: Vec<String> = s.split('\u{000C}').map(|x| x.to_string()).collect();
-        // drop trailing empty page if any
-        while matches!(pages.last(), Some(last) if last.trim().is_empty()) {
+        // pdftotext terminates every page with \x0c, so only the segment after the final
+        // form feed is an artifact; interior empty pages are kept so indices match the PDF.
+        if pages.len() > 1 && matches!(pages.last(), Some(last) if last.trim().is_empty()) {
             pages.pop();
         }
-        Ok(pages)
+        if let Some((first, last)) = range {
+            check_page_range((first, last), pages.len())?;
+            pages = pages.drain(first - 1..last).collect();
+        }
+        Ok(Extraction { pages, pdf_info: info, poppler_warnings: warnings, ..Extraction::default() })
+    }
+}
+
+/// Extract text pages from a DjVu file's hidden text layer with `djvutxt`, which separates pages
+/// with form feeds like pdftotext. The page count comes from `djvused -e n`, so pages without
+/// hidden text (or a file with no text layer at all) come back empty and are routed to OCR.
+/// Errors reuse `PopplerError`; `pdf_info` stays empty.
+pub fn djvu_extract(path: &Path, range: Option<(usize, usize)>) -> Result<Extraction, PopplerError> {
+    if !path.exists() {
+        return Err(PopplerError::FileNotFound(path.display().to_string()));
+    }
+    let run = |bin: &str, args: &[&str]| -> Result<Vec<u8>, PopplerError> {
+        let out = Command::new(bin)
+            .args(args)
+            .arg(path)
+            .output()
+            .map_err(|e| PopplerError::Other(format!("{}: {}", bin, e)))?;
+        if !out.status.success() {
+            return Err(PopplerError::Other(format!("{} failed: {}", bin, String::from_utf8_lossy(&out.stderr).trim())));
+        }
+        Ok(out.stdout)
+    };
+    let count = run("djvused", &["-e", "n"])?;
+    let page_count: usize = String::from_utf8_lossy(&count)
+        .trim()
+        .parse()
+        .map_err(|_| PopplerError::Other(format!("djvused: unexpected page count {:?}", String::from_utf8_lossy(&count).trim())))?;
+    if page_count == 0 {
+        return Err(PopplerError::EmptyDocument(path.display().to_string()));
+    }
+    let text = run("djvutxt", &[])?;
+    let mut pages: Vec<String> = if text.is_empty() {
+        Vec::new()
+    } else {
+        String::from_utf8_lossy(&text).split('\u{000C}').map(|x| x.to_string()).collect()
+    };
+    // the trailing form feed leaves one extra empty piece; missing text layers leave none
+    pages.resize(page_count, String::new());
+    if let Some((first, last)) = range {
+        check_page_range((first, last), pages.len())?;
+        pages = pages.drain(first - 1..last).collect();
     }
+    Ok(Extraction { pages, ..Extraction::default() })
 }
 
 /// Return 0-based indices of pages whose non-whitespace characters are less than min_chars.
@@ -310,10 +1147,160 @@ pub fn detect_suspect_pages(pages: &[String], min_chars: usize) -> Vec<usize> {
     out
 }
 
+/// Page size assumed by `detect_suspect_pages_by_density` when a page's size is unknown (A4).
+pub const DEFAULT_PAGE_SIZE_PTS: (f64, f64) = (595.0, 842.0);
+
+/// Like `detect_suspect_pages`, but relative to page area: flags pages with fewer than
+/// `min_chars_per_sq_inch` non-whitespace characters per square inch. `sizes_pts` holds each
+/// page's `(width, height)` in points (see `page_sizes_pts`); missing entries count as A4.
+pub fn detect_suspect_pages_by_density(pages: &[String], sizes_pts: &[Option<(f64, f64)>], min_chars_per_sq_inch: f64) -> Vec<usize> {
+    let mut out = Vec::new();
+    for (idx, page) in pages.iter().enumerate() {
+        let (w, h) = sizes_pts.get(idx).copied().flatten().filter(|(w, h)| *w > 0.0 && *h > 0.0).unwrap_or(DEFAULT_PAGE_SIZE_PTS);
+        let sq_inches = (w / 72.0) * (h / 72.0);
+        let count = page.chars().filter(|c| !c.is_whitespace()).count();
+        if (count as f64) / sq_inches < min_chars_per_sq_inch {
+            out.push(idx);
+        }
+    }
+    out
+}
+
+/// Per-page entry of the meta `pages` array, for spotting the pages that drag coverage down.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PageReport {
+    /// 0-based index into the extracted pages (same numbering as `suspect_pages`).
+    pub index: usize,
+    /// Non-whitespace characters of the page text that went into cleanup (OCR text when OCR ran).
+    pub chars: usize,
+    pub suspect: bool,
+    pub ocr_ran: bool,
+    /// Mean tesseract word confidence (0–100) for pages where OCR ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_confidence: Option<f64>,
+}
+
+/// One `PageReport` per page of `pages`; `confidence` is `OcrOutcome::page_confidence`.
+pub fn page_reports(pages: &[String], suspects: &[usize], ocr_run_pages: &[usize], confidence: &[OcrPageConfidence]) -> Vec<PageReport> {
+    pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| PageReport {
+            index,
+            chars: page.chars().filter(|c| !c.is_whitespace()).count(),
+            suspect: suspects.contains(&index),
+            ocr_ran: ocr_run_pages.contains(&index),
+            ocr_confidence: confidence
+                .iter()
+                .find(|c| c.index == index && ocr_run_pages.contains(&index))
+                .map(|c| c.confidence),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PdfKind {
+    TextBased,
+    /// Image-only: (almost) every page lacks a text layer.
+    Scanned,
+    Mixed,
+}
+
+/// Classify a document by its fraction of low-text pages (same 64-char cutoff as the
+/// suspect-page check): >= 90% is `Scanned`, <= 10% is `TextBased`, anything between `Mixed`.
+/// A document with no pages counts as scanned.
+pub fn classify_pdf(pages: &[String]) -> PdfKind {
+    if pages.is_empty() {
+        return PdfKind::Scanned;
+    }
+    let ratio = detect_suspect_pages(pages, 64).len() as f64 / pages.len() as f64;
+    if ratio >= 0.9 {
+        PdfKind::Scanned
+    } else if ratio <= 0.1 {
+        PdfKind::TextBased
+    } else {
+        PdfKind::Mixed
+    }
+}
+
+/// Pre-flight verdict for one input (`--validate-only`): extraction, classification and
+/// suspect pages only, no cleanup, OCR or emit.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PdfVerdict {
+    /// `None` when the file could not be read.
+    pub kind: Option<PdfKind>,
+    pub page_count: usize,
+    pub suspect_count: usize,
+    pub encrypted: bool,
+    /// Scanned, or has suspect pages the conversion would OCR.
+    pub needs_ocr: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PdfVerdict {
+    pub fn readable(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Verdict for an extraction result (`poppler_extract_info` or `djvu_extract`). Encrypted and
+/// zero-page documents are unreadable.
+pub fn pdf_verdict(extracted: &Result<Extraction, PopplerError>) -> PdfVerdict {
+    match extracted {
+        Ok(x) => {
+            let kind = classify_pdf(&x.pages);
+            let suspect_count = detect_suspect_pages(&x.pages, 64).len();
+            PdfVerdict {
+                kind: Some(kind),
+                page_count: x.pages.len(),
+                suspect_count,
+                encrypted: false,
+                needs_ocr: kind == PdfKind::Scanned || suspect_count > 0,
+                error: None,
+            }
+        }
+        Err(e) => PdfVerdict {
+            kind: None,
+            page_count: 0,
+            suspect_count: 0,
+            encrypted: matches!(e, PopplerError::EncryptedPDF(_)),
+            needs_ocr: false,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SuppressorConfig {
     pub threshold_ratio: f64,               // e.g., 0.60
     pub keep_lines: Option<Regex>,
+    pub header_patterns: Vec<Regex>,        // extra strong header patterns (PRD cleanup)
+    pub footer_patterns: Vec<Regex>,        // extra strong footer patterns (PRD cleanup)
+    pub max_removals_per_page: usize,       // beyond this, lines are kept and counted as overrun
+    pub header_lines: usize,                // top-N lines of a page count as header position
+    pub footer_lines: usize,                // bottom-N lines of a page count as footer position
+    pub watermark_ratio: Option<f64>,       // short all-caps lines on this fraction of pages, any position
+    pub watermark_whitelist: Option<Regex>, // never treated as watermark (legitimate short headings)
+    pub whitelist: Option<Regex>,           // OR'd with the built-in structural whitelist (BAB, Pasal, ...)
+}
+
+impl Default for SuppressorConfig {
+    fn default() -> Self {
+        SuppressorConfig {
+            threshold_ratio: 0.60,
+            keep_lines: None,
+            header_patterns: Vec::new(),
+            footer_patterns: Vec::new(),
+            max_removals_per_page: 5,
+            header_lines: 1,
+            footer_lines: 1,
+            watermark_ratio: None,
+            watermark_whitelist: None,
+            whitelist: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -322,9 +1309,16 @@ pub struct SuppressorStats {
     pub suppressor_overrun: usize,
     pub removed_header: usize,
     pub removed_footer: usize,
+    /// Repeated 2–3 line blocks (e.g. letterhead + address) removed as a unit.
+    #[serde(default)]
+    pub removed_blocks: usize,
+    /// Watermark lines ("SALINAN", agency stamps) removed regardless of position.
+    #[serde(default)]
+    pub removed_watermark: usize,
 }
 
 /// Suppress repeated headers/footers and page numbers conservatively before cleanup.
+/// Repeated 2–3 line blocks at the top/bottom of pages are removed first, then single lines.
 /// Returns new pages and stats.
 pub fn suppress_repeated_lines(pages: &[String], cfg: &SuppressorConfig) -> (Vec<String>, SuppressorStats, Vec<String>) {
     let page_count = pages.len().max(1);
@@ -336,32 +1330,97 @@ pub fn suppress_repeated_lines(pages: &[String], cfg: &SuppressorConfig) -> (Vec
     let re_head1 = Regex::new(r"(?mi)^\s*PRESIDEN\s+REPUBLIK\s+INDONESIA\s*$").unwrap();
     let re_head2 = Regex::new(r"(?mi)^\s*KEMENTERIAN\s+KETENAGAKERJAAN\s*(RI)?\s*$").unwrap();
     let re_head3 = Regex::new(r"(?mi)^\s*(TAMBAHAN\s+)?LEMBARAN\s+NEGARA\s+REPUBLIK\s+INDONESIA.*$").unwrap();
-    let re_whitelist = Regex::new(r"(?i)^(BAB\s+[IVXLCDM]|Pasal\s+\d+|Menimbang:?|Mengingat:?|PENJELASAN)\b").unwrap();
+    let re_whitelist = Regex::new(r"(?i)^(BAB\s+[IVXLCDM]|Pasal\s+\d+[A-Z]?|Menimbang:?|Mengingat:?|PENJELASAN)\b").unwrap();
+    let re_ws = Regex::new(r"\s+").unwrap();
+    let whitelisted = |l: &str| re_whitelist.is_match(l) || cfg.whitelist.as_ref().map(|re| re.is_match(l)).unwrap_or(false);
 
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
     let mut freq: HashMap<String, usize> = HashMap::new();
     let mut top: HashMap<String, usize> = HashMap::new();
     let mut bottom: HashMap<String, usize> = HashMap::new();
 
-    for (pi, page) in pages.iter().enumerate() {
+    for page in pages.iter() {
         let lines: Vec<&str> = page.lines().collect();
         for (li, raw) in lines.iter().enumerate() {
-            let mut line = raw.trim();
+            let line = raw.trim();
             if line.is_empty() { continue; }
-            if re_whitelist.is_match(line) { continue; }
+            if whitelisted(line) { continue; }
             // Normalize spaces
-            let norm = Regex::new(r"\s+").unwrap().replace_all(line, " ").to_string();
+            let norm = re_ws.replace_all(line, " ").to_string();
             *freq.entry(norm.clone()).or_insert(0) += 1;
-            if li == 0 { *top.entry(norm.clone()).or_insert(0) += 1; }
-            if li + 1 == lines.len() { *bottom.entry(norm.clone()).or_insert(0) += 1; }
+            if li < cfg.header_lines { *top.entry(norm.clone()).or_insert(0) += 1; }
+            if li + cfg.footer_lines >= lines.len() { *bottom.entry(norm.clone()).or_insert(0) += 1; }
         }
     }
 
-    let mut to_remove_repeated: HashMap<String, ()> = HashMap::new();
+    // Block candidates: the first/last 2–3 non-empty lines of each page, counted once per page.
+    const BLOCK_SIZES: [usize; 2] = [3, 2];
+    let non_empty = |page: &str| -> Vec<(usize, String)> {
+        page.lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| (i, re_ws.replace_all(l.trim(), " ").to_string()))
+            .collect()
+    };
+    let guarded = |block: &[String]| {
+        block.iter().any(|l| whitelisted(l) || cfg.keep_lines.as_ref().map(|re| re.is_match(l)).unwrap_or(false))
+    };
+    let mut top_blocks: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut bottom_blocks: HashMap<Vec<String>, usize> = HashMap::new();
+    for page in pages.iter() {
+        let lines = non_empty(page);
+        for n in BLOCK_SIZES {
+            if lines.len() <= n {
+                continue;
+            }
+            let head: Vec<String> = lines[..n].iter().map(|(_, l)| l.clone()).collect();
+            let tail: Vec<String> = lines[lines.len() - n..].iter().map(|(_, l)| l.clone()).collect();
+            *top_blocks.entry(head).or_insert(0) += 1;
+            *bottom_blocks.entry(tail).or_insert(0) += 1;
+        }
+    }
+    let repeated_block = |map: &HashMap<Vec<String>, usize>, block: &[String]| {
+        pages.len() > 1 && map.get(block).copied().unwrap_or(0) >= threshold && !guarded(block)
+    };
+
+    let mut block_drop: Vec<HashSet<usize>> = vec![HashSet::new(); pages.len()];
+    let mut block_lines: HashSet<String> = HashSet::new();
+    let mut removed_blocks = 0usize;
+    for (pi, page) in pages.iter().enumerate() {
+        let lines = non_empty(page);
+        let mut top_taken = 0usize;
+        for n in BLOCK_SIZES {
+            if lines.len() <= n {
+                continue;
+            }
+            let head: Vec<String> = lines[..n].iter().map(|(_, l)| l.clone()).collect();
+            if repeated_block(&top_blocks, &head) {
+                block_drop[pi].extend(lines[..n].iter().map(|(i, _)| *i));
+                block_lines.extend(head);
+                removed_blocks += 1;
+                top_taken = n;
+                break;
+            }
+        }
+        for n in BLOCK_SIZES {
+            if lines.len() <= n + top_taken {
+                continue;
+            }
+            let tail: Vec<String> = lines[lines.len() - n..].iter().map(|(_, l)| l.clone()).collect();
+            if repeated_block(&bottom_blocks, &tail) {
+                block_drop[pi].extend(lines[lines.len() - n..].iter().map(|(i, _)| *i));
+                block_lines.extend(tail);
+                removed_blocks += 1;
+                break;
+            }
+        }
+    }
+
+    let mut to_remove_repeated: HashMap<String, ()> = HashMap::new();
     for (line, &c) in freq.iter() {
         if c >= threshold {
             let len = line.len();
-            if len >= 3 && len <= 120 && !re_whitelist.is_match(line) {
+            if (3..=120).contains(&len) && !whitelisted(line) {
                 let t = *top.get(line).unwrap_or(&0);
                 let b = *bottom.get(line).unwrap_or(&0);
                 if t * 2 >= c || b * 2 >= c { // position heuristic
@@ -371,28 +1430,60 @@ pub fn suppress_repeated_lines(pages: &[String], cfg: &SuppressorConfig) -> (Vec
         }
     }
 
-    let mut stats = SuppressorStats::default();
+    // Watermarks: 2–3 word all-caps lines on enough pages, wherever they land on the page
+    let re_watermark = Regex::new(r"^\p{Lu}+(?: \p{Lu}+){1,2}$").unwrap();
+    let mut watermarks: HashSet<String> = HashSet::new();
+    if let Some(ratio) = cfg.watermark_ratio.filter(|_| pages.len() > 1) {
+        let wm_threshold = ((ratio * page_count as f64).ceil() as usize).max(2);
+        let mut pages_with: HashMap<String, usize> = HashMap::new();
+        for page in pages.iter() {
+            let on_page: HashSet<String> = page.lines().map(|l| re_ws.replace_all(l.trim(), " ").to_string()).filter(|l| re_watermark.is_match(l)).collect();
+            for l in on_page {
+                *pages_with.entry(l).or_insert(0) += 1;
+            }
+        }
+        watermarks = pages_with
+            .into_iter()
+            .filter(|(l, c)| {
+                *c >= wm_threshold
+                    && !whitelisted(l)
+                    && !cfg.watermark_whitelist.as_ref().map(|re| re.is_match(l)).unwrap_or(false)
+            })
+            .map(|(l, _)| l)
+            .collect();
+    }
+
+    let mut stats = SuppressorStats { removed_blocks, ..SuppressorStats::default() };
     let mut removed_samples: Vec<String> = Vec::new();
     let mut new_pages: Vec<String> = Vec::with_capacity(pages.len());
 
-    for page in pages.iter() {
+    for (pi, page) in pages.iter().enumerate() {
         let mut removed_this_page = 0usize;
         let mut kept: Vec<String> = Vec::new();
-        for raw in page.lines() {
+        for (li, raw) in page.lines().enumerate() {
             let line = raw.trim_end();
+            if block_drop[pi].contains(&li) {
+                if removed_samples.len() < 5 {
+                    removed_samples.push(line.trim().to_string());
+                }
+                continue;
+            }
             let mut drop = false;
             // strong patterns
-            if re_head1.is_match(line) || re_head2.is_match(line) || re_head3.is_match(line) {
+            if re_head1.is_match(line) || re_head2.is_match(line) || re_head3.is_match(line) || cfg.header_patterns.iter().any(|re| re.is_match(line)) {
                 drop = true; stats.removed_header += 1;
-            } else if re_num_dash.is_match(line) || re_hal.is_match(line) {
+            } else if re_num_dash.is_match(line) || re_hal.is_match(line) || cfg.footer_patterns.iter().any(|re| re.is_match(line)) {
                 drop = true; stats.removed_footer += 1;
             } else if re_plain_num.is_match(line) {
                 // only if frequent and appears in repeated list
-                let norm = Regex::new(r"\s+").unwrap().replace_all(line.trim(), " ").to_string();
+                let norm = re_ws.replace_all(line.trim(), " ").to_string();
                 if to_remove_repeated.contains_key(&norm) { drop = true; stats.removed_footer += 1; }
             } else {
-                let norm = Regex::new(r"\s+").unwrap().replace_all(line.trim(), " ").to_string();
+                let norm = re_ws.replace_all(line.trim(), " ").to_string();
                 if to_remove_repeated.contains_key(&norm) { drop = true; }
+                else if watermarks.contains(&norm) && !cfg.keep_lines.as_ref().map(|re| re.is_match(line)).unwrap_or(false) {
+                    drop = true; stats.removed_watermark += 1;
+                }
             }
             if drop {
                 if let Some(re) = &cfg.keep_lines {
@@ -404,7 +1495,7 @@ pub fn suppress_repeated_lines(pages: &[String], cfg: &SuppressorConfig) -> (Vec
                 if removed_samples.len() < 5 {
                     removed_samples.push(line.trim().to_string());
                 }
-                if removed_this_page > 5 {
+                if removed_this_page > cfg.max_removals_per_page {
                     stats.suppressor_overrun += 1;
                     kept.push(line.to_string()); // stop dropping too many; keep rest
                 }
@@ -416,7 +1507,198 @@ pub fn suppress_repeated_lines(pages: &[String], cfg: &SuppressorConfig) -> (Vec
     }
 
     stats.removed_lines_sample = removed_samples;
-    (new_pages, stats, to_remove_repeated.keys().cloned().collect())
+    let mut candidates: Vec<String> = to_remove_repeated.into_keys().collect();
+    candidates.extend(block_lines.into_iter().filter(|l| !candidates.contains(l)).collect::<Vec<_>>());
+    candidates.extend(watermarks.into_iter().filter(|l| !candidates.contains(l)).collect::<Vec<_>>());
+    (new_pages, stats, candidates)
+}
+
+/// A footnote lifted out of a page's bottom notes region.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Footnote {
+    /// Document-wide sequential label used in `[^label]`.
+    pub label: usize,
+    /// Marker as printed in the PDF (`1`, `¹`, `*`, ...).
+    pub marker: String,
+    /// 0-based page index.
+    pub page: usize,
+    pub text: String,
+}
+
+/// Lift page-bottom footnotes out of `pages`, before header/footer suppression can eat them.
+/// A notes region is one or more paragraphs at the bottom of a page (below the body, after a
+/// blank or `_____` rule line) whose first line starts with a marker (`1 `, `¹`, `*`, `†`)
+/// followed by text; trailing page numbers stay where they are. The first matching marker in the
+/// page body becomes `[^N]`, with N counted across the document. Returns the new pages and the
+/// notes, for `render_footnotes`.
+pub fn extract_footnotes(pages: &[String]) -> (Vec<String>, Vec<Footnote>) {
+    let note_re = Regex::new(r"^\s*(\d{1,3}|[¹²³⁴⁵⁶⁷⁸⁹⁰]+|\*{1,3}|[†‡])\s+(\S.*)$").unwrap();
+    let rule_re = Regex::new(r"^\s*[_\-–—]{5,}\s*$").unwrap();
+    let page_no_re = Regex::new(r"(?i)^\s*(?:-\s*\d{1,4}\s*-|\d{1,4}|Hal(?:\.|aman)\s*\d+)\s*$").unwrap();
+    let blank = |l: &str| l.trim().is_empty();
+    let mut notes: Vec<Footnote> = Vec::new();
+    let mut out = Vec::with_capacity(pages.len());
+    for (pi, page) in pages.iter().enumerate() {
+        let lines: Vec<&str> = page.lines().collect();
+        let mut end = lines.len();
+        while end > 0 && (blank(lines[end - 1]) || page_no_re.is_match(lines[end - 1])) {
+            end -= 1;
+        }
+        // grow the region upwards one paragraph at a time while paragraphs open with a marker
+        let mut start = end;
+        loop {
+            let mut p_end = start;
+            while p_end > 0 && blank(lines[p_end - 1]) {
+                p_end -= 1;
+            }
+            let mut p_start = p_end;
+            while p_start > 0 && !blank(lines[p_start - 1]) && !rule_re.is_match(lines[p_start - 1]) {
+                p_start -= 1;
+            }
+            if p_start == p_end || p_start == 0 || !note_re.is_match(lines[p_start]) {
+                break;
+            }
+            start = p_start;
+        }
+        let mut body_end = start;
+        while body_end > 0 && (blank(lines[body_end - 1]) || rule_re.is_match(lines[body_end - 1])) {
+            body_end -= 1;
+        }
+        if start == end || body_end == 0 {
+            out.push(page.clone());
+            continue;
+        }
+        let mut page_notes: Vec<(String, String)> = Vec::new();
+        for line in &lines[start..end] {
+            if let Some(c) = note_re.captures(line) {
+                page_notes.push((c[1].to_string(), c[2].trim().to_string()));
+            } else if let Some(last) = page_notes.last_mut().filter(|_| !blank(line)) {
+                last.1.push(' ');
+                last.1.push_str(line.trim());
+            }
+        }
+        let mut body = lines[..body_end].join("\n");
+        for (marker, text) in page_notes {
+            let label = notes.len() + 1;
+            let marker_re = if marker.chars().all(|c| c.is_ascii_digit()) {
+                // a digit glued to the end of a word or closing punctuation: "pekerja1", "(2003).1"
+                Regex::new(&format!(r"([\p{{L}}\)\.,;:”]){}([\s\.,;:)]|$)", marker)).unwrap()
+            } else {
+                Regex::new(&format!(r"(\S){}([^*]|$)", regex::escape(&marker))).unwrap()
+            };
+            body = marker_re.replacen(&body, 1, format!("${{1}}[^{}]${{2}}", label)).into_owned();
+            notes.push(Footnote { label, marker, page: pi, text });
+        }
+        let tail: Vec<&str> = lines[end..].to_vec();
+        if !tail.is_empty() {
+            body.push('\n');
+            body.push_str(&tail.join("\n"));
+        }
+        out.push(body);
+    }
+    (out, notes)
+}
+
+/// Markdown footnote definitions (`[^1]: text`), blank-line separated so a cleanup pass never
+/// joins them; empty for no notes.
+pub fn render_footnotes(notes: &[Footnote]) -> String {
+    notes.iter().map(|n| format!("[^{}]: {}", n.label, n.text)).collect::<Vec<_>>().join("\n\n")
+}
+
+/// Remove a table-of-contents block: a "DAFTAR ISI" header followed by dotted-leader
+/// entries ("BAB I .......... 3"). Removal stops at the first non-TOC line (blank lines are
+/// tolerated, and the block may continue onto the next page); a bare `Pasal N` line always
+/// ends the block. Returns new pages and the number of removed lines.
+pub fn strip_toc(pages: &[String]) -> (Vec<String>, usize) {
+    let re_toc_head = Regex::new(r"(?i)^\s*DAFTAR\s+ISI\s*:?\s*$").unwrap();
+    let re_leader = Regex::new(r"^.*(?:\.{3,}|…+)\s*\d+\s*$").unwrap();
+    let re_pasal = Regex::new(r"(?i)^\s*Pasal\s+\d+[A-Z]?\s*$").unwrap();
+
+    let mut removed = 0usize;
+    let mut in_toc = false;
+    let mut out: Vec<String> = Vec::with_capacity(pages.len());
+    for page in pages {
+        let mut kept: Vec<&str> = Vec::new();
+        for line in page.lines() {
+            if in_toc {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if re_leader.is_match(line) && !re_pasal.is_match(line) {
+                    removed += 1;
+                    continue;
+                }
+                in_toc = false;
+            } else if re_toc_head.is_match(line) {
+                in_toc = true;
+                removed += 1;
+                continue;
+            }
+            kept.push(line);
+        }
+        out.push(if kept.len() == page.lines().count() { page.clone() } else { kept.join("\n") });
+    }
+    (out, removed)
+}
+
+/// A token that does not read as a word: symbol-dense (a third or more of it outside letters,
+/// digits and ordinary punctuation), a 5+ letter run without vowels, or 4+ switches between
+/// lowercase and uppercase/digits ("hQ7zKp2W"). URLs and e-mail addresses never count.
+fn is_gibberish_token(t: &str) -> bool {
+    let email = t.split_once('@').is_some_and(|(user, host)| {
+        !user.is_empty() && host.contains('.') && t.chars().all(|c| c.is_alphanumeric() || "@.-_+".contains(c))
+    });
+    if t.contains("://") || t.starts_with("www.") || email {
+        return false;
+    }
+    let chars: Vec<char> = t.chars().collect();
+    if chars.len() < 3 {
+        return false;
+    }
+    let symbols = chars.iter().filter(|c| !c.is_alphanumeric() && !".,;:()-'\"/".contains(**c)).count();
+    if symbols * 3 >= chars.len() {
+        return true;
+    }
+    let letters: Vec<char> = chars.iter().copied().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() >= 5 && !letters.iter().any(|c| "aiueoAIUEO".contains(*c)) {
+        return true;
+    }
+    let switches = chars
+        .windows(2)
+        .filter(|w| w[0].is_alphanumeric() && w[1].is_alphanumeric() && w[0].is_lowercase() != w[1].is_lowercase())
+        .count();
+    switches >= 4
+}
+
+/// Whether a line looks like OCR noise from a QR code or barcode box: at least 12 visible
+/// characters, 70% of which sit in gibberish tokens. Ordinary legal prose keeps well below that
+/// ratio, so short glitches inside a sentence never remove it.
+pub fn is_gibberish_line(line: &str) -> bool {
+    let visible = line.chars().filter(|c| !c.is_whitespace()).count();
+    if visible < 12 {
+        return false;
+    }
+    let garbage: usize = line.split_whitespace().filter(|t| is_gibberish_token(t)).map(|t| t.chars().count()).sum();
+    garbage * 10 >= visible * 7
+}
+
+/// Drop gibberish lines (see `is_gibberish_line`) from every page. Returns new pages and the
+/// number of removed lines.
+pub fn strip_gibberish(pages: &[String]) -> (Vec<String>, usize) {
+    let mut removed = 0usize;
+    let out = pages
+        .iter()
+        .map(|page| {
+            let kept: Vec<&str> = page.lines().filter(|l| !is_gibberish_line(l)).collect();
+            if kept.len() == page.lines().count() {
+                page.clone()
+            } else {
+                removed += page.lines().count() - kept.len();
+                kept.join("\n")
+            }
+        })
+        .collect();
+    (out, removed)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -425,121 +1707,499 @@ pub struct OcrText {
     pub text: String,
 }
 
+/// Put OCR texts into `pages`, but only where the OCR result has more non-whitespace characters
+/// than the extracted text: pages flagged suspect for benign reasons keep their (often better)
+/// text layer. Returns the indices that kept the original extraction.
+pub fn apply_ocr_texts(pages: &mut [String], texts: &[OcrText]) -> Vec<usize> {
+    let visible = |s: &str| s.chars().filter(|c| !c.is_whitespace()).count();
+    let mut kept_original = Vec::new();
+    for t in texts {
+        let Some(slot) = pages.get_mut(t.index) else { continue };
+        if visible(&t.text) > visible(slot) {
+            *slot = t.text.clone();
+        } else {
+            kept_original.push(t.index);
+        }
+    }
+    kept_original
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OcrErrorEntry {
     pub index: usize,
     pub message: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrPageDpi {
+    pub index: usize,
+    pub dpi: u32,
+}
+
+/// Mean word confidence of one OCR-ed page, from the `conf` column of tesseract's TSV output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrPageConfidence {
+    pub index: usize,
+    pub confidence: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct OcrOutcome {
     pub texts: Vec<OcrText>,
     pub failed: Vec<usize>,
     pub skipped_due_to_missing_deps: bool,
     pub errors: Vec<OcrErrorEntry>,
+    pub page_dpi: Vec<OcrPageDpi>,
+    /// One entry per page in `texts` whose TSV output carried word confidences.
+    pub page_confidence: Vec<OcrPageConfidence>,
+    /// Language picked from the first OCR-ed page when `OcrOptions::lang` is `"auto"`.
+    pub detected_lang: Option<String>,
+    /// Wall time spent rendering pages (`pdftoppm`, or `ddjvu` for DjVu), summed over pages.
+    pub total_render_ms: u64,
+    /// Wall time spent in `tesseract` (fallback attempts included), summed over pages.
+    pub total_ocr_ms: u64,
+    /// Size of all rendered page images.
+    pub total_image_bytes: u64,
 }
 
-/// Optional OCR for suspect pages using `pdftoppm` and `tesseract`.
-/// - pages: 0-based indices to OCR
-/// - Returns texts for successfully OCR-ed pages, and failed indices.
-/// - Never panics; if deps are missing, marks skipped and returns no texts.
-pub fn ocr_tesseract(path: &Path, pages: &[usize], lang: &str, dpi: u32, artifacts_dir: Option<&Path>, psm: u8, oem: u8) -> OcrOutcome {
-    let has_pdftoppm = which::which("pdftoppm").is_ok();
-    let has_tesseract = which::which("tesseract").is_ok();
-    if !has_pdftoppm || !has_tesseract {
-        return OcrOutcome { texts: vec![], failed: pages.to_vec(), skipped_due_to_missing_deps: true, errors: vec![] };
+/// Render resolution for OCR: a fixed DPI, or chosen per page from its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcrDpi {
+    Fixed(u32),
+    Auto,
+}
+
+const AUTO_DPI_TARGET_PX: f64 = 3000.0;
+const AUTO_DPI_MIN: u32 = 150;
+const AUTO_DPI_MAX: u32 = 600;
+const AUTO_DPI_FALLBACK: u32 = 300;
+
+/// Pick a DPI so a page `height_pts` tall (1pt = 1/72 inch) renders at ~3000px,
+/// clamped to 150..=600. Unknown/invalid heights fall back to 300.
+pub fn auto_dpi_for_height(height_pts: f64) -> u32 {
+    if !height_pts.is_finite() || height_pts <= 0.0 {
+        return AUTO_DPI_FALLBACK;
     }
-    let tmpdir = tempfile::tempdir().ok();
+    let dpi = (AUTO_DPI_TARGET_PX * 72.0 / height_pts).round() as u32;
+    dpi.clamp(AUTO_DPI_MIN, AUTO_DPI_MAX)
+}
+
+/// Parse `(width, height)` in points from `pdfinfo` output ("Page    1 size: 595 x 842 pts").
+pub fn parse_page_size_pts(pdfinfo_out: &str) -> Option<(f64, f64)> {
+    let re = Regex::new(r"(?m)^Page\s*(?:\d+\s+)?size:\s*([0-9.]+)\s*x\s*([0-9.]+)\s*pts").unwrap();
+    let cap = re.captures(pdfinfo_out)?;
+    let w = cap[1].parse::<f64>().ok()?;
+    let h = cap[2].parse::<f64>().ok()?;
+    Some((w, h))
+}
+
+/// Every `Page N size:` line of `pdfinfo -f A -l B` output, as `(page_no, (width, height))`.
+pub fn parse_page_sizes_pts(pdfinfo_out: &str) -> Vec<(usize, (f64, f64))> {
+    let re = Regex::new(r"(?m)^Page\s+(\d+)\s+size:\s*([0-9.]+)\s*x\s*([0-9.]+)\s*pts").unwrap();
+    re.captures_iter(pdfinfo_out)
+        .filter_map(|c| Some((c[1].parse().ok()?, (c[2].parse().ok()?, c[3].parse().ok()?))))
+        .collect()
+}
+
+/// Sizes in points of `count` pages starting at 1-based `first`, from one `pdfinfo` call;
+/// `None` for pages pdfinfo does not report (or all of them when it is unavailable).
+pub fn page_sizes_pts(path: &Path, first: usize, count: usize) -> Vec<Option<(f64, f64)>> {
+    let mut sizes = vec![None; count];
+    if count == 0 {
+        return sizes;
+    }
+    let out = Command::new("pdfinfo")
+        .arg("-f").arg(first.to_string())
+        .arg("-l").arg((first + count - 1).to_string())
+        .arg(path)
+        .output();
+    if let Some(out) = out.ok().filter(|o| o.status.success()) {
+        for (page_no, size) in parse_page_sizes_pts(&String::from_utf8_lossy(&out.stdout)) {
+            if let Some(slot) = page_no.checked_sub(first).and_then(|i| sizes.get_mut(i)) {
+                *slot = Some(size);
+            }
+        }
+    }
+    sizes
+}
+
+/// Query a single page's size in points via `pdfinfo -f N -l N` (1-based page number).
+pub fn page_size_pts(path: &Path, page_no: usize) -> Option<(f64, f64)> {
+    let out = Command::new("pdfinfo")
+        .arg("-f").arg(page_no.to_string())
+        .arg("-l").arg(page_no.to_string())
+        .arg(path)
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    parse_page_size_pts(&String::from_utf8_lossy(&out.stdout))
+}
+
+/// Image format `pdftoppm` renders for OCR (and copies into artifacts). JPEG trades a little
+/// accuracy for much smaller files on large scans.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OcrImageFormat {
+    #[default]
+    Png,
+    /// `quality` is passed as `-jpegopt quality=N` (1–100).
+    Jpeg { quality: u8 },
+}
+
+impl OcrImageFormat {
+    pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+    /// File extension `pdftoppm -singlefile` appends to the output prefix.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OcrImageFormat::Png => "png",
+            OcrImageFormat::Jpeg { .. } => "jpg",
+        }
+    }
+
+    fn pdftoppm_args(self) -> Vec<String> {
+        match self {
+            OcrImageFormat::Png => vec!["-png".to_string()],
+            OcrImageFormat::Jpeg { quality } => vec!["-jpeg".to_string(), "-jpegopt".to_string(), format!("quality={}", quality)],
+        }
+    }
+}
+
+/// Pick Tesseract languages for a page of OCR text by counting common function words:
+/// `ind`, `eng`, or `ind+eng` when both are present or there is too little text to tell.
+pub fn detect_ocr_lang(text: &str) -> &'static str {
+    const IND: [&str; 16] = ["yang", "dan", "di", "dengan", "untuk", "dalam", "ini", "pada", "atau", "dari", "tidak", "adalah", "sebagaimana", "tersebut", "pasal", "ayat"];
+    const ENG: [&str; 16] = ["the", "and", "of", "to", "in", "is", "for", "that", "with", "shall", "this", "be", "by", "or", "article", "which"];
+    let (mut ind, mut eng) = (0usize, 0usize);
+    for word in text.split(|c: char| !c.is_alphabetic()).filter(|w| !w.is_empty()) {
+        let w = word.to_lowercase();
+        if IND.contains(&w.as_str()) {
+            ind += 1;
+        } else if ENG.contains(&w.as_str()) {
+            eng += 1;
+        }
+    }
+    if ind + eng < 5 {
+        return "ind+eng";
+    }
+    let eng_share = eng as f64 / (ind + eng) as f64;
+    if eng_share < 0.15 {
+        "ind"
+    } else if eng_share > 0.85 {
+        "eng"
+    } else {
+        "ind+eng"
+    }
+}
+
+/// Settings for `ocr_tesseract`.
+#[derive(Debug, Clone)]
+pub struct OcrOptions {
+    /// Tesseract `-l` value; `"auto"` OCRs the first page with `ind+eng` and keeps
+    /// `detect_ocr_lang`'s pick for the remaining pages.
+    pub lang: String,
+    pub dpi: OcrDpi,
+    pub psm: u8,
+    pub oem: u8,
+    /// Artifacts root; images and texts go to `<dir>/ocr/`.
+    pub artifacts_dir: Option<PathBuf>,
+    /// Prefix for render and artifact file names, so documents can share one artifacts dir.
+    pub doc_id: String,
+    /// Added to each index to get the PDF page, for extractions that began past page 1.
+    pub page_offset: usize,
+    pub image_format: OcrImageFormat,
+}
+
+impl Default for OcrOptions {
+    fn default() -> Self {
+        OcrOptions { lang: "ind".to_string(), dpi: OcrDpi::Fixed(300), psm: 4, oem: 1, artifacts_dir: None, doc_id: "doc".to_string(), page_offset: 0, image_format: OcrImageFormat::Png }
+    }
+}
+
+/// File name of a page's OCR artifact: `{doc_id}-page-{N}.{ext}` (1-based N).
+pub fn ocr_artifact_name(doc_id: &str, page_no: usize, ext: &str) -> String {
+    format!("{}-page-{}.{}", doc_id, page_no, ext)
+}
+
+/// Process-wide cap on concurrent OCR subprocesses, shared by every `ocr_tesseract` call:
+/// `(limit, in use)`, where a limit of 0 means `default_ocr_threads()`.
+static OCR_SLOTS: std::sync::Mutex<(usize, usize)> = std::sync::Mutex::new((0, 0));
+static OCR_SLOT_FREED: std::sync::Condvar = std::sync::Condvar::new();
+
+/// Half the available CPUs (at least 1): `tesseract` is itself multi-threaded.
+pub fn default_ocr_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get() / 2).max(1)
+}
+
+/// Cap the number of `pdftoppm`/`ddjvu`/`tesseract` processes running at once across the whole
+/// process, however many documents or pages are OCR-ed in parallel. 0 restores the default.
+pub fn set_ocr_threads(n: usize) {
+    let mut slots = OCR_SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    slots.0 = n;
+    OCR_SLOT_FREED.notify_all();
+}
+
+/// The current OCR process cap.
+pub fn ocr_threads() -> usize {
+    match OCR_SLOTS.lock().unwrap_or_else(|e| e.into_inner()).0 {
+        0 => default_ocr_threads(),
+        n => n,
+    }
+}
+
+/// Held while one OCR subprocess runs; dropping it frees the slot.
+struct OcrSlot;
+
+impl OcrSlot {
+    fn acquire() -> OcrSlot {
+        let mut slots = OCR_SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let limit = if slots.0 == 0 { default_ocr_threads() } else { slots.0 };
+            if slots.1 < limit {
+                slots.1 += 1;
+                return OcrSlot;
+            }
+            slots = OCR_SLOT_FREED.wait(slots).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
 
-    let mut texts = Vec::new();
-    let mut failed = Vec::new();
-    let mut errors = Vec::new();
-
-    for &idx0 in pages {
-        let page_no = (idx0 + 1) as i32; // pdftoppm is 1-based
-        // Always render into temp path, then copy into artifacts/ocr if requested
-        let base = tmpdir.as_ref().map(|d| d.path().to_path_buf()).unwrap_or_else(|| std::env::temp_dir());
-        let render_prefix = base.join(format!("p{}", page_no));
-        let render_img = render_prefix.with_extension("png");
-        let artifact_img = artifacts_dir.map(|ad| {
-            let ocr_dir = ad.join("ocr");
-            let _ = std::fs::create_dir_all(&ocr_dir);
-            ocr_dir.join(format!("page-{}.png", page_no))
-        });
-
-        // Render page to PNG via pdftoppm
-        let out = Command::new("pdftoppm")
-            .arg("-r").arg(dpi.to_string())
+impl Drop for OcrSlot {
+    fn drop(&mut self) {
+        let mut slots = OCR_SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+        slots.1 -= 1;
+        OCR_SLOT_FREED.notify_one();
+    }
+}
+
+/// What OCR-ing one page produced, folded into `OcrOutcome` in page order.
+struct PageOcr {
+    index: usize,
+    dpi: Option<u32>,
+    render_ms: u64,
+    ocr_ms: u64,
+    image_bytes: u64,
+    result: Result<(String, Option<f64>), String>,
+    artifact_error: Option<String>,
+}
+
+/// Render one page (`pdftoppm`, or `ddjvu` for DjVu) into `base` and OCR it with `lang`,
+/// falling back to `ind+eng` and then PSM 6 when tesseract fails or reads nothing.
+fn ocr_page(path: &Path, idx0: usize, lang: &str, opts: &OcrOptions, base: &Path) -> PageOcr {
+    let djvu = is_djvu(path);
+    let renderer = if djvu { "ddjvu" } else { "pdftoppm" };
+    let page_no = idx0 + opts.page_offset + 1; // pdftoppm and ddjvu are 1-based
+    let dpi_used = (!djvu).then(|| match opts.dpi {
+        OcrDpi::Fixed(n) => n,
+        OcrDpi::Auto => page_size_pts(path, page_no).map(|(_, h)| auto_dpi_for_height(h)).unwrap_or(AUTO_DPI_FALLBACK),
+    });
+    let mut page = PageOcr { index: idx0, dpi: dpi_used, render_ms: 0, ocr_ms: 0, image_bytes: 0, result: Err(String::new()), artifact_error: None };
+    // Always render into temp path, then copy into artifacts/ocr if requested
+    let render_prefix = base.join(format!("{}-p{}", opts.doc_id, page_no));
+    let ext = if djvu { "tif" } else { opts.image_format.extension() };
+    // not `with_extension`: a dotted doc_id ("uu.13.2003") would lose everything after its first dot
+    let render_img = base.join(format!("{}-p{}.{}", opts.doc_id, page_no, ext));
+    let artifact_img = opts.artifacts_dir.as_ref().map(|ad| {
+        let ocr_dir = ad.join("ocr");
+        let _ = std::fs::create_dir_all(&ocr_dir);
+        ocr_dir.join(ocr_artifact_name(&opts.doc_id, page_no, ext))
+    });
+    let artifact_txt = artifact_img.as_ref().map(|p| p.with_extension("txt"));
+
+    // Render page to PNG/JPEG via pdftoppm, or TIFF via ddjvu
+    let slot = OcrSlot::acquire();
+    let render_started = std::time::Instant::now();
+    let out = match dpi_used {
+        Some(dpi_used) => Command::new("pdftoppm")
+            .arg("-r").arg(dpi_used.to_string())
             .arg("-f").arg(page_no.to_string())
             .arg("-l").arg(page_no.to_string())
-            .arg("-png")
+            .args(opts.image_format.pdftoppm_args())
             .arg("-singlefile")
             .arg(path)
             .arg(&render_prefix)
+            .output(),
+        None => Command::new("ddjvu")
+            .arg("-format=tiff")
+            .arg(format!("-page={}", page_no))
+            .arg(path)
+            .arg(&render_img)
+            .output(),
+    };
+    drop(slot);
+    page.render_ms = render_started.elapsed().as_millis() as u64;
+    match out {
+        Ok(o) if o.status.success() => {}
+        _ => { page.result = Err(format!("{}_failed", renderer)); return page; }
+    }
+    // Verify image exists and size > 0
+    if !render_img.exists() {
+        page.result = Err("image_missing".into());
+        return page;
+    }
+    if let Ok(meta) = std::fs::metadata(&render_img) {
+        page.image_bytes = meta.len();
+        if meta.len() == 0 { page.result = Err("image_zero_size".into()); return page; }
+    }
+
+    // Tesseract OCR to stdout as TSV: the text is rebuilt from the words, which carry confidences
+    let run_tess = |lang_arg: &str, psm_arg: u8, oem_arg: u8| -> Result<(String, Option<f64>), String> {
+        let _slot = OcrSlot::acquire();
+        let out = Command::new("tesseract")
+            .arg(&render_img)
+            .arg("stdout")
+            .arg("-l").arg(lang_arg)
+            .arg("--psm").arg(psm_arg.to_string())
+            .arg("--oem").arg(oem_arg.to_string())
+            .arg("tsv")
             .output();
         match out {
-            Ok(o) if o.status.success() => {}
-            _ => { failed.push(idx0); errors.push(OcrErrorEntry{ index: idx0, message: "pdftoppm_failed".into()}); continue; }
-        }
-        // Verify image exists and size > 0
-        if !render_img.exists() {
-            failed.push(idx0);
-            errors.push(OcrErrorEntry{ index: idx0, message: "image_missing".into()});
-            continue;
-        }
-        if let Ok(meta) = std::fs::metadata(&render_img) {
-            if meta.len() == 0 { failed.push(idx0); errors.push(OcrErrorEntry{ index: idx0, message: "image_zero_size".into()}); continue; }
-        }
-
-        // Tesseract OCR to stdout
-        let mut run_tess = |lang_arg: &str, psm_arg: u8, oem_arg: u8| -> Result<String, String> {
-            let out = Command::new("tesseract")
-                .arg(&render_img)
-                .arg("stdout")
-                .arg("-l").arg(lang_arg)
-                .arg("--psm").arg(psm_arg.to_string())
-                .arg("--oem").arg(oem_arg.to_string())
-                .output();
-            match out {
-                Ok(o) if o.status.success() => {
-                    let s = String::from_utf8_lossy(&o.stdout).to_string();
-                    if s.trim().is_empty() { Err("empty_text".into()) } else { Ok(s) }
-                }
-                Ok(o) => Err(format!("tesseract_exit_{}", o.status.code().unwrap_or(-1))),
-                Err(e) => Err(format!("tesseract_spawn_error: {}", e)),
+            Ok(o) if o.status.success() => {
+                let (s, conf) = parse_tesseract_tsv(&String::from_utf8_lossy(&o.stdout));
+                if s.trim().is_empty() { Err("empty_text".into()) } else { Ok((s, conf)) }
             }
-        };
+            Ok(o) => Err(format!("tesseract_exit_{}", o.status.code().unwrap_or(-1))),
+            Err(e) => Err(format!("tesseract_spawn_error: {}", e)),
+        }
+    };
 
-        // primary attempt
-        match run_tess(lang, psm, oem) {
-            Ok(text) => {
-                texts.push(OcrText { index: idx0, text });
-            }
-            Err(e1) => {
-                // fallback once: try lang ind+eng keeping psm/oem; if still empty/error, try psm=6
-                let fallback_lang = if lang.contains('+') { lang } else { "ind+eng" };
-                match run_tess(fallback_lang, psm, oem) {
-                    Ok(text) => { texts.push(OcrText { index: idx0, text }); }
-                    Err(e2) => {
-                        // final attempt with psm=6
-                        match run_tess(fallback_lang, 6, oem) {
-                            Ok(text) => { texts.push(OcrText { index: idx0, text }); }
-                            Err(e3) => { failed.push(idx0); errors.push(OcrErrorEntry{ index: idx0, message: format!("{};{};{}", e1, e2, e3)}); }
-                        }
+    let ocr_started = std::time::Instant::now();
+    // fallback once: try lang ind+eng keeping psm/oem; if still empty/error, try psm=6
+    let fallback_lang = if lang.contains('+') { lang } else { "ind+eng" };
+    page.result = run_tess(lang, opts.psm, opts.oem).or_else(|e1| {
+        run_tess(fallback_lang, opts.psm, opts.oem)
+            .or_else(|e2| run_tess(fallback_lang, 6, opts.oem).map_err(|e3| format!("{};{};{}", e1, e2, e3)))
+    });
+    page.ocr_ms = ocr_started.elapsed().as_millis() as u64;
+
+    if let Ok((text, _)) = &page.result {
+        // If artifacts dir is requested and OCR succeeded, copy image for traceability
+        if let Some(dst) = artifact_img.as_ref() {
+            let _ = std::fs::copy(&render_img, dst);
+        }
+        // Persist per-page OCR text next to the image; IO errors are recorded, never fatal
+        if let Some(dst) = artifact_txt.as_ref() {
+            if let Err(e) = std::fs::write(dst, text) {
+                page.artifact_error = Some(format!("text_artifact_write_failed: {}", e));
+            }
+        }
+    }
+    page
+}
+
+/// Optional OCR for suspect pages using `pdftoppm` (`ddjvu` for DjVu files) and `tesseract`.
+/// - pages: 0-based indices to OCR (relative to `page_offset`); outcome indices stay relative
+/// - Returns texts for successfully OCR-ed pages, and failed indices, both in `pages` order.
+/// - Never panics; if deps are missing, marks skipped and returns no texts.
+/// - With `OcrDpi::Auto`, each page's DPI is derived from its `pdfinfo` size (300 if unknown).
+/// - DjVu pages render to TIFF at their native scan resolution; `dpi` and `image_format` do not apply.
+/// - Pages are OCR-ed by `ocr_threads()` workers; every subprocess also takes one of the
+///   `set_ocr_threads` slots first, so parallel callers share the cap. With `lang: "auto"`,
+///   pages run one at a time until the first one yields text and decides the language.
+/// - With `artifacts_dir`, writes `ocr/{doc_id}-page-N.png` (`.jpg` for JPEG) and `.txt` (1-based N).
+pub fn ocr_tesseract(path: &Path, pages: &[usize], opts: &OcrOptions) -> OcrOutcome {
+    let auto_lang = opts.lang == "auto";
+    let sample_lang = if auto_lang { "ind+eng".to_string() } else { opts.lang.clone() };
+    let mut detected_lang: Option<String> = None;
+    let renderer = if is_djvu(path) { "ddjvu" } else { "pdftoppm" };
+    let has_renderer = which::which(renderer).is_ok();
+    let has_tesseract = which::which("tesseract").is_ok();
+    if !has_renderer || !has_tesseract {
+        return OcrOutcome { texts: vec![], failed: pages.to_vec(), skipped_due_to_missing_deps: true, errors: vec![], page_dpi: vec![], page_confidence: vec![], detected_lang: None, total_render_ms: 0, total_ocr_ms: 0, total_image_bytes: 0 };
+    }
+    let tmpdir = tempfile::tempdir().ok();
+    let base = tmpdir.as_ref().map(|d| d.path().to_path_buf()).unwrap_or_else(std::env::temp_dir);
+
+    let mut done: Vec<PageOcr> = Vec::new();
+    let mut rest = pages;
+    // auto: the first page that yields text decides the language for the rest
+    while let (true, None, Some((&idx0, tail))) = (auto_lang, &detected_lang, rest.split_first()) {
+        let page = ocr_page(path, idx0, &sample_lang, opts, &base);
+        detected_lang = page.result.as_ref().ok().map(|(text, _)| detect_ocr_lang(text).to_string());
+        done.push(page);
+        rest = tail;
+    }
+    let lang = detected_lang.clone().unwrap_or(sample_lang);
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let mut parallel: Vec<(usize, PageOcr)> = std::thread::scope(|s| {
+        let workers: Vec<_> = (0..ocr_threads().min(rest.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut mine = Vec::new();
+                    loop {
+                        let at = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let Some(&idx0) = rest.get(at) else { break mine };
+                        mine.push((at, ocr_page(path, idx0, &lang, opts, &base)));
                     }
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|w| w.join().unwrap_or_default()).collect()
+    });
+    parallel.sort_by_key(|(at, _)| *at);
+    done.extend(parallel.into_iter().map(|(_, page)| page));
+
+    let mut outcome = OcrOutcome { texts: vec![], failed: vec![], skipped_due_to_missing_deps: false, errors: vec![], page_dpi: vec![], page_confidence: vec![], detected_lang, total_render_ms: 0, total_ocr_ms: 0, total_image_bytes: 0 };
+    for page in done {
+        if let Some(dpi) = page.dpi {
+            outcome.page_dpi.push(OcrPageDpi { index: page.index, dpi });
+        }
+        outcome.total_render_ms += page.render_ms;
+        outcome.total_ocr_ms += page.ocr_ms;
+        outcome.total_image_bytes += page.image_bytes;
+        match page.result {
+            Ok((text, confidence)) => {
+                outcome.texts.push(OcrText { index: page.index, text });
+                if let Some(confidence) = confidence {
+                    outcome.page_confidence.push(OcrPageConfidence { index: page.index, confidence });
                 }
             }
+            Err(message) => {
+                outcome.failed.push(page.index);
+                outcome.errors.push(OcrErrorEntry { index: page.index, message });
+            }
         }
+        if let Some(message) = page.artifact_error {
+            outcome.errors.push(OcrErrorEntry { index: page.index, message });
+        }
+    }
+    outcome
+}
 
-        // If artifacts dir is requested and render succeeded (not failed), copy image for traceability
-        if let Some(dst) = artifact_img.as_ref() {
-            if !failed.contains(&idx0) {
-                let _ = std::fs::copy(&render_img, dst);
+/// Text and mean word confidence from tesseract's TSV output. Words (level 5 rows) on the same
+/// line are joined with a space; a new paragraph or block starts after a blank line, as in
+/// tesseract's plain-text output. Words with a negative `conf` do not count toward the mean;
+/// `None` when no word has a confidence.
+pub fn parse_tesseract_tsv(tsv: &str) -> (String, Option<f64>) {
+    let mut text = String::new();
+    let mut last_line: Option<[&str; 4]> = None;
+    let (mut conf_sum, mut conf_words) = (0f64, 0usize);
+    for row in tsv.lines().skip(1) {
+        let cols: Vec<&str> = row.split('\t').collect();
+        if cols.len() < 12 || cols[0] != "5" || cols[11].trim().is_empty() {
+            continue;
+        }
+        let line = [cols[1], cols[2], cols[3], cols[4]];
+        match last_line {
+            Some(prev) if prev == line => text.push(' '),
+            Some(prev) if prev[..3] == line[..3] => text.push('\n'),
+            Some(_) => text.push_str("\n\n"),
+            None => {}
+        }
+        last_line = Some(line);
+        text.push_str(cols[11].trim());
+        if let Ok(conf) = cols[10].trim().parse::<f64>() {
+            if conf >= 0.0 {
+                conf_sum += conf;
+                conf_words += 1;
             }
         }
     }
-
-    OcrOutcome { texts, failed, skipped_due_to_missing_deps: false, errors }
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    (text, (conf_words > 0).then(|| conf_sum / conf_words as f64))
 }
 
 /// Merge pages with OCR overrides. Overrides replace the corresponding page text by index.
@@ -550,9 +2210,68 @@ pub fn merge_pages(pages: &[String], overrides: &[OcrText]) -> String {
             *slot = ov.text.clone();
         }
     }
+    dehyphenate_page_seams(&mut out_pages);
     out_pages.join("\n")
 }
 
+/// `merge_pages`, with an HTML comment `<!-- page:N -->` line opening each page (N is 1-based,
+/// counted from `first_page_no`). `law_cleanup` keeps these lines, moving one down to the next
+/// line break when the page boundary falls inside a soft-wrapped sentence.
+pub fn merge_pages_with_markers(pages: &[String], overrides: &[OcrText], first_page_no: usize) -> String {
+    let mut out_pages: Vec<String> = pages.to_vec();
+    for ov in overrides {
+        if let Some(slot) = out_pages.get_mut(ov.index) {
+            *slot = ov.text.clone();
+        }
+    }
+    // seams are joined before the markers go in, so a hyphenated word still meets its tail
+    dehyphenate_page_seams(&mut out_pages);
+    out_pages
+        .iter()
+        .enumerate()
+        .map(|(i, p)| format!("<!-- page:{} -->\n{}", first_page_no + i, p))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Rejoin words hyphenated across a page boundary: the last non-empty line of page N ends in
+/// `word-` and the first non-empty line of page N+1 starts with a lowercase continuation.
+/// The continuation token moves up to page N. Reduplication (`anak-` / `anak`) keeps its hyphen.
+/// Returns the number of seams joined.
+pub fn dehyphenate_page_seams(pages: &mut [String]) -> usize {
+    let re_tail = Regex::new(r"(\p{L}+)-$").unwrap();
+    let re_head = Regex::new(r"^(\s*)(\S+)\s*(.*)$").unwrap();
+    let mut joined = 0;
+    for i in 0..pages.len().saturating_sub(1) {
+        let mut cur: Vec<String> = pages[i].lines().map(|l| l.to_string()).collect();
+        let Some(ci) = cur.iter().rposition(|l| !l.trim().is_empty()) else { continue };
+        let tail = cur[ci].trim_end().to_string();
+        let Some(stem) = re_tail.captures(&tail).map(|c| c[1].to_string()) else { continue };
+
+        let mut next: Vec<String> = pages[i + 1].lines().map(|l| l.to_string()).collect();
+        let Some(ni) = next.iter().position(|l| !l.trim().is_empty()) else { continue };
+        let Some(caps) = re_head.captures(&next[ni]) else { continue };
+        let token = caps[2].to_string();
+        if !token.chars().next().map(|c| c.is_lowercase()).unwrap_or(false) {
+            continue;
+        }
+        let word: String = token.chars().take_while(|c| c.is_alphabetic()).collect();
+        let redup = word.to_lowercase().starts_with(&stem.to_lowercase());
+        let base = if redup { tail.as_str() } else { &tail[..tail.len() - 1] };
+        cur[ci] = format!("{}{}", base, token);
+        let rest = caps[3].to_string();
+        if rest.is_empty() {
+            next.remove(ni);
+        } else {
+            next[ni] = format!("{}{}", &caps[1], rest);
+        }
+        pages[i] = cur.join("\n");
+        pages[i + 1] = next.join("\n");
+        joined += 1;
+    }
+    joined
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CleanupStats {
     pub removed_header: usize,
@@ -562,66 +2281,407 @@ pub struct CleanupStats {
     pub removed_lines_sample: Vec<String>,
     #[serde(default)]
     pub suppressor_overrun: usize,
+    #[serde(default)]
+    pub toc_lines_removed: usize,
+    #[serde(default)]
+    pub removed_blocks: usize,
+    /// Runs of cp1252/UTF-8 double-encoded text repaired by `repair_mojibake`.
+    #[serde(default)]
+    pub mojibake_repaired: usize,
+    /// Indented blocks rendered as blockquotes (`preserve_quotes`).
+    #[serde(default)]
+    pub quote_blocks: usize,
+    /// Watermark lines dropped by the suppressor (`SuppressorStats::removed_watermark`).
+    #[serde(default)]
+    pub removed_watermark: usize,
+    /// Formula lines kept verbatim (`preserve_formulas`).
+    #[serde(default)]
+    pub formula_lines: usize,
+    /// QR/barcode OCR noise lines dropped by `strip_gibberish` (`--strip-gibberish`).
+    #[serde(default)]
+    pub gibberish_removed: usize,
+    /// "ttd." signature placeholders rewritten to `SIGNATURE_MARKER` lines.
+    #[serde(default)]
+    pub signatures_marked: usize,
 }
 
+/// Line that replaces a "ttd." (tanda tangan) signature placeholder in cleaned text.
+pub const SIGNATURE_MARKER: &str = "(signature)";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CleanupOutput {
     pub cleaned: String,
     pub stats: CleanupStats,
 }
 
-/// Minimal, safe law-aware cleanup.
-pub fn law_cleanup(text: &str, _law_mode: &str) -> CleanupOutput {
-    // 1) Remove hyphenation across lines: (\w)-\n(\w) -> $1$2
-    let hyphen_re = Regex::new(r"(\w)-\n(\w)").unwrap();
-    let hyphens_fixed = hyphen_re.find_iter(text).count();
-    let no_hyph = hyphen_re.replace_all(text, "$1$2").into_owned();
+/// Options for `law_cleanup_with`.
+#[derive(Debug, Clone, Default)]
+pub struct CleanupConfig {
+    /// Extra header line patterns, merged with the built-in ones.
+    pub header_patterns: Vec<Regex>,
+    /// Extra footer line patterns, merged with the built-in ones.
+    pub footer_patterns: Vec<Regex>,
+    /// Straighten curly quotes, spaced en/em dashes to ` - `, and `…` to `...`.
+    pub normalize_punctuation: bool,
+    /// Render indented, blank-line-delimited blocks (quoted article text) as `> ` blockquotes.
+    pub preserve_quotes: bool,
+    /// Lines matching this are never removed as header/footer (same as `SuppressorConfig::keep_lines`).
+    pub keep_lines: Option<Regex>,
+    /// Skip the soft-wrap join (step 3) and keep the original line breaks.
+    pub no_reflow: bool,
+    /// Keep formula lines (`is_formula_line`) verbatim on their own line: no soft-wrap join,
+    /// no list-marker rewriting.
+    pub preserve_formulas: bool,
+}
 
-    // 2) Remove common header/footer lines
-    let header_re = Regex::new(r"(?mi)^\s*PRESIDEN\s+REPUBLIK\s+INDONESIA\s*$").unwrap();
-    let header2_re = Regex::new(r"(?mi)^\s*KEMENTERIAN\s+KETENAGAKERJAAN\s*(RI)?\s*$").unwrap();
-    let header3_re = Regex::new(r"(?mi)^\s*(TAMBAHAN\s+)?LEMBARAN\s+NEGARA\s+REPUBLIK\s+INDONESIA.*$").unwrap();
-    let footer_re = Regex::new(r"(?m)^\s*-\s*\d+\s*-\s*$").unwrap();
-    let footer_dash_re = Regex::new(r"(?m)^\s*[\u2012\u2013\u2014\u2212\-]{1,3}\s*\d+\s*[\u2012\u2013\u2014\u2212\-]{1,3}\s*$").unwrap();
-    let footer_hal_re = Regex::new(r"(?mi)^\s*(Hal(?:\.|aman))\s*\d+\s*$").unwrap();
-    let footer_plainnum_re = Regex::new(r"(?m)^\s*\d{1,3}\s*$").unwrap();
-    let mut removed_header = 0usize;
-    let mut removed_footer = 0usize;
-    let mut kept_lines: Vec<String> = Vec::new();
-    for line in no_hyph.lines() {
-        if header_re.is_match(line) || header2_re.is_match(line) || header3_re.is_match(line) {
-            removed_header += 1;
+/// Turn quoted blocks into Markdown blockquotes, line by line. A block is a paragraph (bounded
+/// by blank lines or the text edges) of at least two lines, each indented 4+ columns beyond the
+/// text's usual indentation and none a list item; `-layout` paragraph indents stay untouched.
+/// Returns the number of blocks converted.
+fn mark_blockquotes(lines: &mut [String]) -> usize {
+    let indent = |l: &str| l.len() - l.trim_start().len();
+    let blank = |l: &str| l.trim().is_empty();
+    let list_re = Regex::new(r"^\s*(\d+\.|[a-z]\.|\(\d+\)|-)\s").unwrap();
+    // baseline = most common indentation (smallest on ties)
+    let mut counts: std::collections::BTreeMap<usize, usize> = std::collections::BTreeMap::new();
+    for l in lines.iter().filter(|l| !blank(l)) {
+        *counts.entry(indent(l)).or_default() += 1;
+    }
+    let Some(baseline) = counts.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))).map(|(k, _)| *k) else { return 0 };
+    let mut blocks = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        if blank(&lines[i]) {
+            i += 1;
             continue;
         }
-        if footer_re.is_match(line) || footer_dash_re.is_match(line) || footer_hal_re.is_match(line) || footer_plainnum_re.is_match(line) {
-            removed_footer += 1;
-            continue;
+        let end = (i..lines.len()).find(|&j| blank(&lines[j])).unwrap_or(lines.len());
+        let quoted = end - i >= 2 && lines[i..end].iter().all(|l| indent(l) >= baseline + 4 && !list_re.is_match(l));
+        if quoted {
+            for l in &mut lines[i..end] {
+                *l = format!("> {}", l.trim());
+            }
+            blocks += 1;
         }
-        kept_lines.push(line.to_string());
+        i = end;
     }
+    blocks
+}
 
-    // 3) Join soft-wrap: line ending with alnum continues with a space
-    let mut joined = String::new();
-    let mut prev_ended_alnum = false;
-    for (i, line) in kept_lines.iter().enumerate() {
-        let trimmed_next = if i > 0 && prev_ended_alnum { line.trim_start() } else { line.as_str() };
-        if i > 0 {
-            if prev_ended_alnum && !joined.ends_with(':') && !joined.ends_with(';') {
-                joined.push(' ');
-            } else {
-                joined.push('\n');
-            }
+/// Whether a line is a formula ("Tarif = Dasar x 0,5%"): it has an `=` plus another operator or
+/// a number, and at least 40% of its tokens are operators, numbers or percentages. Sentences
+/// that merely contain an equals sign stay below that density.
+pub fn is_formula_line(line: &str) -> bool {
+    if !line.contains('=') {
+        return false;
+    }
+    let is_op = |t: &str| matches!(t, "=" | "x" | "X" | "×" | "*" | "/" | ":" | "+" | "-" | "−" | "÷" | "^" | "<" | ">" | "≤" | "≥");
+    let is_num = |t: &str| {
+        let t = t.trim_matches(|c| matches!(c, '(' | ')' | ';' | ','));
+        !t.is_empty() && t.chars().any(|c| c.is_ascii_digit()) && t.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '%'))
+    };
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let symbolic = tokens.iter().filter(|t| is_op(t) || is_num(t)).count();
+    let beyond_equals = tokens.iter().any(|t| *t != "=" && (is_op(t) || is_num(t)));
+    beyond_equals && symbolic * 10 >= tokens.len() * 4
+}
+
+/// ASCII-fold typographic punctuation: curly quotes, spaced en/em dashes, and ellipses.
+/// Dashes touching a word (ranges such as `1–3`) are left alone.
+pub fn normalize_punctuation(line: &str) -> String {
+    let spaced_dash = Regex::new(r" [\u2013\u2014] ").unwrap();
+    let s: String = line
+        .chars()
+        .map(|c| match c {
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{00AB}' | '\u{00BB}' => '"',
+            '\u{2018}' | '\u{2019}' | '\u{201A}' => '\'',
+            c => c,
+        })
+        .collect();
+    spaced_dash.replace_all(&s, " - ").replace('\u{2026}', "...")
+}
+
+/// Windows-1252 byte for a char, as a UTF-8 decoder misreading cp1252 would have produced it.
+/// The five bytes cp1252 leaves undefined come through as the matching C1 control char.
+fn cp1252_byte(c: char) -> Option<u8> {
+    let b = match c as u32 {
+        0x00..=0x7F | 0xA0..=0xFF | 0x81 | 0x8D | 0x8F | 0x90 | 0x9D => return Some(c as u32 as u8),
+        0x20AC => 0x80, 0x201A => 0x82, 0x0192 => 0x83, 0x201E => 0x84, 0x2026 => 0x85,
+        0x2020 => 0x86, 0x2021 => 0x87, 0x02C6 => 0x88, 0x2030 => 0x89, 0x0160 => 0x8A,
+        0x2039 => 0x8B, 0x0152 => 0x8C, 0x017D => 0x8E, 0x2018 => 0x91, 0x2019 => 0x92,
+        0x201C => 0x93, 0x201D => 0x94, 0x2022 => 0x95, 0x2013 => 0x96, 0x2014 => 0x97,
+        0x02DC => 0x98, 0x2122 => 0x99, 0x0161 => 0x9A, 0x203A => 0x9B, 0x0153 => 0x9C,
+        0x017E => 0x9E, 0x0178 => 0x9F,
+        _ => return None,
+    };
+    Some(b)
+}
+
+/// One repair pass: each run of non-ASCII cp1252-encodable chars that starts with a UTF-8 lead
+/// byte (Â..ô) and re-encodes to valid UTF-8 is replaced by the decoded text.
+fn repair_mojibake_pass(text: &str) -> (String, usize) {
+    let mut out = String::with_capacity(text.len());
+    let mut repaired = 0usize;
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if !('\u{C2}'..='\u{F4}').contains(&c) {
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        let mut j = i;
+        let mut bytes = Vec::new();
+        while j < chars.len() && !chars[j].is_ascii() {
+            match cp1252_byte(chars[j]) {
+                Some(b) => bytes.push(b),
+                None => break,
+            }
+            j += 1;
+        }
+        // Confidence: the whole run must decode, and to fewer chars than it has (real Latin-1
+        // words like "Ãrea" or a lone "é" never form valid multi-byte sequences).
+        match String::from_utf8(bytes) {
+            Ok(decoded) if j - i >= 2 && decoded.chars().count() < j - i => {
+                out.push_str(&decoded);
+                repaired += 1;
+                i = j;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    (out, repaired)
+}
+
+fn repair_mojibake_counted(text: &str) -> (String, usize) {
+    let mut cur = text.to_string();
+    let mut total = 0usize;
+    // Text double-encoded twice needs two rounds; stop as soon as a pass finds nothing.
+    for _ in 0..3 {
+        let (next, n) = repair_mojibake_pass(&cur);
+        if n == 0 {
+            break;
+        }
+        total += n;
+        cur = next;
+    }
+    (cur, total)
+}
+
+/// Undo UTF-8 text that was misread as Windows-1252 and re-encoded ("Ã©" -> "é", "â€œ" -> "“").
+/// Only runs that decode cleanly back to UTF-8 are touched, so correct text passes through.
+pub fn repair_mojibake(text: &str) -> String {
+    repair_mojibake_counted(text).0
+}
+
+/// Regulation family selected by `--law-mode`; drives header removal and strict structure rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LawMode {
+    Auto,
+    Uu,
+    Pp,
+    Permen,
+    Perwali,
+    Perda,
+    Perpres,
+    Kepmen,
+}
+
+impl LawMode {
+    pub const ALL: [LawMode; 8] = [
+        LawMode::Auto, LawMode::Uu, LawMode::Pp, LawMode::Permen,
+        LawMode::Perwali, LawMode::Perda, LawMode::Perpres, LawMode::Kepmen,
+    ];
+
+    /// Case-insensitive parse of a `--law-mode` value.
+    pub fn parse(s: &str) -> Option<LawMode> {
+        LawMode::ALL.into_iter().find(|m| m.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LawMode::Auto => "auto",
+            LawMode::Uu => "uu",
+            LawMode::Pp => "pp",
+            LawMode::Permen => "permen",
+            LawMode::Perwali => "perwali",
+            LawMode::Perda => "perda",
+            LawMode::Perpres => "perpres",
+            LawMode::Kepmen => "kepmen",
+        }
+    }
+
+    /// Article-based families that must have BAB and Pasal under `--strict`. Decision letters
+    /// (kepmen) number their operative part KESATU, KEDUA, ... instead.
+    pub fn requires_articles(self) -> bool {
+        matches!(self, LawMode::Pp | LawMode::Permen | LawMode::Perda | LawMode::Perpres)
+    }
+
+    /// Decision letters, structured by diktum (KESATU, KEDUA, ...) rather than Pasal.
+    pub fn uses_diktum(self) -> bool {
+        matches!(self, LawMode::Kepmen)
+    }
+
+    /// Running-header lines specific to this family, on top of the built-in ones.
+    fn header_patterns(self) -> Vec<Regex> {
+        let pats: &[&str] = match self {
+            LawMode::Perda => &[r"^\s*(GUBERNUR|BUPATI|WALI\s?KOTA)\s+[A-Z][A-Z .]*[A-Z]\s*$"],
+            LawMode::Perwali => &[r"^\s*WALI\s?KOTA\s+[A-Z][A-Z .]*[A-Z]\s*$"],
+            LawMode::Kepmen => &[r"^\s*MENTERI\s+[A-Z][A-Z .]*[A-Z]\s*$"],
+            _ => &[],
+        };
+        pats.iter().map(|p| Regex::new(p).unwrap()).collect()
+    }
+}
+
+/// Resolve `auto` from the regulation title near the top of the text; explicit modes pass through.
+/// Stays `Auto` when no known title is found.
+pub fn resolve_law_mode(mode: LawMode, text: &str) -> LawMode {
+    if mode != LawMode::Auto {
+        return mode;
+    }
+    let head: String = text.lines().take(40).collect::<Vec<_>>().join(" ").to_uppercase();
+    let head = head.split_whitespace().collect::<Vec<_>>().join(" ");
+    let rules: [(&str, LawMode); 7] = [
+        ("PERATURAN DAERAH", LawMode::Perda),
+        ("PERATURAN PRESIDEN", LawMode::Perpres),
+        ("KEPUTUSAN MENTERI", LawMode::Kepmen),
+        ("PERATURAN MENTERI", LawMode::Permen),
+        ("PERATURAN PEMERINTAH", LawMode::Pp),
+        ("PERATURAN WALI", LawMode::Perwali),
+        ("UNDANG-UNDANG", LawMode::Uu),
+    ];
+    // earliest title phrase wins: a PP's preamble cites UNDANG-UNDANG further down
+    rules.iter()
+        .filter_map(|(needle, m)| head.find(needle).map(|i| (i, *m)))
+        .min_by_key(|(i, _)| *i)
+        .map(|(_, m)| m)
+        .unwrap_or(LawMode::Auto)
+}
+
+/// Minimal, safe law-aware cleanup.
+pub fn law_cleanup(text: &str, law_mode: &str) -> CleanupOutput {
+    law_cleanup_with(text, law_mode, &CleanupConfig::default())
+}
+
+/// `law_cleanup` with explicit configuration (custom header/footer patterns, ...).
+pub fn law_cleanup_with(text: &str, law_mode: &str, cfg: &CleanupConfig) -> CleanupOutput {
+    let mode_headers = LawMode::parse(law_mode).unwrap_or(LawMode::Auto).header_patterns();
+    // 0) Undo cp1252/UTF-8 double encoding before any pattern matching
+    let (text, mojibake_repaired) = repair_mojibake_counted(text);
+    let text = text.as_str();
+    // 1) Remove hyphenation across lines: (\w)-\n(\w) -> $1$2
+    let hyphen_re = Regex::new(r"(\w)-\n(\w)").unwrap();
+    let hyphens_fixed = hyphen_re.find_iter(text).count();
+    let no_hyph = hyphen_re.replace_all(text, "$1$2").into_owned();
+
+    // 2) Remove common header/footer lines
+    let header_re = Regex::new(r"(?mi)^\s*PRESIDEN\s+REPUBLIK\s+INDONESIA\s*$").unwrap();
+    let header2_re = Regex::new(r"(?mi)^\s*KEMENTERIAN\s+KETENAGAKERJAAN\s*(RI)?\s*$").unwrap();
+    let header3_re = Regex::new(r"(?mi)^\s*(TAMBAHAN\s+)?LEMBARAN\s+NEGARA\s+REPUBLIK\s+INDONESIA.*$").unwrap();
+    let footer_re = Regex::new(r"(?m)^\s*-\s*\d+\s*-\s*$").unwrap();
+    let footer_dash_re = Regex::new(r"(?m)^\s*[\u2012\u2013\u2014\u2212\-]{1,3}\s*\d+\s*[\u2012\u2013\u2014\u2212\-]{1,3}\s*$").unwrap();
+    let footer_hal_re = Regex::new(r"(?mi)^\s*(Hal(?:\.|aman))\s*\d+\s*$").unwrap();
+    let footer_plainnum_re = Regex::new(r"(?m)^\s*\d{1,3}\s*$").unwrap();
+    let ttd_re = Regex::new(r"^(\s*)(?i:t\.?t\.?d)\b\.?,?\s*(.*)$").unwrap();
+    let mut signatures_marked = 0usize;
+    let mut removed_header = 0usize;
+    let mut removed_footer = 0usize;
+    let mut kept_lines: Vec<String> = Vec::new();
+    for line in no_hyph.lines() {
+        if cfg.keep_lines.as_ref().map(|re| re.is_match(line)).unwrap_or(false) {
+            kept_lines.push(line.to_string());
+            continue;
+        }
+        if header_re.is_match(line) || header2_re.is_match(line) || header3_re.is_match(line) || cfg.header_patterns.iter().any(|re| re.is_match(line))
+            || mode_headers.iter().any(|re| re.is_match(line))
+        {
+            removed_header += 1;
+            continue;
+        }
+        if footer_re.is_match(line) || footer_dash_re.is_match(line) || footer_hal_re.is_match(line) || footer_plainnum_re.is_match(line)
+            || cfg.footer_patterns.iter().any(|re| re.is_match(line))
+        {
+            removed_footer += 1;
+            continue;
+        }
+        // "ttd." becomes a marker line of its own; a name on the same line moves below it
+        if let Some(c) = ttd_re.captures(line) {
+            signatures_marked += 1;
+            kept_lines.push(format!("{}{}", &c[1], SIGNATURE_MARKER));
+            if !c[2].trim().is_empty() {
+                kept_lines.push(format!("{}{}", &c[1], c[2].trim_end()));
+            }
+            continue;
+        }
+        kept_lines.push(line.to_string());
+    }
+    // Only after footer removal: the footer patterns above rely on the original dash characters
+    if cfg.normalize_punctuation {
+        for line in kept_lines.iter_mut() {
+            *line = normalize_punctuation(line);
+        }
+    }
+    let quote_blocks = if cfg.preserve_quotes { mark_blockquotes(&mut kept_lines) } else { 0 };
+
+    // 3) Join soft-wrap: line ending with alnum continues with a space (unless `no_reflow`)
+    // Markdown headings (`## ...`) count too, so a second pass over our own output is a no-op.
+    let is_heading = Regex::new(r"^(?i)(#{1,6}\s|BAB\s+[IVXLCDM]|Pasal\s+\d+[A-Z]?|Menimbang:?|Mengingat:?|PENJELASAN|(?-i:KE(?:SATU|DUA|TIGA|EMPAT|LIMA|ENAM|TUJUH|DELAPAN|SEMBILAN|SEPULUH|SEBELAS)))\b").unwrap();
+    let page_marker = Regex::new(r"^\s*<!-- page:\d+ -->\s*$").unwrap();
+    // a line opening with a list marker starts a new item even after a line ending in alnum
+    let any_marker = Regex::new(r"^\s*(?:\(\d+\)|\d+\.|[a-z]\.|-|>)(?:\s|$)").unwrap();
+    let mut joined = String::new();
+    let footnote_ref = Regex::new(r"\[\^\d+\]$").unwrap();
+    let mut prev_ended_alnum = false;
+    // Page markers inside a soft-wrapped sentence move down to the next real line break
+    let mut pending_markers: Vec<&str> = Vec::new();
+    let is_formula = |l: &str| cfg.preserve_formulas && is_formula_line(l);
+    let formula_lines = kept_lines.iter().filter(|l| is_formula(l)).count();
+    for (i, line) in kept_lines.iter().enumerate() {
+        if prev_ended_alnum && page_marker.is_match(line) {
+            pending_markers.push(line.trim());
+            continue;
+        }
+        let formula = is_formula(line);
+        // `-layout` pads lines with trailing spaces; drop them so joins get exactly one space
+        let line_body = line.trim_end();
+        let trimmed_next = if i > 0 && prev_ended_alnum && !formula && !any_marker.is_match(line) { line_body.trim_start() } else { line_body };
+        if i > 0 {
+            // a blank line is a paragraph break, never a continuation
+            // nor is the signature marker, which stays on its own line above the name
+            if prev_ended_alnum && !formula && !line.trim().is_empty() && line.trim() != SIGNATURE_MARKER && !any_marker.is_match(line) && !joined.ends_with(':') && !joined.ends_with(';') {
+                joined.push(' ');
+            } else {
+                for marker in pending_markers.drain(..) {
+                    joined.push('\n');
+                    joined.push_str(marker);
+                }
+                joined.push('\n');
+            }
         }
         joined.push_str(trimmed_next);
-        // treat heading lines as non-alnum enders
-        let is_heading = Regex::new(r"^(?i)(BAB\s+[IVXLCDM]|Pasal\s+\d+|Menimbang:?|Mengingat:?|PENJELASAN)\b").unwrap();
-        prev_ended_alnum = !is_heading.is_match(line)
-            && line.chars().rev().find(|c| !c.is_whitespace()).map(|c| c.is_ascii_alphanumeric()).unwrap_or(false);
+        // treat heading and blockquote lines as non-alnum enders
+        // a trailing footnote reference (`kata[^1]`) does not end the sentence
+        let ending = footnote_ref.replace(line_body, "");
+        prev_ended_alnum = !cfg.no_reflow && !formula && !is_heading.is_match(&collapse_letter_spacing(line)) && !line.starts_with("> ")
+            && ending.chars().rev().find(|c| !c.is_whitespace()).map(|c| c.is_ascii_alphanumeric()).unwrap_or(false);
+    }
+    for marker in pending_markers {
+        joined.push('\n');
+        joined.push_str(marker);
     }
 
-    // 4) Normalize lists
+    // 4) Normalize lists: numbered items keep their number; lettered points become
+    //    `- a.` items, nested under an open numbered item when indented at least as far
     let letter_re = Regex::new(r"^\s*([a-z])\.\s+").unwrap();
-    let num_re = Regex::new(r"^\s*\d+\.\s+").unwrap();
+    let num_re = Regex::new(r"^\s*(\d+)\.\s+").unwrap();
+    let indent_of = |s: &str| s.len() - s.trim_start().len();
+    let mut open_num_indent: Option<usize> = None;
     let orphan_paren = Regex::new(r"(?m)^\s*\((\d+)\)\s*$").unwrap();
     let orphan_num = Regex::new(r"(?m)^\s*([0-9]+)\.\s*$").unwrap();
     let orphan_letter = Regex::new(r"(?m)^\s*([a-z])\.\s*$").unwrap();
@@ -631,33 +2691,69 @@ pub fn law_cleanup(text: &str, _law_mode: &str) -> CleanupOutput {
     while i < lines.len() {
         let line = &lines[i];
         let mut merged_line = line.clone();
-        let mut consumed_next = false;
-        if (orphan_paren.is_match(line) || orphan_num.is_match(line) || orphan_letter.is_match(line)) && i + 1 < lines.len() {
-            let next = &lines[i + 1];
-            let is_heading_next = Regex::new(r"^(?i)(BAB\s+[IVXLCDM]|Pasal\s+\d+|Menimbang:?|Mengingat:?|PENJELASAN)\b").unwrap();
-            if !next.trim().is_empty() && !is_heading_next.is_match(next) {
+        let mut consumed = 0usize;
+        // Markers skipped on the way to the continuation; re-emitted after the merged item
+        let mut seam_markers: Vec<String> = Vec::new();
+        if is_formula(line) {
+            out_lines.push(line.clone());
+            i += 1;
+            continue;
+        }
+        if orphan_paren.is_match(line) || orphan_num.is_match(line) || orphan_letter.is_match(line) {
+            // The continuation may sit past a page seam: blank lines and page markers in between
+            let mut j = i + 1;
+            while j < lines.len() && (lines[j].trim().is_empty() || page_marker.is_match(&lines[j])) {
+                j += 1;
+            }
+            if let Some(next) = lines.get(j).filter(|n| !is_heading.is_match(n.trim_start()) && !is_formula(n)) {
                 let token = if let Some(c) = orphan_paren.captures(line) { format!("({})", &c[1]) }
                     else if let Some(c) = orphan_num.captures(line) { format!("{}.", &c[1]) }
                     else if let Some(c) = orphan_letter.captures(line) { format!("{}.", &c[1]) } else { String::new() };
-                merged_line = format!("{} {}", token, next.trim_start());
-                consumed_next = true;
+                merged_line = format!("{} {}", token, next.trim_start().trim_end());
+                seam_markers = lines[i + 1..j].iter().filter(|l| page_marker.is_match(l)).map(|l| l.trim().to_string()).collect();
+                // `-layout` may wrap the text over more lines than the soft-wrap join caught (lines
+                // ending in `,`): fold them in up to a blank line, marker, heading or sentence end
+                let mut k = j + 1;
+                while let Some(cont) = lines.get(k).filter(|_| !cfg.no_reflow) {
+                    let t = cont.trim();
+                    if t.is_empty() || page_marker.is_match(cont) || is_heading.is_match(t) || any_marker.is_match(cont)
+                        || is_formula(cont) || t == SIGNATURE_MARKER || merged_line.ends_with(['.', ';', ':'])
+                    {
+                        break;
+                    }
+                    merged_line.push(' ');
+                    merged_line.push_str(t);
+                    k += 1;
+                }
+                consumed = k - 1 - i;
             }
         }
+        let indent = indent_of(&merged_line);
         let norm = if let Some(c) = letter_re.captures(&merged_line) {
-            letter_re.replace(&merged_line, format!("- ({} )", &c[1]).as_str()).into_owned()
-        } else if num_re.is_match(&merged_line) {
-            num_re.replace(&merged_line, "1. ").into_owned()
+            let nested = matches!(open_num_indent, Some(n) if indent >= n);
+            if !nested {
+                open_num_indent = None;
+            }
+            let prefix = if nested { "   " } else { "" };
+            letter_re.replace(&merged_line, regex::NoExpand(&format!("{}- {}. ", prefix, &c[1]))).into_owned()
+        } else if let Some(c) = num_re.captures(&merged_line) {
+            open_num_indent = Some(indent);
+            num_re.replace(&merged_line, regex::NoExpand(&format!("{}. ", &c[1]))).into_owned()
         } else {
+            if is_heading.is_match(merged_line.trim_start()) {
+                open_num_indent = None;
+            }
             merged_line
         };
         out_lines.push(norm);
-        i += 1 + if consumed_next { 1 } else { 0 };
+        out_lines.extend(seam_markers);
+        i += 1 + consumed;
     }
     let cleaned = out_lines.join("\n");
 
     CleanupOutput {
         cleaned,
-        stats: CleanupStats { removed_header, removed_footer, hyphens_fixed, removed_lines_sample: Vec::new(), suppressor_overrun: 0 },
+        stats: CleanupStats { removed_header, removed_footer, hyphens_fixed, removed_lines_sample: Vec::new(), suppressor_overrun: 0, toc_lines_removed: 0, removed_blocks: 0, mojibake_repaired, quote_blocks, removed_watermark: 0, gibberish_removed: 0, formula_lines, signatures_marked },
     }
 }
 
@@ -668,6 +2764,28 @@ pub struct Found {
     pub menimbang: bool,
     pub mengingat: bool,
     pub penjelasan: bool,
+    /// Enumerated items under Menimbang.
+    #[serde(default)]
+    pub considerata: usize,
+    /// Enumerated items under Mengingat.
+    #[serde(default)]
+    pub basis: usize,
+    /// Official title from the "Menetapkan :" line, without the trailing period.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Roman numerals of promoted BAB headings, in document order (see `check_structure`).
+    #[serde(skip)]
+    pub bab_numerals: Vec<String>,
+    /// Diktum headings (KESATU, KEDUA, ...) of decision letters.
+    #[serde(default)]
+    pub diktum: usize,
+    /// "Pasal N" explanation entries after the PENJELASAN heading, promoted as `### Pasal N`
+    /// and not included in `pasal`.
+    #[serde(default)]
+    pub penjelasan_pasal: usize,
+    /// Labels of body Pasal headings ("15", "15A"), in document order; PENJELASAN is excluded.
+    #[serde(skip)]
+    pub pasal_labels: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -676,36 +2794,168 @@ pub struct PromoteOutput {
     pub found: Found,
 }
 
+/// Collapse a letter-spaced line as Poppler renders emphasized headings: "B A B   I   KETENTUAN"
+/// becomes "BAB I KETENTUAN". Runs of 2+ spaces separate words; within a word, single-character
+/// tokens are joined. Lines where most tokens are not single letters are returned unchanged.
+pub fn collapse_letter_spacing(line: &str) -> String {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let single_letters = tokens.iter().filter(|t| t.chars().count() == 1 && t.chars().all(|c| c.is_alphabetic())).count();
+    let singles = tokens.iter().filter(|t| t.chars().count() == 1).count();
+    if single_letters < 3 || singles * 2 <= tokens.len() {
+        return line.to_string();
+    }
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let words: Vec<String> = line
+        .trim()
+        .split("  ")
+        .filter(|group| !group.trim().is_empty())
+        .map(|group| {
+            let parts: Vec<&str> = group.split(' ').filter(|p| !p.is_empty()).collect();
+            if parts.iter().all(|p| p.chars().count() == 1) { parts.concat() } else { parts.join(" ") }
+        })
+        .collect();
+    format!("{}{}", indent, words.join(" "))
+}
+
 /// Promote legal headings to Markdown according to minimal patterns.
+/// Lettered/numbered items after Menimbang/Mengingat become list items (`- a. bahwa ...`,
+/// `1. Pasal ...`), with soft-wrapped continuation lines folded into their item. The list
+/// ends at the next structural heading or at prose following a blank line.
 pub fn promote_legal_headings(input: &str, _law_mode: &str) -> PromoteOutput {
     // Prepare regexes per-line
-    let re_mm = Regex::new(r"^\s*(Menimbang|Mengingat)\s*:\s*$").unwrap();
+    let re_mm = Regex::new(r"^\s*(Menimbang|Mengingat)\s*:\s*(.*)$").unwrap();
+    let re_item = Regex::new(r"^\s*(?:-\s+)?([a-z]|\d{1,2})\.\s+(.*)$").unwrap();
+    let re_list_end = Regex::new(r"(?i)^\s*(Dengan\s+Persetujuan|MEMUTUSKAN|Menetapkan)\b").unwrap();
+    // Poppler may emit letter-spaced forms: "M E M U T U S K A N"
+    let re_memutuskan = Regex::new(r"(?i)^\s*M\s*E\s*M\s*U\s*T\s*U\s*S\s*K\s*A\s*N\s*:?\s*$").unwrap();
+    let re_menetapkan = Regex::new(r"(?i)^\s*Menetapkan\s*:\s*(\S.*)$").unwrap();
     let re_bab = Regex::new(r"^\s*BAB\s+([IVXLCDM]+)\b(.*)$").unwrap();
-    let re_pasal = Regex::new(r"^\s*Pasal\s+(\d+)\s*$").unwrap();
+    // Amendment laws insert articles as "Pasal 15A"; tolerate "15 a" from spaced/lowercased extraction
+    let re_pasal = Regex::new(r"^\s*Pasal\s+(\d+)\s?([A-Za-z])?\s*$").unwrap();
     let re_penj = Regex::new(r"^\s*PENJELASAN\s*$").unwrap();
     let re_rom_sub = Regex::new(r"^\s*([IVX]+)\.\s+([A-Z][^\n]+)$").unwrap();
+    // Keputusan number their operative part "KESATU :", "KEDUA :", ..., "KEDUA PULUH SATU :"
+    let re_diktum = Regex::new(r"^\s*(KE(?:SATU|DUA|TIGA|EMPAT|LIMA|ENAM|TUJUH|DELAPAN|SEMBILAN|SEPULUH|SEBELAS)(?:\s+(?:BELAS|PULUH(?:\s+(?:SATU|DUA|TIGA|EMPAT|LIMA|ENAM|TUJUH|DELAPAN|SEMBILAN))?))?)\s*:\s*(.*)$").unwrap();
 
-    let mut out = Vec::new();
+    let mut out: Vec<String> = Vec::new();
     let mut found = Found::default();
-    for line in input.lines() {
+    // Open Menimbang/Mengingat list, and whether the last emitted line is one of its items
+    let mut list_section: Option<&str> = None;
+    let mut last_is_item = false;
+    // The Menetapkan title may wrap over following all-caps lines
+    let mut title_open = false;
+    let re_page_marker = Regex::new(r"^\s*<!-- page:\d+ -->\s*$").unwrap();
+    for raw in input.lines() {
+        // `--page-markers` comments pass through without closing an open list or title
+        if re_page_marker.is_match(raw) {
+            out.push(raw.trim().to_string());
+            continue;
+        }
+        // Letter-spaced headings ("P a s a l   1") are matched in collapsed form
+        let collapsed = collapse_letter_spacing(raw);
+        let is_structural = |l: &str| {
+            re_mm.is_match(l) || re_bab.is_match(l) || re_pasal.is_match(l) || re_penj.is_match(l) || re_menetapkan.is_match(l)
+        };
+        let line = if collapsed != raw && is_structural(&collapsed) { collapsed.as_str() } else { raw };
+        if title_open {
+            let t = line.trim();
+            let continues = !t.is_empty() && !t.chars().any(|c| c.is_lowercase())
+                && !re_bab.is_match(line) && !re_penj.is_match(line) && !re_memutuskan.is_match(line)
+                && !re_diktum.is_match(line);
+            if continues {
+                if let Some(title) = found.title.as_mut() {
+                    title.push(' ');
+                    title.push_str(t);
+                }
+                out.push(line.to_string());
+                continue;
+            }
+            title_open = false;
+        }
         if let Some(cap) = re_mm.captures(line) {
             let title = cap.get(1).unwrap().as_str();
             if title.eq_ignore_ascii_case("Menimbang") { found.menimbang = true; }
             if title.eq_ignore_ascii_case("Mengingat") { found.mengingat = true; }
             out.push(format!("## {}", title));
+            list_section = Some(if title.eq_ignore_ascii_case("Menimbang") { "menimbang" } else { "mengingat" });
+            last_is_item = false;
+            // "Menimbang : a. bahwa ..." carries its first item on the heading line
+            let rest = cap.get(2).map(|m| m.as_str().trim()).unwrap_or("");
+            if let Some(item) = re_item.captures(rest) {
+                out.push(list_item(&item[1], &item[2]));
+                if list_section == Some("menimbang") { found.considerata += 1; } else { found.basis += 1; }
+                last_is_item = true;
+            } else if !rest.is_empty() {
+                out.push(rest.to_string());
+            }
+            continue;
+        }
+        if list_section.is_some() {
+            if line.trim().is_empty() {
+                last_is_item = false;
+                out.push(line.to_string());
+                continue;
+            }
+            let structural = re_bab.is_match(line) || re_pasal.is_match(line) || re_penj.is_match(line)
+                || re_list_end.is_match(line) || re_memutuskan.is_match(line);
+            if !structural {
+                if let Some(item) = re_item.captures(line) {
+                    out.push(list_item(&item[1], &item[2]));
+                    if list_section == Some("menimbang") { found.considerata += 1; } else { found.basis += 1; }
+                    last_is_item = true;
+                    continue;
+                }
+                if last_is_item {
+                    if let Some(prev) = out.last_mut() {
+                        prev.push(' ');
+                        prev.push_str(line.trim());
+                    }
+                    continue;
+                }
+            }
+            list_section = None;
+            last_is_item = false;
+        }
+        if re_memutuskan.is_match(line) {
+            out.push("## MEMUTUSKAN".to_string());
+            continue;
+        }
+        if let Some(cap) = re_menetapkan.captures(line) {
+            found.title = Some(cap[1].trim().to_string());
+            title_open = true;
+            out.push(line.to_string());
             continue;
         }
         if let Some(cap) = re_bab.captures(line) {
             found.bab += 1;
             let roman = cap.get(1).unwrap().as_str();
+            found.bab_numerals.push(roman.to_string());
             let rest = cap.get(2).map(|m| m.as_str()).unwrap_or("");
             out.push(format!("## BAB {}{}", roman, rest));
             continue;
         }
         if let Some(cap) = re_pasal.captures(line) {
-            found.pasal += 1;
-            let num = cap.get(1).unwrap().as_str();
-            out.push(format!("## Pasal {}", num));
+            let suffix = cap.get(2).map(|m| m.as_str().to_uppercase()).unwrap_or_default();
+            let label = format!("{}{}", &cap[1], suffix);
+            // PENJELASAN walks the articles again ("Pasal 1 Cukup jelas."): those entries explain
+            // a body article, so they sit one level deeper and are counted on their own
+            if found.penjelasan {
+                found.penjelasan_pasal += 1;
+                out.push(format!("### Pasal {}", label));
+            } else {
+                found.pasal += 1;
+                out.push(format!("## Pasal {}", label));
+                found.pasal_labels.push(label);
+            }
+            continue;
+        }
+        if let Some(cap) = re_diktum.captures(line) {
+            found.diktum += 1;
+            out.push(format!("## {}", &cap[1]));
+            let rest = cap[2].trim();
+            if !rest.is_empty() {
+                out.push(rest.to_string());
+            }
             continue;
         }
         if re_penj.is_match(line) {
@@ -722,83 +2972,2207 @@ pub fn promote_legal_headings(input: &str, _law_mode: &str) -> PromoteOutput {
         out.push(line.to_string());
     }
 
+    if let Some(title) = found.title.as_mut() {
+        *title = title.trim_end_matches('.').trim_end().to_string();
+    }
     PromoteOutput { markdown: out.join("\n"), found }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Metrics {
-    pub character_coverage: f64,
-    pub leak_rate: f64,
-    pub split_violations: usize,
+/// Document title for the `# ` heading: the "Menetapkan :" title when promotion found one, else
+/// "Nomor N Tahun YYYY" from the file name, else the file stem itself.
+pub fn document_title(found: &Found, ident: Option<&DocIdentifier>, stem: &str) -> String {
+    match (&found.title, ident) {
+        (Some(title), _) if !title.trim().is_empty() => title.clone(),
+        (_, Some(id)) => format!("Nomor {} Tahun {}", id.nomor, id.tahun),
+        _ => stem.to_string(),
+    }
 }
 
-/// Compute coverage, leak rate, and split violations.
-pub fn compute_metrics(raw_text: &str, markdown: &str, _found: &Found) -> Metrics {
-    // Coverage: non-whitespace ratio
-    let nw = |s: &str| s.chars().filter(|c| !c.is_whitespace()).count() as f64;
-    let raw_nw = nw(raw_text);
-    let md_nw = nw(markdown);
-    let character_coverage = if raw_nw > 0.0 { (md_nw / raw_nw).min(1.0) } else { 0.0 };
+/// Prepend `# title` to promoted markdown; markdown that already opens with an H1 is returned unchanged.
+pub fn prepend_title(markdown: &str, title: &str) -> String {
+    if markdown.trim_start().starts_with("# ") {
+        return markdown.to_string();
+    }
+    format!("# {}\n\n{}", title, markdown.trim_start_matches('\n'))
+}
 
-    // Leak rate: fraction of header/footer lines remaining among total detected in raw + remaining
-    let header_re = Regex::new(r"(?mi)^\s*(TAMBAHAN\s+)?LEMBARAN\s+NEGARA\s+REPUBLIK\s+INDONESIA.*$").unwrap();
-    let footer_re = Regex::new(r"(?m)^\s*-\s*\d+\s*-\s*$|^\s*[\u2012\u2013\u2014\u2212\-]{1,3}\s*\d+\s*[\u2012\u2013\u2014\u2212\-]{1,3}\s*$|(?mi)^\s*(Hal(?:\.|aman))\s*\d+\s*$").unwrap();
+/// Markdown list line for a considerata/basis item: letters as `- a. `, numbers as `1. `.
+fn list_item(label: &str, text: &str) -> String {
+    if label.chars().all(|c| c.is_ascii_digit()) {
+        format!("{}. {}", label, text.trim())
+    } else {
+        format!("- {}. {}", label, text.trim())
+    }
+}
 
-    let count_matches = |s: &str, re: &Regex| -> usize { s.lines().filter(|l| re.is_match(l)).count() };
-    let raw_headers = count_matches(raw_text, &header_re);
-    let raw_footers = count_matches(raw_text, &footer_re);
-    let md_headers = count_matches(markdown, &header_re);
-    let md_footers = count_matches(markdown, &footer_re);
+/// First line where a second cleanup + promotion pass disagrees with the markdown it was fed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IdempotenceDiff {
+    /// 1-based line number.
+    pub line: usize,
+    pub first: String,
+    pub second: String,
+}
 
-    let detected_total = raw_headers + raw_footers + md_headers + md_footers; // include remaining to avoid div-by-zero
-    let leak_rate = if detected_total > 0 {
-        (md_headers + md_footers) as f64 / detected_total as f64
-    } else {
-        0.0
+/// Re-run `law_cleanup_with` + `promote_legal_headings` on finished markdown and report the first
+/// line that changes, or `None` when the pipeline is a fixed point on this document.
+pub fn check_idempotent(markdown: &str, law_mode: &str, cfg: &CleanupConfig) -> Option<IdempotenceDiff> {
+    let cleaned = law_cleanup_with(markdown, law_mode, cfg);
+    let again = promote_legal_headings(&cleaned.cleaned, law_mode).markdown;
+    if again == markdown {
+        return None;
+    }
+    let (a, b): (Vec<&str>, Vec<&str>) = (markdown.lines().collect(), again.lines().collect());
+    let n = a.len().max(b.len());
+    // Texts can differ only in trailing newlines, which `lines()` hides; report past the end then.
+    let idx = (0..n).find(|&i| a.get(i) != b.get(i)).unwrap_or(n);
+    Some(IdempotenceDiff {
+        line: idx + 1,
+        first: a.get(idx).unwrap_or(&"").to_string(),
+        second: b.get(idx).unwrap_or(&"").to_string(),
+    })
+}
+
+/// Value of a canonical uppercase roman numeral (`"XIV"` -> 14); `None` for malformed ones like `"IIII"`.
+pub fn parse_roman(s: &str) -> Option<u32> {
+    let val = |c: char| match c {
+        'I' => Some(1), 'V' => Some(5), 'X' => Some(10), 'L' => Some(50),
+        'C' => Some(100), 'D' => Some(500), 'M' => Some(1000), _ => None,
     };
+    let digits: Vec<i64> = s.chars().map(val).collect::<Option<_>>()?;
+    let mut total = 0i64;
+    for (i, &d) in digits.iter().enumerate() {
+        // subtractive notation: a smaller digit before a larger one counts negative
+        if digits.get(i + 1).is_some_and(|&next| next > d) { total -= d } else { total += d }
+    }
+    let total = u32::try_from(total).ok().filter(|&t| t > 0)?;
+    (to_roman(total) == s).then_some(total)
+}
 
-    // Split violations: simple heuristics
-    let re_split_paren = Regex::new(r"\(\s*\n\s*\d+\)").unwrap();
-    let re_line_just_letter = Regex::new(r"(?m)^\s*[a-z]\.\s*$").unwrap();
-    let re_line_just_number = Regex::new(r"(?m)^\s*\d+\.\s*$").unwrap();
-    let split_violations = re_split_paren.find_iter(markdown).count()
-        + re_line_just_letter.find_iter(markdown).count()
-        + re_line_just_number.find_iter(markdown).count();
+/// Canonical uppercase roman numeral for `n` (1..=3999).
+pub fn to_roman(mut n: u32) -> String {
+    const TABLE: [(u32, &str); 13] = [
+        (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+        (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+    ];
+    let mut out = String::new();
+    for (v, r) in TABLE {
+        while n >= v {
+            out.push_str(r);
+            n -= v;
+        }
+    }
+    out
+}
 
-    Metrics { character_coverage, leak_rate, split_violations }
+/// Structural inconsistencies found after promotion, reported in meta as `structure_warnings`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StructureWarnings {
+    /// BAB numerals skipped by the sequence I, II, III, ...
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_bab: Vec<String>,
+    /// BAB numerals that repeat or go backwards.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unordered_bab: Vec<String>,
+    /// BAB numerals that are not canonical roman numerals (e.g. OCR'd `IIII`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invalid_bab: Vec<String>,
+    /// Pasal numbers absent from 1..=highest; inserted articles ("15A") do not count as gaps.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_pasal: Vec<u32>,
+    /// Pasal labels that appear more than once, each reported once.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_pasal: Vec<String>,
+    /// Headings whose count in the final markdown disagrees with `Found` (see `recount_headings`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub heading_mismatches: Vec<HeadingMismatch>,
 }
 
-#[derive(Debug, Error)]
-pub enum EmitError {
-    #[error("WriteFailed: {0}")]
-    WriteFailed(String),
+impl StructureWarnings {
+    pub fn is_empty(&self) -> bool {
+        self.missing_bab.is_empty() && self.unordered_bab.is_empty() && self.invalid_bab.is_empty()
+            && self.missing_pasal.is_empty() && self.duplicate_pasal.is_empty()
+            && self.heading_mismatches.is_empty()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EmitPaths {
-    pub md_path: String,
-    pub meta_path: String,
+/// A heading kind counted differently by `promote_legal_headings` and the final markdown.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HeadingMismatch {
+    /// "Pasal", "penjelasan Pasal", "BAB", "Menimbang", "Mengingat", "PENJELASAN" or "diktum".
+    pub heading: String,
+    pub found: usize,
+    pub markdown: usize,
 }
 
-/// Atomically write markdown and meta JSON into outdir with doc_id stem.
-pub fn emit_files(markdown: &str, meta: &serde_json::Value, outdir: &str, doc_id: &str) -> Result<EmitPaths, EmitError> {
-    std::fs::create_dir_all(outdir).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
-    let md_path = Path::new(outdir).join(format!("{}.md", doc_id));
-    let meta_path = Path::new(outdir).join(format!("{}.meta.json", doc_id));
+/// Recount promoted `##` headings in the final markdown and report every kind whose count
+/// differs from `Found`. Catches later steps that drop or reintroduce headings; empty when
+/// the two agree. Menimbang/Mengingat/PENJELASAN are flags in `Found`, so only their presence is compared.
+pub fn recount_headings(markdown: &str, found: &Found) -> Vec<HeadingMismatch> {
+    let re_diktum = Regex::new(r"^## KE(?:SATU|DUA|TIGA|EMPAT|LIMA|ENAM|TUJUH|DELAPAN|SEMBILAN|SEPULUH|SEBELAS)\b").unwrap();
+    let count = |pred: &dyn Fn(&str) -> bool| markdown.lines().filter(|l| pred(l)).count();
+    let checks = [
+        ("Pasal", found.pasal, count(&|l| l.starts_with("## Pasal "))),
+        ("penjelasan Pasal", found.penjelasan_pasal, count(&|l| l.starts_with("### Pasal "))),
+        ("BAB", found.bab, count(&|l| l.starts_with("## BAB "))),
+        ("Menimbang", found.menimbang as usize, count(&|l| l == "## Menimbang").min(1)),
+        ("Mengingat", found.mengingat as usize, count(&|l| l == "## Mengingat").min(1)),
+        ("PENJELASAN", found.penjelasan as usize, count(&|l| l == "## PENJELASAN").min(1)),
+        ("diktum", found.diktum, count(&|l| re_diktum.is_match(l))),
+    ];
+    checks
+        .into_iter()
+        .filter(|(_, f, m)| f != m)
+        .map(|(heading, found, markdown)| HeadingMismatch { heading: heading.to_string(), found, markdown })
+        .collect()
+}
 
-    // Write temp files then rename
-    let pid = std::process::id();
-    let md_tmp = md_path.with_extension(format!("md.tmp.{}", pid));
-    let meta_tmp = meta_path.with_extension(format!("meta.json.tmp.{}", pid));
+/// Check that promoted BAB headings form the contiguous sequence I, II, III, ... and that body
+/// Pasal numbers run 1..=N without gaps or repeats.
+pub fn check_structure(found: &Found) -> StructureWarnings {
+    let mut w = StructureWarnings::default();
+    let mut prev = 0u32;
+    for numeral in &found.bab_numerals {
+        let Some(n) = parse_roman(numeral) else {
+            w.invalid_bab.push(numeral.clone());
+            continue;
+        };
+        if n <= prev {
+            w.unordered_bab.push(numeral.clone());
+            continue;
+        }
+        w.missing_bab.extend((prev + 1..n).map(to_roman));
+        prev = n;
+    }
 
-    std::fs::write(&md_tmp, markdown).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
-    let meta_bytes = serde_json::to_vec_pretty(meta).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
-    std::fs::write(&meta_tmp, meta_bytes).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    let mut seen: Vec<&str> = Vec::new();
+    let mut bases: Vec<u32> = Vec::new();
+    for label in &found.pasal_labels {
+        if seen.contains(&label.as_str()) {
+            if !w.duplicate_pasal.contains(label) {
+                w.duplicate_pasal.push(label.clone());
+            }
+            continue;
+        }
+        seen.push(label);
+        // "15A" is inserted after 15 and belongs to it
+        let digits: String = label.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if let Ok(n) = digits.parse::<u32>() {
+            bases.push(n);
+        }
+    }
+    let highest = bases.iter().copied().max().unwrap_or(0);
+    w.missing_pasal = (1..=highest).filter(|n| !bases.contains(n)).collect();
+    w
+}
 
-    std::fs::rename(&md_tmp, &md_path).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
-    std::fs::rename(&meta_tmp, &meta_path).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+/// Closing block of a regulation: where/when it was enacted and promulgated, who signed it,
+/// and the state gazette reference. Dates are ISO `YYYY-MM-DD`. Absent fields stay `None`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Promulgation {
+    /// "Disahkan di" / "Ditetapkan di"
+    pub enacted_place: Option<String>,
+    pub enacted_date: Option<String>,
+    /// "Diundangkan di"
+    pub promulgated_place: Option<String>,
+    pub promulgated_date: Option<String>,
+    /// Name under the first "ttd." (`SIGNATURE_MARKER` in cleaned text) after the enactment line.
+    pub signatory: Option<String>,
+    /// e.g. "LEMBARAN NEGARA REPUBLIK INDONESIA TAHUN 2003 NOMOR 39"
+    pub gazette: Option<String>,
+}
 
-    Ok(EmitPaths { md_path: md_path.to_string_lossy().to_string(), meta_path: meta_path.to_string_lossy().to_string() })
+/// Parse an Indonesian date ("12 Maret 2021", old spellings "Pebruari"/"Nopember") to ISO.
+fn parse_indonesian_date(day: &str, month: &str, year: &str) -> Option<String> {
+    const MONTHS: [&[&str]; 12] = [
+        &["januari"], &["februari", "pebruari"], &["maret"], &["april"], &["mei"], &["juni"],
+        &["juli"], &["agustus"], &["september"], &["oktober"], &["november", "nopember"], &["desember"],
+    ];
+    let m = month.to_lowercase();
+    let month_no = MONTHS.iter().position(|names| names.contains(&m.as_str()))? + 1;
+    let d = day.parse::<u32>().ok().filter(|d| (1..=31).contains(d))?;
+    Some(format!("{}-{:02}-{:02}", year, month_no, d))
+}
+
+/// Extract the promulgation block ("Disahkan di ... pada tanggal ...", signatory,
+/// "Diundangkan di ...", gazette number). Works on raw or soft-wrap-joined text.
+pub fn extract_promulgation(markdown: &str) -> Promulgation {
+    let place_date = |verb: &str| {
+        Regex::new(&format!(
+            r"\b(?:{})\s+di\s+(\p{{Lu}}[\p{{L}}]*(?:[ \t]+\p{{Lu}}[\p{{L}}]*)*)\s*,?\s*(?:pada\s+tanggal\s+(\d{{1,2}})\s+(\p{{L}}+)\s+(\d{{4}}))?",
+            verb
+        ))
+        .unwrap()
+    };
+    let re_enacted = place_date("Disahkan|DISAHKAN|Ditetapkan|DITETAPKAN");
+    let re_promulgated = place_date("Diundangkan|DIUNDANGKAN");
+    let re_ttd = Regex::new(r"(?i)(?:\bttd\b\.?|\(signature\))\s+([^\n]+)").unwrap();
+    let re_gazette = Regex::new(r"(?i)(TAMBAHAN\s+)?\b(LEMBARAN|BERITA)\s+NEGARA\s+REPUBLIK\s+INDONESIA\s+TAHUN\s+(\d{4})\s+NOMOR\s+(\d+)").unwrap();
+    let re_until_promulgated = Regex::new(r"(?i)\s*\bDiundangkan\b.*$").unwrap();
+
+    let mut p = Promulgation::default();
+    let enacted = re_enacted.captures(markdown);
+    let promulgated = re_promulgated.captures(markdown);
+    let date_of = |c: &regex::Captures| match (c.get(2), c.get(3), c.get(4)) {
+        (Some(d), Some(m), Some(y)) => parse_indonesian_date(d.as_str(), m.as_str(), y.as_str()),
+        _ => None,
+    };
+    if let Some(c) = &enacted {
+        p.enacted_place = Some(c[1].to_string());
+        p.enacted_date = date_of(c);
+    }
+    if let Some(c) = &promulgated {
+        p.promulgated_place = Some(c[1].to_string());
+        p.promulgated_date = date_of(c);
+    }
+    // Signatory: first "ttd." after the enactment line, before the promulgation line
+    if let Some(c) = &enacted {
+        let start = c.get(0).unwrap().end();
+        let end = promulgated.as_ref().map(|m| m.get(0).unwrap().start()).filter(|&e| e > start).unwrap_or(markdown.len());
+        if let Some(t) = re_ttd.captures(&markdown[start..end]) {
+            let name = re_until_promulgated.replace(&t[1], "");
+            let name = name.trim().trim_end_matches(',').trim();
+            if !name.is_empty() && name.len() <= 80 && !name.contains(':') {
+                p.signatory = Some(name.to_string());
+            }
+        }
+    }
+    p.gazette = re_gazette
+        .captures_iter(markdown)
+        .find(|c| c.get(1).is_none())
+        .map(|c| format!("{} NEGARA REPUBLIK INDONESIA TAHUN {} NOMOR {}", c[2].to_uppercase(), &c[3], &c[4]));
+    p
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Document,
+    /// Other top-level `##` headings (Menimbang, Mengingat, PENJELASAN, ...).
+    Section,
+    Bab,
+    Bagian,
+    Pasal,
+    Ayat,
+    Point,
+}
+
+impl NodeKind {
+    fn depth(&self) -> usize {
+        match self {
+            NodeKind::Document => 0,
+            NodeKind::Section | NodeKind::Bab => 1,
+            NodeKind::Bagian => 2,
+            NodeKind::Pasal => 3,
+            NodeKind::Ayat => 4,
+            NodeKind::Point => 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DocumentNode {
+    pub kind: NodeKind,
+    /// Number/roman/letter label, e.g. "I", "Kesatu", "5", "a".
+    pub label: Option<String>,
+    pub title: Option<String>,
+    pub text: String,
+    pub children: Vec<DocumentNode>,
+}
+
+impl DocumentNode {
+    fn new(kind: NodeKind, label: Option<String>, title: Option<String>) -> DocumentNode {
+        DocumentNode { kind, label, title, text: String::new(), children: Vec::new() }
+    }
+
+    fn push_text(&mut self, line: &str) {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        self.text.push_str(line);
+    }
+}
+
+/// Parse promoted markdown into a BAB → Bagian → Pasal → ayat → point tree.
+/// Out-of-order nodes attach to the nearest open ancestor of shallower depth
+/// (e.g. a Pasal before any BAB hangs off the document root) instead of being dropped.
+pub fn build_ast(markdown: &str, _found: &Found) -> DocumentNode {
+    let re_bab = Regex::new(r"^##\s+BAB\s+([IVXLCDM]+)\b\s*(.*)$").unwrap();
+    // `### Pasal N` explanation entries nest as Pasal nodes under the PENJELASAN section
+    let re_pasal = Regex::new(r"^###?\s+Pasal\s+(\S+)\s*$").unwrap();
+    let re_section = Regex::new(r"^#{2,3}\s+(.+)$").unwrap();
+    let re_bagian = Regex::new(r"(?i)^\s*Bagian\s+(\S+)\s*(.*)$").unwrap();
+    let re_ayat = Regex::new(r"^\s*\((\d+)\)\s*(.*)$").unwrap();
+    let re_point_norm = Regex::new(r"^\s*-\s+([a-z])\.\s*(.*)$").unwrap();
+    let re_point = Regex::new(r"^\s*([a-z]|\d+)\.\s+(.*)$").unwrap();
+
+    // stack[0] is the document root; closed nodes are folded into their parent
+    let mut stack: Vec<DocumentNode> = vec![DocumentNode::new(NodeKind::Document, None, None)];
+    fn open(stack: &mut Vec<DocumentNode>, node: DocumentNode) {
+        while stack.len() > 1 && stack.last().map(|n| n.kind.depth() >= node.kind.depth()).unwrap_or(false) {
+            let done = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(done);
+        }
+        stack.push(node);
+    }
+    let non_empty = |s: &str| if s.trim().is_empty() { None } else { Some(s.trim().to_string()) };
+
+    for line in markdown.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(c) = re_bab.captures(line) {
+            open(&mut stack, DocumentNode::new(NodeKind::Bab, Some(c[1].to_string()), non_empty(&c[2])));
+        } else if let Some(c) = re_pasal.captures(line) {
+            open(&mut stack, DocumentNode::new(NodeKind::Pasal, Some(c[1].to_string()), None));
+        } else if let Some(c) = re_section.captures(line) {
+            open(&mut stack, DocumentNode::new(NodeKind::Section, None, non_empty(&c[1])));
+        } else if let Some(c) = re_bagian.captures(line) {
+            open(&mut stack, DocumentNode::new(NodeKind::Bagian, Some(c[1].to_string()), non_empty(&c[2])));
+        } else if let Some(c) = re_ayat.captures(line) {
+            let mut node = DocumentNode::new(NodeKind::Ayat, Some(c[1].to_string()), None);
+            if let Some(t) = non_empty(&c[2]) { node.push_text(&t); }
+            open(&mut stack, node);
+        } else if let Some(c) = re_point_norm.captures(line).or_else(|| re_point.captures(line)) {
+            let mut node = DocumentNode::new(NodeKind::Point, Some(c[1].to_string()), None);
+            if let Some(t) = non_empty(&c[2]) { node.push_text(&t); }
+            open(&mut stack, node);
+        } else if let Some(top) = stack.last_mut() {
+            top.push_text(line.trim());
+        }
+    }
+    while stack.len() > 1 {
+        let done = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(done);
+    }
+    stack.pop().unwrap()
+}
+
+/// Output document format selected via `--output-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Md,
+    Txt,
+    Html,
+}
+
+impl OutputFormat {
+    /// Every format, e.g. to recognise any emitted document by its extension.
+    pub const ALL: [OutputFormat; 3] = [OutputFormat::Md, OutputFormat::Txt, OutputFormat::Html];
+
+    pub fn parse(s: &str) -> Option<OutputFormat> {
+        match s.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Some(OutputFormat::Md),
+            "txt" | "text" => Some(OutputFormat::Txt),
+            "html" | "htm" => Some(OutputFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// File extension (without dot) of the emitted document.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Md => "md",
+            OutputFormat::Txt => "txt",
+            OutputFormat::Html => "html",
+        }
+    }
+}
+
+/// Greedy word-wrap of body paragraphs at `width` columns (0 = unchanged). Headings, tables,
+/// quotes, HTML comments (page markers) and footnote definitions are left as-is; list items
+/// keep their marker on the first line and continue with a hanging indent. A word longer than
+/// `width` gets a line of its own rather than being split. A word that would open a block at the
+/// start of a line (`-`, `*`, `+`, `>`, `#`, `1.`, `(1)`, `a.`) stays on the previous line even
+/// past `width`, so wrapping never turns running text into a list, quote or heading.
+pub fn wrap_markdown(md: &str, width: usize) -> String {
+    if width == 0 {
+        return md.to_string();
+    }
+    let re_marker = Regex::new(r"^(\s*)((?:- )?(?:[a-z]\.|\d+\.|\(\d+\)|-)\s+)").unwrap();
+    let re_block_token = Regex::new(r"^(?:[-*+>]|#+|\d+\.|\(\d+\)|[a-z]\.)$").unwrap();
+    let mut out: Vec<String> = Vec::new();
+    for line in md.lines() {
+        let t = line.trim_start();
+        let indent = line.len() - t.len();
+        let verbatim = t.is_empty()
+            || t.starts_with('#')
+            || t.starts_with('|')
+            || t.starts_with('>')
+            || t.starts_with("<!--")
+            || t.starts_with("[^")
+            || line.chars().count() <= width;
+        if verbatim {
+            out.push(line.to_string());
+            continue;
+        }
+        let (lead, body) = match re_marker.captures(line) {
+            Some(c) => {
+                let m = c.get(0).unwrap();
+                (m.as_str().to_string(), &line[m.end()..])
+            }
+            None if indent >= 4 => {
+                out.push(line.to_string());
+                continue;
+            }
+            None => (line[..indent].to_string(), t),
+        };
+        let hang = " ".repeat(lead.chars().count());
+        let mut cur = lead;
+        let mut cur_len = cur.chars().count();
+        let mut has_word = false;
+        for word in body.split_whitespace() {
+            let wl = word.chars().count();
+            if has_word && cur_len + 1 + wl > width && !re_block_token.is_match(word) {
+                out.push(std::mem::replace(&mut cur, hang.clone()));
+                cur_len = hang.chars().count();
+                has_word = false;
+            }
+            if has_word {
+                cur.push(' ');
+                cur_len += 1;
+            }
+            cur.push_str(word);
+            cur_len += wl;
+            has_word = true;
+        }
+        out.push(cur);
+    }
+    let mut s = out.join("\n");
+    if md.ends_with('\n') {
+        s.push('\n');
+    }
+    s
+}
+
+/// Render promoted output in the requested format.
+/// `Txt` strips Markdown heading prefixes but keeps each heading on its own line;
+/// `Html` is the bare body fragment (see `render_html` for the full page).
+pub fn render(promoted: &PromoteOutput, fmt: OutputFormat) -> String {
+    match fmt {
+        OutputFormat::Md => promoted.markdown.clone(),
+        OutputFormat::Html => markdown_to_html(&promoted.markdown),
+        OutputFormat::Txt => {
+            let re_heading = Regex::new(r"^#{1,6}\s+").unwrap();
+            promoted
+                .markdown
+                .lines()
+                .map(|l| re_heading.replace(l, "").into_owned())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Escape a text run and turn footnote references (`[^3]`) into superscript links.
+fn html_inline(s: &str) -> String {
+    let re_ref = Regex::new(r"\[\^(\d+)\]").unwrap();
+    re_ref.replace_all(&html_escape(s), r##"<sup><a href="#fn-$1">$1</a></sup>"##).into_owned()
+}
+
+/// Minimal Markdown → HTML for the subset the pipeline emits: ATX headings, paragraphs,
+/// `- ` / `1. ` lists (one nesting level by indentation), `> ` blockquotes, pipe tables,
+/// footnote definitions and page-marker comments (passed through). PENJELASAN entries link to
+/// their article.
+fn markdown_to_html(md: &str) -> String {
+    let re_heading = Regex::new(r"^(#{1,6})\s+(.*)$").unwrap();
+    let re_ul = Regex::new(r"^(\s*)-\s+(.*)$").unwrap();
+    let re_ol = Regex::new(r"^(\s*)(\d+)\.\s+(.*)$").unwrap();
+    let re_fn = Regex::new(r"^\[\^(\d+)\]:\s*(.*)$").unwrap();
+    let re_table_sep = Regex::new(r"^\|[\s:|-]+\|$").unwrap();
+    let re_pasal = Regex::new(r"^Pasal\s+(\S+)$").unwrap();
+
+    let mut out: Vec<String> = Vec::new();
+    let mut para: Vec<String> = Vec::new();
+    let mut quote: Vec<String> = Vec::new();
+    let mut table: Vec<String> = Vec::new();
+    // Open lists as (tag, indent); the innermost is last
+    let mut lists: Vec<(&str, usize)> = Vec::new();
+
+    fn flush(out: &mut Vec<String>, para: &mut Vec<String>, quote: &mut Vec<String>, table: &mut Vec<String>) {
+        if !para.is_empty() {
+            out.push(format!("<p>{}</p>", para.join("\n")));
+            para.clear();
+        }
+        if !quote.is_empty() {
+            out.push(format!("<blockquote><p>{}</p></blockquote>", quote.join("\n")));
+            quote.clear();
+        }
+        if !table.is_empty() {
+            out.push("<table>".to_string());
+            for (i, row) in table.iter().enumerate() {
+                let tag = if i == 0 { "th" } else { "td" };
+                let cells: Vec<String> = row.trim().trim_matches('|').split('|').map(|c| format!("<{0}>{1}</{0}>", tag, html_inline(c.trim()))).collect();
+                out.push(format!("<tr>{}</tr>", cells.concat()));
+            }
+            out.push("</table>".to_string());
+            table.clear();
+        }
+    }
+    let close_lists = |out: &mut Vec<String>, lists: &mut Vec<(&str, usize)>, keep: usize| {
+        while lists.len() > keep {
+            let (tag, _) = lists.pop().unwrap();
+            if let Some(last) = out.last_mut() {
+                last.push_str("</li>");
+            }
+            out.push(format!("</{}>", tag));
+        }
+    };
+
+    for line in md.lines() {
+        let t = line.trim();
+        if t.is_empty() {
+            flush(&mut out, &mut para, &mut quote, &mut table);
+            continue;
+        }
+        let item = re_ul.captures(line).map(|c| ("ul", c[1].len(), None, c[2].to_string()))
+            .or_else(|| re_ol.captures(line).map(|c| ("ol", c[1].len(), Some(c[2].to_string()), c[3].to_string())));
+        if let Some((tag, indent, number, text)) = item {
+            flush(&mut out, &mut para, &mut quote, &mut table);
+            let keep = lists.iter().take_while(|(_, i)| *i < indent).count();
+            let same = lists.get(keep).is_some_and(|(t, i)| *t == tag && *i == indent);
+            if same {
+                close_lists(&mut out, &mut lists, keep + 1);
+                if let Some(last) = out.last_mut() {
+                    last.push_str("</li>");
+                }
+            } else {
+                close_lists(&mut out, &mut lists, keep);
+                out.push(format!("<{}>", tag));
+                lists.push((tag, indent));
+            }
+            let value = number.map(|n| format!(" value=\"{}\"", n)).unwrap_or_default();
+            out.push(format!("<li{}>{}", value, html_inline(&text)));
+            continue;
+        }
+        if !lists.is_empty() && line.starts_with(' ') && para.is_empty() {
+            // hanging-indent continuation of the open item
+            out.push(html_inline(t));
+            continue;
+        }
+        close_lists(&mut out, &mut lists, 0);
+        if let Some(c) = re_heading.captures(t) {
+            flush(&mut out, &mut para, &mut quote, &mut table);
+            let level = c[1].len();
+            // Body articles get an anchor; their PENJELASAN entries (`### Pasal N`) link back
+            match (level, re_pasal.captures(&c[2])) {
+                (2, Some(p)) => out.push(format!("<h2 id=\"pasal-{0}\">Pasal {0}</h2>", html_escape(&p[1]))),
+                (3, Some(p)) => out.push(format!("<h3><a href=\"#pasal-{0}\">Pasal {0}</a></h3>", html_escape(&p[1]))),
+                _ => out.push(format!("<h{0}>{1}</h{0}>", level, html_inline(&c[2]))),
+            }
+        } else if t.starts_with("<!--") {
+            flush(&mut out, &mut para, &mut quote, &mut table);
+            out.push(t.to_string());
+        } else if let Some(c) = re_fn.captures(t) {
+            flush(&mut out, &mut para, &mut quote, &mut table);
+            out.push(format!("<p class=\"footnote\" id=\"fn-{0}\"><sup>{0}</sup> {1}</p>", &c[1], html_inline(&c[2])));
+        } else if let Some(q) = t.strip_prefix('>') {
+            quote.push(html_inline(q.trim_start()));
+        } else if t.starts_with('|') && t.ends_with('|') {
+            if !re_table_sep.is_match(t) {
+                table.push(t.to_string());
+            }
+        } else {
+            para.push(html_inline(t));
+        }
+    }
+    flush(&mut out, &mut para, &mut quote, &mut table);
+    close_lists(&mut out, &mut lists, 0);
+    out.join("\n")
+}
+
+/// Side-by-side review for `--review`: each raw page in a fenced block followed by the cleaned
+/// markdown of that page. Pages are told apart by `<!-- page:N -->` markers (`--page-markers`);
+/// text before the first marker (the `# ` title) belongs to the first page, and without any
+/// markers the whole markdown follows the last raw page as one section.
+pub fn render_review(raw_pages: &[String], markdown: &str, doc_id: &str, first_page_no: usize) -> String {
+    let marker = Regex::new(r"^\s*<!-- page:(\d+) -->\s*$").unwrap();
+    let mut sections: Vec<String> = vec![String::new(); raw_pages.len()];
+    let mut unpaged = String::new();
+    let mut current: Option<usize> = None;
+    for line in markdown.lines() {
+        if let Some(c) = marker.captures(line) {
+            current = c[1].parse::<usize>().ok().and_then(|n| n.checked_sub(first_page_no)).filter(|&i| i < sections.len());
+            continue;
+        }
+        let target = match current {
+            Some(i) => &mut sections[i],
+            None => &mut unpaged,
+        };
+        target.push_str(line);
+        target.push('\n');
+    }
+    // Without markers the cleaned text cannot be split by page
+    let paged = markdown.lines().any(|l| marker.is_match(l));
+    let target = if paged { sections.first_mut() } else { sections.last_mut() };
+    if let Some(section) = target.filter(|_| !unpaged.trim().is_empty()) {
+        *section = format!("{}{}", unpaged, section);
+    }
+    let mut out = format!("# Review: {}\n", doc_id);
+    for (i, (raw, cleaned)) in raw_pages.iter().zip(&sections).enumerate() {
+        // a fence longer than any backtick run in the raw text
+        let longest = raw.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat(longest.max(2) + 1);
+        out.push_str(&format!("\n## Page {}\n\n{}text\n{}\n{}\n\n", first_page_no + i, fence, raw.trim_end(), fence));
+        let cleaned = cleaned.trim();
+        out.push_str(if cleaned.is_empty() { "*(no cleaned text)*" } else { cleaned });
+        out.push_str("\n\n---\n");
+    }
+    out
+}
+
+/// Complete HTML page for `--output-format html`: `doc_id` as title, a small info panel
+/// from the meta (top-level scalars plus `found.*` and `metrics.*`; timestamps left out so
+/// reruns are byte-identical), then the rendered markdown.
+pub fn render_html(promoted: &PromoteOutput, doc_id: &str, meta: &serde_json::Value) -> String {
+    let scalar = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Some(v.to_string()),
+        _ => None,
+    };
+    let mut rows: Vec<(String, String)> = Vec::new();
+    if let Some(obj) = meta.as_object() {
+        for (key, value) in obj {
+            if let Some(v) = scalar(value) {
+                rows.push((key.clone(), v));
+            } else if key == "found" || key == "metrics" {
+                for (k, v) in value.as_object().into_iter().flatten() {
+                    if let Some(v) = scalar(v) {
+                        rows.push((format!("{}.{}", key, k), v));
+                    }
+                }
+            }
+        }
+    }
+    let panel: String = rows
+        .iter()
+        .map(|(k, v)| format!("<tr><th>{}</th><td>{}</td></tr>\n", html_escape(k), html_escape(v)))
+        .collect();
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"id\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\nbody {{ font-family: serif; max-width: 50em; margin: 2em auto; line-height: 1.5; }}\naside.meta {{ font: 0.8em sans-serif; border: 1px solid #ccc; padding: 0.5em 1em; margin-bottom: 2em; }}\naside.meta th {{ text-align: left; padding-right: 1em; }}\n</style>\n</head>\n<body>\n<aside class=\"meta\">\n<table>\n{panel}</table>\n</aside>\n<main>\n{body}\n</main>\n</body>\n</html>\n",
+        title = html_escape(doc_id),
+        panel = panel,
+        body = markdown_to_html(&promoted.markdown),
+    )
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metrics {
+    pub character_coverage: f64,
+    pub leak_rate: f64,
+    pub split_violations: usize,
+    /// Fraction of distinct lowercase word tokens from the raw text present in the markdown.
+    #[serde(default)]
+    pub word_coverage: f64,
+    /// 1-based markdown line numbers of each split violation (sorted).
+    #[serde(default)]
+    pub split_violation_lines: Vec<usize>,
+    /// `leak_rate` restricted to running headers (LEMBARAN NEGARA ...).
+    #[serde(default)]
+    pub header_leak_rate: f64,
+    /// `leak_rate` restricted to page-number footers (`- 2 -`, `Halaman 2`).
+    #[serde(default)]
+    pub footer_leak_rate: f64,
+    #[serde(default)]
+    pub leak_counts: LeakCounts,
+}
+
+/// Header/footer lines matched in the raw text (detected) and still present in the markdown (remaining).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeakCounts {
+    pub headers_detected: usize,
+    pub headers_remaining: usize,
+    pub footers_detected: usize,
+    pub footers_remaining: usize,
+}
+
+/// Running headers counted as leaks when they survive into the markdown.
+fn leak_header_re() -> Regex {
+    Regex::new(r"(?mi)^\s*(TAMBAHAN\s+)?LEMBARAN\s+NEGARA\s+REPUBLIK\s+INDONESIA.*$|^\s*PRESIDEN\s+REPUBLIK\s+INDONESIA\s*$").unwrap()
+}
+
+/// Page-number footers counted as leaks when they survive into the markdown.
+fn leak_footer_re() -> Regex {
+    Regex::new(r"(?m)^\s*-\s*\d+\s*-\s*$|^\s*[\u2012\u2013\u2014\u2212\-]{1,3}\s*\d+\s*[\u2012\u2013\u2014\u2212\-]{1,3}\s*$|(?mi)^\s*(Hal(?:\.|aman))\s*\d+\s*$").unwrap()
+}
+
+/// A header/footer line left in the markdown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeakedLine {
+    /// 1-based markdown line number.
+    pub line: usize,
+    pub text: String,
+}
+
+/// Every line of `markdown` that `compute_metrics` counts as a leaked header or footer.
+pub fn leaked_lines(markdown: &str) -> Vec<LeakedLine> {
+    let header_re = leak_header_re();
+    let footer_re = leak_footer_re();
+    markdown
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| header_re.is_match(l) || footer_re.is_match(l))
+        .map(|(i, l)| LeakedLine { line: i + 1, text: l.trim().to_string() })
+        .collect()
+}
+
+/// Compute coverage, leak rate, and split violations.
+pub fn compute_metrics(raw_text: &str, markdown: &str, _found: &Found) -> Metrics {
+    // Coverage: non-whitespace ratio
+    let nw = |s: &str| s.chars().filter(|c| !c.is_whitespace()).count() as f64;
+    let raw_nw = nw(raw_text);
+    let md_nw = nw(markdown);
+    let character_coverage = if raw_nw > 0.0 { (md_nw / raw_nw).min(1.0) } else { 0.0 };
+
+    // Word coverage: recall of distinct raw word tokens (Unicode \w runs, lowercased)
+    let re_word = Regex::new(r"\w+").unwrap();
+    let words = |s: &str| -> std::collections::HashSet<String> { re_word.find_iter(s).map(|m| m.as_str().to_lowercase()).collect() };
+    let raw_words = words(raw_text);
+    let md_words = words(markdown);
+    let word_coverage = if raw_words.is_empty() {
+        0.0
+    } else {
+        raw_words.iter().filter(|w| md_words.contains(*w)).count() as f64 / raw_words.len() as f64
+    };
+
+    // Leak rate: fraction of header/footer lines remaining among total detected in raw + remaining
+    let header_re = leak_header_re();
+    let footer_re = leak_footer_re();
+
+    let count_matches = |s: &str, re: &Regex| -> usize { s.lines().filter(|l| re.is_match(l)).count() };
+    let raw_headers = count_matches(raw_text, &header_re);
+    let raw_footers = count_matches(raw_text, &footer_re);
+    let md_headers = count_matches(markdown, &header_re);
+    let md_footers = count_matches(markdown, &footer_re);
+
+    // denominators include remaining to avoid div-by-zero
+    let rate = |remaining: usize, detected: usize| -> f64 {
+        let total = detected + remaining;
+        if total > 0 { remaining as f64 / total as f64 } else { 0.0 }
+    };
+    let leak_rate = rate(md_headers + md_footers, raw_headers + raw_footers);
+    let header_leak_rate = rate(md_headers, raw_headers);
+    let footer_leak_rate = rate(md_footers, raw_footers);
+    let leak_counts = LeakCounts { headers_detected: raw_headers, headers_remaining: md_headers, footers_detected: raw_footers, footers_remaining: md_footers };
+
+    // Split violations: simple heuristics
+    let re_split_paren = Regex::new(r"\(\s*\n\s*\d+\)").unwrap();
+    let re_line_just_letter = Regex::new(r"(?m)^\s*[a-z]\.\s*$").unwrap();
+    let re_line_just_number = Regex::new(r"(?m)^\s*\d+\.\s*$").unwrap();
+    let line_of = |offset: usize| markdown[..offset].matches('\n').count() + 1;
+    let mut split_violation_lines: Vec<usize> = re_split_paren
+        .find_iter(markdown)
+        .chain(re_line_just_letter.find_iter(markdown))
+        .chain(re_line_just_number.find_iter(markdown))
+        .map(|m| line_of(m.start() + (m.as_str().len() - m.as_str().trim_start().len())))
+        .collect();
+    let split_violations = split_violation_lines.len();
+    split_violation_lines.sort_unstable();
+
+    Metrics { character_coverage, leak_rate, split_violations, word_coverage, split_violation_lines, header_leak_rate, footer_leak_rate, leak_counts }
+}
+
+/// Optional per-document quality gate; `None` disables a check.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QualityThresholds {
+    pub min_coverage: Option<f64>,
+    pub max_leak_rate: Option<f64>,
+    pub max_split_violations: Option<usize>,
+}
+
+impl QualityThresholds {
+    pub fn is_empty(&self) -> bool {
+        self.min_coverage.is_none() && self.max_leak_rate.is_none() && self.max_split_violations.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QualityViolation {
+    /// Name of the violated threshold flag, e.g. `min_coverage`.
+    pub threshold: String,
+    pub limit: f64,
+    pub value: f64,
+}
+
+/// Compare metrics against thresholds; returns every violated threshold (empty = pass).
+pub fn check_quality(metrics: &Metrics, t: &QualityThresholds) -> Vec<QualityViolation> {
+    let mut out = Vec::new();
+    if let Some(min) = t.min_coverage {
+        if metrics.character_coverage < min {
+            out.push(QualityViolation { threshold: "min_coverage".into(), limit: min, value: metrics.character_coverage });
+        }
+    }
+    if let Some(max) = t.max_leak_rate {
+        if metrics.leak_rate > max {
+            out.push(QualityViolation { threshold: "max_leak_rate".into(), limit: max, value: metrics.leak_rate });
+        }
+    }
+    if let Some(max) = t.max_split_violations {
+        if metrics.split_violations > max {
+            out.push(QualityViolation { threshold: "max_split_violations".into(), limit: max as f64, value: metrics.split_violations as f64 });
+        }
+    }
+    out
+}
+
+/// Options for `process_text`: the text-side settings the CLI applies to each document.
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// `Auto` is resolved from the text's title, as for PDFs.
+    pub law_mode: LawMode,
+    pub suppressor: SuppressorConfig,
+    pub cleanup: CleanupConfig,
+    /// Keep `<!-- page:N -->` markers, numbered from `first_page`.
+    pub page_markers: bool,
+    pub first_page: usize,
+    /// Move footnotes out of the pages and append them as `[^N]:` definitions (`--footnotes`).
+    pub footnotes: bool,
+    /// Drop garbled OCR/text-layer lines before cleanup (`--strip-gibberish`).
+    pub strip_gibberish: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        ConvertOptions {
+            law_mode: LawMode::Auto,
+            suppressor: SuppressorConfig::default(),
+            cleanup: CleanupConfig::default(),
+            page_markers: false,
+            first_page: 1,
+            footnotes: false,
+            strip_gibberish: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConvertResult {
+    /// Merged pages after suppression; the baseline the metrics compare against.
+    pub merged: String,
+    /// `law_mode` after resolving `Auto`.
+    pub law_mode: LawMode,
+    /// Cleanup stats with the suppressor counts folded in, as written to meta.
+    pub stats: CleanupStats,
+    pub promoted: PromoteOutput,
+    pub structure_warnings: StructureWarnings,
+    pub metrics: Metrics,
+    /// Lines the suppressor removed (the `suppressor_preview.txt` artifact).
+    pub suppressed_lines: Vec<String>,
+    /// Footnotes taken out of the pages; already appended to `promoted.markdown`.
+    pub footnotes: Vec<Footnote>,
+}
+
+/// Run the text half of the pipeline on already extracted pages: footnote and gibberish
+/// removal, TOC stripping, repeated-line suppression, merge, cleanup, heading promotion,
+/// structure checks and metrics. No Poppler or OCR; PDFs and `--from-text` both go through here.
+pub fn process_text(pages: &[String], opts: &ConvertOptions) -> ConvertResult {
+    // Footnotes leave the pages before suppression, which would take them for footers
+    let (pages, footnotes) = if opts.footnotes { extract_footnotes(pages) } else { (pages.to_vec(), Vec::new()) };
+    let (pages, gibberish_removed) = if opts.strip_gibberish { strip_gibberish(&pages) } else { (pages, 0) };
+    let (pages, toc_lines_removed) = strip_toc(&pages);
+    let (suppressed, suppress_stats, suppressed_lines) = suppress_repeated_lines(&pages, &opts.suppressor);
+    let merged = if opts.page_markers {
+        merge_pages_with_markers(&suppressed, &[], opts.first_page)
+    } else {
+        merge_pages(&suppressed, &[])
+    };
+    let law_mode = resolve_law_mode(opts.law_mode, &merged);
+    let mut cleaned = law_cleanup_with(&merged, law_mode.as_str(), &opts.cleanup);
+    cleaned.stats.removed_header += suppress_stats.removed_header;
+    cleaned.stats.removed_footer += suppress_stats.removed_footer;
+    cleaned.stats.removed_lines_sample = suppress_stats.removed_lines_sample;
+    cleaned.stats.suppressor_overrun = suppress_stats.suppressor_overrun;
+    cleaned.stats.removed_blocks = suppress_stats.removed_blocks;
+    cleaned.stats.removed_watermark = suppress_stats.removed_watermark;
+    cleaned.stats.toc_lines_removed = toc_lines_removed;
+    cleaned.stats.gibberish_removed = gibberish_removed;
+    let mut promoted = promote_legal_headings(&cleaned.cleaned, law_mode.as_str());
+    if !footnotes.is_empty() {
+        promoted.markdown = format!("{}\n\n{}\n", promoted.markdown.trim_end(), render_footnotes(&footnotes));
+    }
+    let mut structure_warnings = check_structure(&promoted.found);
+    structure_warnings.heading_mismatches = recount_headings(&promoted.markdown, &promoted.found);
+    let metrics = compute_metrics(&merged, &promoted.markdown, &promoted.found);
+    ConvertResult { merged, law_mode, stats: cleaned.stats, promoted, structure_warnings, metrics, suppressed_lines, footnotes }
+}
+
+/// Split a text dump into pages on form feeds (pdftotext's page separator); a trailing form
+/// feed does not start an extra empty page.
+pub fn split_text_pages(text: &str) -> Vec<String> {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    let text = text.strip_suffix('\u{0C}').unwrap_or(text);
+    text.split('\u{0C}').map(|p| p.to_string()).collect()
+}
+
+/// One already extracted document for `process_text_batch`.
+#[derive(Debug, Clone)]
+pub struct TextDocument {
+    pub doc_id: String,
+    pub pages: Vec<String>,
+}
+
+/// `process_text` over several documents, in order. `progress` sees `Started`, one `Document`
+/// before each conversion, then `Finished`.
+pub fn process_text_batch(docs: &[TextDocument], opts: &ConvertOptions, progress: ProgressHook) -> Vec<ConvertResult> {
+    let report = |ev: ProgressEvent| {
+        if let Some(hook) = progress {
+            hook(ev);
+        }
+    };
+    let total = docs.len();
+    report(ProgressEvent::Started { total });
+    let results = docs
+        .iter()
+        .enumerate()
+        .map(|(index, doc)| {
+            report(ProgressEvent::Document { index, total, doc_id: doc.doc_id.clone() });
+            process_text(&doc.pages, opts)
+        })
+        .collect();
+    report(ProgressEvent::Finished { total });
+    results
+}
+
+/// Severity of a record passed to a `LogHook`, most severe first: `Warn` covers skipped or
+/// filtered inputs, recoverable problems and side outputs written; `Info` is per-document
+/// start/finish only; `Debug` is every pipeline step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// Receives the JSON log records (`{"tool": ..}` objects) of `convert_pdf`; `None` drops them.
+pub type LogHook<'a> = Option<&'a dyn Fn(LogLevel, serde_json::Value)>;
+
+/// Settings for `convert_pdf`, shared by every document of a batch.
+#[derive(Debug, Clone, Default)]
+pub struct PdfOptions {
+    /// Text pipeline settings; `first_page` follows `extract.pages`.
+    pub convert: ConvertOptions,
+    pub extract: ExtractOptions,
+    /// Extract `.djvu`/`.djv` inputs with `djvu_extract` and render them with `ddjvu`.
+    pub djvu: bool,
+    /// Tesseract settings; `doc_id`, `artifacts_dir` and `page_offset` are set per document.
+    pub ocr: OcrOptions,
+    /// `--with-ocr[=on|off]`; `None` OCRs suspect pages when tesseract is installed.
+    pub with_ocr: Option<bool>,
+    /// OCR every page, not only the suspect ones.
+    pub ocr_all: bool,
+    /// Suspect pages by characters per square inch instead of a fixed character count.
+    pub suspect_density: Option<f64>,
+    pub strict: bool,
+    pub verify_idempotent: bool,
+    pub quality: QualityThresholds,
+    /// Record quality-gate and leak failures in `PdfConversion` instead of failing the document.
+    pub continue_on_error: bool,
+    pub fail_on_leak: bool,
+    pub emit_ast: bool,
+    /// Open the output with a `# title` heading.
+    pub title: bool,
+    /// `wrap_markdown` width for the emitted text (0 = off).
+    pub wrap: usize,
+    pub split_by_bab: bool,
+    /// `outdir` is the document's own directory, so BAB parts go straight into it.
+    pub per_doc_dir: bool,
+    pub output_format: OutputFormat,
+    /// Also write `{doc_id}.review.md`.
+    pub review: bool,
+}
+
+/// One PDF (or DjVu) for `convert_pdf`: where it comes from and where its outputs go.
+#[derive(Debug, Clone)]
+pub struct PdfDocument {
+    pub path: PathBuf,
+    /// PRD datasource name, recorded in the meta.
+    pub datasource: Option<String>,
+    pub doc_id: String,
+    /// Directory receiving `{doc_id}.{ext}` and `{doc_id}.meta.json`.
+    pub outdir: String,
+    /// Step dumps and OCR images (`--artifacts`, `--dump-steps`).
+    pub artifacts_dir: Option<String>,
+    /// SHA-256 of the input, recorded in the meta for `--skip-existing`.
+    pub input_sha256: Option<String>,
+}
+
+/// What `convert_pdf` wrote for one document.
+#[derive(Debug, Clone)]
+pub struct PdfConversion {
+    pub doc_id: String,
+    /// The document in `PdfOptions::output_format`, as written next to the meta.
+    pub rendered: String,
+    pub meta: serde_json::Value,
+    pub bench: BenchSample,
+    pub report: ReportRow,
+    /// Failed the quality gate, or kept header/footer lines under `fail_on_leak`; only ever
+    /// set with `continue_on_error`, otherwise these are errors.
+    pub quality_failed: bool,
+    pub leaked: bool,
+}
+
+/// Convert one PDF (or DjVu) end to end: extract, classify, OCR suspect pages, run
+/// `process_text`, check strict/idempotence/quality/leaks, then emit the document, its meta
+/// and the optional AST, review and BAB parts. `Ok(None)` when the file has no text and no
+/// pages to OCR (`EmptyDocument`, logged as a warning).
+pub fn convert_pdf(doc: &PdfDocument, opts: &PdfOptions, log: LogHook) -> Result<Option<PdfConversion>, PipelineError> {
+    let log_event = |level: LogLevel, value: serde_json::Value| {
+        if let Some(hook) = log {
+            hook(level, value);
+        }
+    };
+    let started_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_millis() as i128).unwrap_or(0);
+    let (file, doc_id, doc_outdir, artifacts_dir, datasource) = (&doc.path, &doc.doc_id, &doc.outdir, &doc.artifacts_dir, &doc.datasource);
+    let (convert_opts, page_subset, output_format) = (&opts.convert, opts.extract.pages, opts.output_format);
+    let (law_mode_arg, cleanup_cfg, page_markers) = (convert_opts.law_mode, &convert_opts.cleanup, convert_opts.page_markers);
+    let (ocr_all, with_ocr_forced, ocr_lang) = (opts.ocr_all, opts.with_ocr, &opts.ocr.lang);
+    let (ocr_dpi, ocr_psm, ocr_oem, ocr_image_format) = (opts.ocr.dpi, opts.ocr.psm, opts.ocr.oem, opts.ocr.image_format);
+    let (strict, continue_on_error, wrap, quality) = (opts.strict, opts.continue_on_error, opts.wrap, &opts.quality);
+    let djvu = opts.djvu && is_djvu(file);
+    let fname = file.file_name().and_then(|s| s.to_str()).unwrap_or("doc.pdf").to_string();
+    let base = fname.trim_end_matches(".pdf").trim_end_matches(".djvu").trim_end_matches(".djv");
+    log_event(LogLevel::Info, serde_json::json!({
+        "tool":"document",
+        "file": file,
+        "doc_id": doc_id,
+        "status":"started"
+    }));
+    let extract_started = std::time::Instant::now();
+    let extracted = if djvu { djvu_extract(file, page_subset) } else { poppler_extract_info(file, &opts.extract) };
+    let Extraction { pages, pdf_info, raw_fallback_pages, failed_pages, poppler_warnings } = match extracted {
+        Ok(extraction) => extraction,
+        // Nothing to convert: warn and move on instead of writing an empty .md
+        Err(PopplerError::EmptyDocument(_)) => {
+            log_event(LogLevel::Warn, serde_json::json!({
+                "tool":"poppler_extract",
+                "file": file,
+                "doc_id": doc_id,
+                "warning":"EmptyDocument",
+                "status":"skipped"
+            }));
+            return Ok(None);
+        }
+        Err(err) => {
+            let err = PipelineError::from(err);
+            log_event(LogLevel::Error, serde_json::json!({
+                "tool":"poppler_extract",
+                "file": file,
+                "error": err.label(),
+                "message": err.to_string(),
+                "error_code": err.code()
+            }));
+            return Err(err);
+        }
+    };
+    let mut bench_sample = BenchSample { pages: pages.len(), extract_ms: extract_started.elapsed().as_millis() as u64, ..BenchSample::default() };
+    log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"poppler_extract",
+        "file": file,
+        "pages": pages.len(),
+        "raw_fallback_pages": raw_fallback_pages
+    }));
+    if !failed_pages.is_empty() {
+        log_event(LogLevel::Warn, serde_json::json!({
+            "tool":"poppler_extract",
+            "file": file,
+            "warning":"pdftotext failed on some pages; they are empty and routed to OCR",
+            "failed_pages": failed_pages
+        }));
+    }
+    // Image-only scans: the text layer is empty, so skip its artifact and OCR every page
+    let pdf_kind = classify_pdf(&pages);
+    log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"classify_pdf",
+        "file": file,
+        "pdf_kind": pdf_kind
+    }));
+    if pdf_kind == PdfKind::Scanned {
+        log_event(LogLevel::Debug, serde_json::json!({
+            "tool":"classify_pdf",
+            "file": file,
+            "note":"scanned document: text extraction artifacts skipped, all pages routed to OCR"
+        }));
+    }
+    if let Some(ad) = artifacts_dir.as_ref().filter(|_| pdf_kind != PdfKind::Scanned) {
+        let joined = pages.join("\n");
+        let _ = std::fs::create_dir_all(ad);
+        let step_path = format!("{}/step1_extract.txt", ad);
+        if let Err(e) = std::fs::write(&step_path, joined) {
+            log_event(LogLevel::Error, serde_json::json!({
+                "tool":"dump_steps",
+                "file": step_path,
+                "error": e.to_string()
+            }));
+        }
+    }
+    let page_count = pages.len();
+    // Every page goes through OCR exactly once; suspect detection is bypassed, not added to
+    let ocr_all_pages = ocr_all || pdf_kind == PdfKind::Scanned;
+    let mut suspects = if ocr_all_pages {
+        (0..page_count).collect()
+    } else if let Some(density) = opts.suspect_density {
+        let sizes = page_sizes_pts(file, page_subset.map_or(1, |(first, _)| first), page_count);
+        detect_suspect_pages_by_density(&pages, &sizes, density)
+    } else {
+        detect_suspect_pages(&pages, 64)
+    };
+    for &i in &failed_pages {
+        if !suspects.contains(&i) {
+            suspects.push(i);
+        }
+    }
+    suspects.sort_unstable();
+    // CI sampling: restrict suspect pages to first N via env CI_SAMPLE_SUSPECTS
+    if let Ok(sample_n) = std::env::var("CI_SAMPLE_SUSPECTS").and_then(|v| v.parse::<usize>().map_err(|_| std::env::VarError::NotPresent)) {
+        if sample_n > 0 && suspects.len() > sample_n { suspects.truncate(sample_n); }
+    }
+    log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"detect_suspect_pages",
+        "file": file,
+        "suspect_pages": suspects
+    }));
+
+    // Enforce OCR for suspect pages when deps available (Minor-Patch-III)
+    let renderer = if djvu { "ddjvu" } else { "pdftoppm" };
+    let has_tesseract = which::which("tesseract").is_ok() && which::which(renderer).is_ok();
+    let ocr_enabled = has_tesseract; // enabled if deps available
+    let ocr_requested = ocr_all || with_ocr_forced.unwrap_or(!suspects.is_empty()); // auto when suspects exist
+
+    let mut ocr_ran = false;
+    let mut ocr_run_pages: Vec<usize> = Vec::new();
+    let mut ocr_confidence: Vec<OcrPageConfidence> = Vec::new();
+    // OCR-ed pages whose text layer had more characters than the OCR result
+    let mut ocr_kept_original: Vec<usize> = Vec::new();
+    // OCR cost: (render ms, tesseract ms, image bytes)
+    let mut ocr_cost = (0u64, 0u64, 0u64);
+    let mut ocr_skipped_reason: Option<String> = None;
+    let mut ocr_lang_used = ocr_lang.clone();
+    let ocr_dpi_meta = match ocr_dpi {
+        OcrDpi::Fixed(n) => serde_json::json!(n),
+        OcrDpi::Auto => serde_json::json!("auto"),
+    };
+    let mut pages_after_ocr = pages.clone();
+    if ocr_enabled && ocr_requested && !suspects.is_empty() {
+        let ocr_opts = OcrOptions {
+            lang: ocr_lang_used.clone(),
+            dpi: ocr_dpi,
+            psm: ocr_psm,
+            oem: ocr_oem,
+            artifacts_dir: artifacts_dir.as_ref().map(PathBuf::from),
+            doc_id: doc_id.clone(),
+            page_offset: page_subset.map_or(0, |(first, _)| first - 1),
+            image_format: ocr_image_format,
+        };
+        let ocr_started = std::time::Instant::now();
+        let ocr = ocr_tesseract(file, &suspects, &ocr_opts);
+        bench_sample.ocr_ms = ocr_started.elapsed().as_millis() as u64;
+        bench_sample.ocr_pages = suspects.len();
+        ocr_cost = (ocr.total_render_ms, ocr.total_ocr_ms, ocr.total_image_bytes);
+        log_event(LogLevel::Debug, serde_json::json!({
+            "tool":"ocr_tesseract",
+            "file": file,
+            "attempted": suspects.len(),
+            "threads": ocr_threads(),
+            "texts": ocr.texts.len(),
+            "failed": ocr.failed,
+            "skipped_due_to_missing_deps": ocr.skipped_due_to_missing_deps,
+            "lang": ocr_lang_used,
+            "lang_auto": ocr_lang == "auto",
+            "detected_lang": ocr.detected_lang
+        }));
+        // meta reports the language actually used for the remaining pages
+        if let Some(detected) = &ocr.detected_lang {
+            ocr_lang_used = detected.clone();
+        }
+        if !ocr.skipped_due_to_missing_deps {
+            ocr_kept_original = apply_ocr_texts(&mut pages_after_ocr, &ocr.texts);
+            ocr_ran = true;
+            ocr_run_pages = ocr.texts.iter().map(|t| t.index).collect();
+            ocr_confidence = ocr.page_confidence.clone();
+            // Write OCR summary when artifacts on
+            if let Some(ad) = &artifacts_dir {
+                let ocr_dir = format!("{}/ocr", ad);
+                let _ = std::fs::create_dir_all(&ocr_dir);
+                let mut summary = String::new();
+                summary.push_str(&format!("doc_id: {}\n", doc_id));
+                summary.push_str(&format!("attempted: {}\n", suspects.len()));
+                summary.push_str(&format!("success: {}\n", ocr.texts.len()));
+                summary.push_str(&format!("failed: {}\n", ocr.failed.len()));
+                if !ocr.failed.is_empty() { summary.push_str(&format!("failed_indices: {:?}\n", ocr.failed)); }
+                if !ocr.texts.is_empty() {
+                    summary.push_str("chars:\n");
+                    for t in &ocr.texts {
+                        let n = t.text.chars().filter(|c| !c.is_whitespace()).count();
+                        let decision = if ocr_kept_original.contains(&t.index) { "kept_original" } else { "replaced" };
+                        summary.push_str(&format!("- page_index={} chars={} {}\n", t.index, n, decision));
+                    }
+                }
+                if !ocr.page_dpi.is_empty() {
+                    summary.push_str("dpi:\n");
+                    for d in &ocr.page_dpi { summary.push_str(&format!("- page_index={} dpi={}\n", d.index, d.dpi)); }
+                }
+                if !ocr.page_confidence.is_empty() {
+                    summary.push_str("confidence:\n");
+                    for c in &ocr.page_confidence { summary.push_str(&format!("- page_index={} confidence={:.1}\n", c.index, c.confidence)); }
+                }
+                if !ocr.errors.is_empty() {
+                    summary.push_str("errors:\n");
+                    for e in &ocr.errors { summary.push_str(&format!("- page_index={} error={}\n", e.index, e.message)); }
+                }
+                let _ = std::fs::write(format!("{}/{}-ocr_summary.txt", ocr_dir, doc_id), summary);
+            }
+        } else {
+            ocr_skipped_reason = Some("tesseract_missing".to_string());
+        }
+    } else if !ocr_enabled && !suspects.is_empty() {
+        ocr_skipped_reason = Some("tesseract_missing".to_string());
+    } else if with_ocr_forced == Some(false) && !suspects.is_empty() {
+        ocr_skipped_reason = Some("disabled_by_flag".to_string());
+    }
+
+    // Persist step2_merge.txt (OCR overrides merged) if artifacts on
+    if let Some(ad) = &artifacts_dir {
+        let _ = std::fs::create_dir_all(ad);
+        let step2_path = format!("{}/step2_merge.txt", ad);
+        let merged_preview = pages_after_ocr.join("\n");
+        let _ = std::fs::write(&step2_path, merged_preview);
+    }
+
+    let text_opts = ConvertOptions { first_page: page_subset.map_or(1, |(first, _)| first), ..convert_opts.clone() };
+    let cleanup_started = std::time::Instant::now();
+    let review_pages = opts.review.then(|| pages_after_ocr.clone());
+    // Promulgation block is read before suppression: gazette lines look like running headers
+    let promulgation = extract_promulgation(&pages_after_ocr.join("\n"));
+    // T5-T8: footnotes, TOC, suppression, merge, cleanup, promotion, metrics (as --from-text)
+    let ConvertResult { merged, law_mode: resolved_mode, stats, mut promoted, structure_warnings, metrics, suppressed_lines, footnotes } =
+        process_text(&pages_after_ocr, &text_opts);
+    let law_mode = resolved_mode.as_str();
+    if stats.gibberish_removed > 0 {
+        log_event(LogLevel::Warn, serde_json::json!({
+            "tool":"strip_gibberish",
+            "file": file,
+            "removed": stats.gibberish_removed
+        }));
+    }
+    if let Some(ad) = &artifacts_dir {
+        // Dump preview
+        let _ = std::fs::create_dir_all(ad);
+        let prev = format!("{}/suppressor_preview.txt", ad);
+        let _ = std::fs::write(&prev, suppressed_lines.join("\n"));
+    }
+    if let Some(ad) = &artifacts_dir {
+        let step2_path = format!("{}/step2_merge.txt", ad);
+        if let Err(e) = std::fs::write(&step2_path, &merged) {
+            log_event(LogLevel::Error, serde_json::json!({
+                "tool":"dump_steps",
+                "file": step2_path,
+                "error": e.to_string()
+            }));
+        }
+    }
+    log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"merge_pages",
+        "file": file,
+        "length": merged.len()
+    }));
+    log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"law_mode",
+        "file": file,
+        "requested": law_mode_arg,
+        "resolved": resolved_mode
+    }));
+    log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"law_cleanup",
+        "file": file,
+        "removed_header": stats.removed_header,
+        "removed_footer": stats.removed_footer,
+        "hyphens_fixed": stats.hyphens_fixed
+    }));
+    if let Some(ad) = &artifacts_dir {
+        let step3_path = format!("{}/step3_md.txt", ad);
+        if let Err(e) = std::fs::write(&step3_path, &promoted.markdown) {
+            log_event(LogLevel::Error, serde_json::json!({
+                "tool":"dump_steps",
+                "file": step3_path,
+                "error": e.to_string()
+            }));
+        }
+    }
+    log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"promote_legal_headings",
+        "file": file,
+        "found": promoted.found
+    }));
+
+    if !structure_warnings.is_empty() {
+        log_event(LogLevel::Warn, serde_json::json!({
+            "tool":"check_structure",
+            "file": file,
+            "structure_warnings": structure_warnings
+        }));
+    }
+
+    // Strict mode: article-based modes (pp/permen/perda/perpres) need BAB+Pasal, kepmen a diktum
+    if strict {
+        let missing = promoted.found.pasal == 0 || promoted.found.bab == 0;
+        // only an explicit --law-mode opts into this; `auto` never fails on structure
+        let articles_broken = law_mode_arg.requires_articles() && (missing || !structure_warnings.is_empty());
+        let diktum_missing = law_mode_arg.uses_diktum() && promoted.found.diktum == 0;
+        if articles_broken || diktum_missing {
+            log_event(LogLevel::Error, serde_json::json!({
+                "tool":"promote_legal_headings",
+                "file": file,
+                "error":"StructureNotFound",
+                "error_code": ExitCode::StructureNotFound.code(),
+                "found": promoted.found,
+                "structure_warnings": structure_warnings
+            }));
+            return Err(PipelineError::StructureNotFound);
+        }
+    }
+
+    // Second pass over the output must be a no-op; otherwise some cleanup rule is not a fixed point
+    if opts.verify_idempotent {
+        if let Some(diff) = check_idempotent(&promoted.markdown, law_mode, cleanup_cfg) {
+            log_event(if strict { LogLevel::Error } else { LogLevel::Warn }, serde_json::json!({
+                "tool":"verify_idempotent",
+                "file": file,
+                "warning":"second cleanup pass changes output",
+                "line": diff.line,
+                "first": diff.first,
+                "second": diff.second
+            }));
+            if strict {
+                return Err(PipelineError::NotIdempotent { line: diff.line });
+            }
+        }
+    }
+
+    // T8: Metrics (computed by process_text)
+    bench_sample.cleanup_ms = cleanup_started.elapsed().as_millis() as u64;
+    log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"compute_metrics",
+        "file": file,
+        "character_coverage": metrics.character_coverage,
+        "word_coverage": metrics.word_coverage,
+        "leak_rate": metrics.leak_rate,
+        "header_leak_rate": metrics.header_leak_rate,
+        "footer_leak_rate": metrics.footer_leak_rate,
+        "split_violations": metrics.split_violations
+    }));
+
+    // Quality gate: exit 7, or count and keep going with --continue-on-error
+    let violations = check_quality(&metrics, quality);
+    if !violations.is_empty() {
+        log_event(LogLevel::Error, serde_json::json!({
+            "tool":"quality_gate",
+            "file": file,
+            "error":"QualityGateFailed",
+            "violations": violations,
+            "error_code": ExitCode::QualityGateFailed.code()
+        }));
+        if !continue_on_error {
+            return Err(PipelineError::QualityGateFailed { failed_documents: 1 });
+        }
+    }
+
+    // --fail-on-leak: any surviving header/footer line fails the document (exit 9)
+    if opts.fail_on_leak && metrics.leak_rate > 0.0 {
+        let leaked = leaked_lines(&promoted.markdown);
+        log_event(LogLevel::Error, serde_json::json!({
+            "tool":"leak_check",
+            "file": file,
+            "error":"LeakDetected",
+            "leak_rate": metrics.leak_rate,
+            "leaked": leaked.len(),
+            "samples": leaked.iter().take(5).collect::<Vec<_>>(),
+            "error_code": ExitCode::LeakDetected.code()
+        }));
+        if !continue_on_error {
+            return Err(PipelineError::LeakDetected { failed_documents: 1 });
+        }
+    }
+
+    // Optional: logical document tree next to the markdown
+    if opts.emit_ast {
+        let ast = build_ast(&promoted.markdown, &promoted.found);
+        let _ = std::fs::create_dir_all(doc_outdir);
+        let ast_path = format!("{}/{}.ast.json", doc_outdir, doc_id);
+        let written = serde_json::to_vec_pretty(&ast)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| std::fs::write(&ast_path, bytes).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            log_event(LogLevel::Error, serde_json::json!({
+                "tool":"build_ast",
+                "file": ast_path,
+                "error": e,
+                "error_code": ExitCode::WriteFailed.code()
+            }));
+            return Err(EmitError::WriteFailed(e).into());
+        }
+        log_event(LogLevel::Debug, serde_json::json!({
+            "tool":"build_ast",
+            "file": file,
+            "ast_path": ast_path,
+            "nodes": ast.children.len()
+        }));
+    }
+
+    // Added after metrics, so the heading never counts toward coverage or leaks
+    let title = document_title(&promoted.found, parse_doc_identifier(&fname).as_ref(), base);
+    if opts.title {
+        promoted.markdown = prepend_title(&promoted.markdown, &title);
+    }
+
+    // --wrap: only the emitted text is wrapped; metrics and AST saw the original lines
+    if wrap > 0 {
+        promoted.markdown = wrap_markdown(&promoted.markdown, wrap);
+    }
+
+    let parts = if opts.split_by_bab { split_by_bab(&promoted.markdown) } else { Vec::new() };
+    let parts_dir = if opts.per_doc_dir { doc_outdir.clone() } else { format!("{}/{}", doc_outdir, doc_id) };
+
+    // T9: Emit files (atomic)
+    let finished_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_millis() as i128).unwrap_or(0);
+    // Timing vector proxy & p95
+    let total_ms = (finished_ms - started_ms).max(0) as u128;
+    let per_page = if page_count>0 { (total_ms / (page_count as u128)) as u64 } else { 0 };
+    let timing_ms_per_page: Vec<u64> = vec![per_page; page_count];
+    let p95_latency_ms_per_page: u64 = per_page;
+    // coverage_pages metric
+    let suspects_len = suspects.len() as i64;
+    let run_len = ocr_run_pages.len() as i64;
+    let pages_i = page_count as i64;
+    let cov_pages = if pages_i > 0 { 1.0 - (((suspects_len - run_len).max(0) as f64) / (pages_i as f64)) } else { 0.0 };
+
+    let meta = serde_json::json!({
+        "doc_id": doc_id,
+        "datasource": datasource,
+        "input_sha256": doc.input_sha256,
+        "document": parse_doc_identifier(&fname),
+        "title": title,
+        "promulgation": promulgation,
+        "engine": if djvu { "djvu" } else { "poppler" },
+        "pdf_kind": pdf_kind,
+        "law_mode": resolved_mode,
+        "pdf_info": pdf_info,
+        "raw_fallback_pages": raw_fallback_pages,
+        "failed_pages": failed_pages,
+        "poppler_warnings": poppler_warnings,
+        "page_markers": page_markers,
+        "page_subset": page_subset.map(|(first, last)| serde_json::json!({"first": first, "last": last})),
+        "suspect_pages": suspects,
+        "pages": page_reports(&pages_after_ocr, &suspects, &ocr_run_pages, &ocr_confidence),
+        "footnotes": footnotes.len(),
+        "wrap": wrap,
+        "ocr": {
+            "enabled": ocr_enabled,
+            "ran": ocr_ran,
+            "skipped_reason": ocr_skipped_reason,
+            "all_pages": ocr_all_pages,
+            "ocr_run_pages": ocr_run_pages,
+            "kept_original_pages": ocr_kept_original,
+            "lang": ocr_lang_used,
+            "psm": ocr_psm,
+            "oem": ocr_oem,
+            "dpi": ocr_dpi_meta,
+            "image_format": ocr_image_format.extension(),
+            "total_render_ms": ocr_cost.0,
+            "total_ocr_ms": ocr_cost.1,
+            "total_image_bytes": ocr_cost.2,
+        },
+        "found": promoted.found,
+        "structure_warnings": if structure_warnings.is_empty() { serde_json::Value::Null } else { serde_json::json!(structure_warnings) },
+        "stats": stats,
+        "metrics": {
+            "character_coverage": metrics.character_coverage,
+            "word_coverage": metrics.word_coverage,
+            "leak_rate": metrics.leak_rate,
+            "header_leak_rate": metrics.header_leak_rate,
+            "footer_leak_rate": metrics.footer_leak_rate,
+            "leak_counts": metrics.leak_counts,
+            "split_violations": metrics.split_violations,
+            "split_violation_lines": metrics.split_violation_lines,
+            "coverage_pages": cov_pages
+        },
+        "output_format": output_format.extension(),
+        "parts": parts.iter().map(|p| p.file_name.clone()).collect::<Vec<_>>(),
+        "quality_gate": if quality.is_empty() { serde_json::Value::Null } else { serde_json::json!({"passed": violations.is_empty(), "violations": violations}) },
+        "page_count": page_count,
+        "timing_ms_per_page": timing_ms_per_page,
+        "p95_latency_ms_per_page": p95_latency_ms_per_page,
+        "timestamps": {"started_ms": started_ms, "finished_ms": finished_ms},
+    });
+    // Compute meta_fingerprint (normalized meta without timestamps or OCR timings)
+    let mut meta_norm = meta.clone();
+    if let Some(obj) = meta_norm.as_object_mut() {
+        obj.remove("timestamps");
+        if let Some(ocr) = obj.get_mut("ocr").and_then(|o| o.as_object_mut()) {
+            ocr.remove("total_render_ms");
+            ocr.remove("total_ocr_ms");
+        }
+    }
+    let meta_norm_bytes = serde_json::to_vec(&meta_norm).unwrap_or_default();
+    let fingerprint = sha256_hex(&meta_norm_bytes);
+    let mut meta_full = meta.as_object().cloned().unwrap_or_default();
+    meta_full.insert("meta_fingerprint".to_string(), serde_json::json!(fingerprint));
+    let meta = serde_json::Value::Object(meta_full);
+    // Ensure doc output directory exists
+    let _ = std::fs::create_dir_all(doc_outdir);
+    let rendered = if output_format == OutputFormat::Html { render_html(&promoted, doc_id, &meta) } else { render(&promoted, output_format) };
+    match emit_files_as(&rendered, &meta, doc_outdir.as_str(), doc_id, output_format) {
+        Ok(paths) => {
+            log_event(LogLevel::Debug, serde_json::json!({
+                "tool":"emit_files",
+                "file": file,
+                "md_path": paths.md_path,
+                "meta_path": paths.meta_path
+            }));
+            if let Some(raw_pages) = &review_pages {
+                let text = render_review(raw_pages, &promoted.markdown, doc_id, page_subset.map_or(1, |(first, _)| first));
+                match emit_review(&text, doc_outdir.as_str(), doc_id) {
+                    Ok(path) => {
+                        log_event(LogLevel::Debug, serde_json::json!({
+                            "tool":"review",
+                            "file": file,
+                            "review_path": path
+                        }));
+                    }
+                    Err(e) => {
+                        log_event(LogLevel::Error, serde_json::json!({
+                            "tool":"review",
+                            "file": file,
+                            "error": e.to_string(),
+                            "error_code": ExitCode::WriteFailed.code()
+                        }));
+                        return Err(e.into());
+                    }
+                }
+            }
+            if !parts.is_empty() {
+                match emit_parts(&parts, &parts_dir, doc_id) {
+                    Ok(written) => {
+                        log_event(LogLevel::Debug, serde_json::json!({
+                            "tool":"split_by_bab",
+                            "file": file,
+                            "parts": written
+                        }));
+                    }
+                    Err(e) => {
+                        log_event(LogLevel::Error, serde_json::json!({
+                            "tool":"split_by_bab",
+                            "file": file,
+                            "error": e.to_string(),
+                            "error_code": ExitCode::WriteFailed.code()
+                        }));
+                        return Err(e.into());
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            log_event(LogLevel::Error, serde_json::json!({
+                "tool":"emit_files",
+                "file": file,
+                "error": e.to_string(),
+                "error_code": ExitCode::WriteFailed.code()
+            }));
+            return Err(e.into());
+        }
+    }
+    let done_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_millis() as i128).unwrap_or(0);
+    log_event(LogLevel::Info, serde_json::json!({
+        "tool":"document",
+        "file": file,
+        "doc_id": doc_id,
+        "status":"finished",
+        "duration_ms": (done_ms - started_ms).max(0)
+    }));
+    Ok(Some(PdfConversion {
+        doc_id: doc_id.clone(),
+        rendered,
+        meta,
+        bench: bench_sample,
+        report: ReportRow {
+            doc_id: doc_id.clone(),
+            page_count,
+            coverage: metrics.character_coverage,
+            leak_rate: metrics.leak_rate,
+            split_violations: metrics.split_violations,
+            ocr_ran,
+            pasal: promoted.found.pasal,
+            bab: promoted.found.bab,
+        },
+        quality_failed: !violations.is_empty(),
+        leaked: opts.fail_on_leak && metrics.leak_rate > 0.0,
+    }))
+}
+
+/// `convert_pdf` over several documents, in order, stopping at the first error. `progress`
+/// sees `Started`, one `Document` before each conversion, then `Finished`; documents skipped
+/// as `EmptyDocument` leave no result.
+pub fn convert_pdf_batch(docs: &[PdfDocument], opts: &PdfOptions, log: LogHook, progress: ProgressHook) -> Result<Vec<PdfConversion>, PipelineError> {
+    let report = |ev: ProgressEvent| {
+        if let Some(hook) = progress {
+            hook(ev);
+        }
+    };
+    let total = docs.len();
+    report(ProgressEvent::Started { total });
+    let mut results = Vec::new();
+    for (index, doc) in docs.iter().enumerate() {
+        report(ProgressEvent::Document { index, total, doc_id: doc.doc_id.clone() });
+        results.extend(convert_pdf(doc, opts, log)?);
+    }
+    report(ProgressEvent::Finished { total });
+    Ok(results)
+}
+
+#[derive(Debug, Error)]
+pub enum EmitError {
+    #[error("WriteFailed: {0}")]
+    WriteFailed(String),
+}
+
+/// Process exit codes of `legalpdf2md`, the single source for `PipelineError::code` and
+/// `--print-codes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ExitCode {
+    Success = 0,
+    /// No input PDFs matched, or Poppler could not read a document.
+    Failure = 1,
+    MissingDependency = 2,
+    PrdInvalid = 3,
+    InvalidArgument = 4,
+    StructureNotFound = 5,
+    WriteFailed = 6,
+    QualityGateFailed = 7,
+    NotIdempotent = 8,
+    LeakDetected = 9,
+}
+
+impl ExitCode {
+    /// Every code, in ascending order.
+    pub const ALL: [ExitCode; 10] = [
+        ExitCode::Success,
+        ExitCode::Failure,
+        ExitCode::MissingDependency,
+        ExitCode::PrdInvalid,
+        ExitCode::InvalidArgument,
+        ExitCode::StructureNotFound,
+        ExitCode::WriteFailed,
+        ExitCode::QualityGateFailed,
+        ExitCode::NotIdempotent,
+        ExitCode::LeakDetected,
+    ];
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// One-line description for `--print-codes`.
+    pub fn meaning(self) -> &'static str {
+        match self {
+            ExitCode::Success => "success",
+            ExitCode::Failure => "no input PDFs found, or Poppler failed on a document",
+            ExitCode::MissingDependency => "required tool missing (pdftotext, pdfinfo, tesseract, ...)",
+            ExitCode::PrdInvalid => "PRD file missing or invalid",
+            ExitCode::InvalidArgument => "invalid command-line argument",
+            ExitCode::StructureNotFound => "--strict: expected legal structure not found",
+            ExitCode::WriteFailed => "output could not be written",
+            ExitCode::QualityGateFailed => "quality gate (--min-coverage, ...) failed",
+            ExitCode::NotIdempotent => "--verify-idempotent --strict: second pass changed the output",
+            ExitCode::LeakDetected => "--fail-on-leak: header/footer lines left in the output",
+        }
+    }
+}
+
+/// Every way a conversion run can fail. `code()` is the CLI exit-code contract.
+/// Library steps keep their own error types (`PrdError`, `EnumerateError`, `PopplerError`,
+/// `EmitError`); this is the run-level error a batch driver such as `legalpdf2md` builds from
+/// them via `From`, plus the quality/structure/leak failures it detects itself.
+#[derive(Debug, Error)]
+pub enum PipelineError {
+    #[error(transparent)]
+    NoFilesFound(#[from] EnumerateError),
+    #[error(transparent)]
+    Poppler(#[from] PopplerError),
+    #[error("MissingDependency: {}", missing.join(", "))]
+    MissingDependency { missing: Vec<String> },
+    #[error(transparent)]
+    PrdInvalid(#[from] PrdError),
+    #[error("InvalidArgument: {0}")]
+    InvalidArgument(String),
+    #[error("StructureNotFound")]
+    StructureNotFound,
+    #[error(transparent)]
+    WriteFailed(#[from] EmitError),
+    #[error("QualityGateFailed: {failed_documents} document(s)")]
+    QualityGateFailed { failed_documents: usize },
+    #[error("NotIdempotent: second pass changes line {line}")]
+    NotIdempotent { line: usize },
+    #[error("LeakDetected: {failed_documents} document(s)")]
+    LeakDetected { failed_documents: usize },
+}
+
+impl PipelineError {
+    /// Process exit code for this error.
+    pub fn code(&self) -> i32 {
+        self.exit_code().code()
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            PipelineError::NoFilesFound(_) | PipelineError::Poppler(_) => ExitCode::Failure,
+            PipelineError::MissingDependency { .. } => ExitCode::MissingDependency,
+            PipelineError::PrdInvalid(_) => ExitCode::PrdInvalid,
+            PipelineError::InvalidArgument(_) => ExitCode::InvalidArgument,
+            PipelineError::StructureNotFound => ExitCode::StructureNotFound,
+            PipelineError::WriteFailed(_) => ExitCode::WriteFailed,
+            PipelineError::QualityGateFailed { .. } => ExitCode::QualityGateFailed,
+            PipelineError::NotIdempotent { .. } => ExitCode::NotIdempotent,
+            PipelineError::LeakDetected { .. } => ExitCode::LeakDetected,
+        }
+    }
+
+    /// Short error label used in JSON logs (`"error": ...`).
+    pub fn label(&self) -> &'static str {
+        match self {
+            PipelineError::NoFilesFound(_) => "NoFilesFound",
+            PipelineError::Poppler(PopplerError::FileNotFound(_)) => "FileNotFound",
+            PipelineError::Poppler(PopplerError::EncryptedPDF(_)) => "EncryptedPDF",
+            PipelineError::Poppler(PopplerError::EmptyDocument(_)) => "EmptyDocument",
+            PipelineError::Poppler(PopplerError::Other(_)) => "PopplerError",
+            PipelineError::MissingDependency { .. } => "MissingDependency",
+            PipelineError::PrdInvalid(_) => "PRDInvalid",
+            PipelineError::InvalidArgument(_) => "InvalidArgument",
+            PipelineError::StructureNotFound => "StructureNotFound",
+            PipelineError::WriteFailed(_) => "WriteFailed",
+            PipelineError::QualityGateFailed { .. } => "QualityGateFailed",
+            PipelineError::NotIdempotent { .. } => "NotIdempotent",
+            PipelineError::LeakDetected { .. } => "LeakDetected",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmitPaths {
+    pub md_path: String,
+    pub meta_path: String,
+}
+
+/// Write `bytes` to `path` and fsync it, so a later rename never exposes a truncated file.
+fn write_synced(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write as _;
+    let mut f = std::fs::File::create(path)?;
+    f.write_all(bytes)?;
+    f.sync_all()
+}
+
+/// Persist renames in `dir` across a crash. Directories cannot be opened for fsync off Unix.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Whether a process with this PID is still running.
+#[cfg(target_os = "linux")]
+fn pid_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pid_alive(pid: u32) -> bool {
+    // `kill -0` only probes; if it cannot run at all, assume alive and keep the file
+    Command::new("kill").arg("-0").arg(pid.to_string()).output().map(|o| o.status.success()).unwrap_or(true)
+}
+
+/// Remove temp files left in `outdir` by emits that died between write and rename
+/// (`*.{md,txt,html}.tmp.PID`, `*.meta.json.tmp.PID`) whose PID is no longer running.
+/// Files of this process and other live processes are kept. Returns the removed paths.
+pub fn sweep_temp_files(outdir: &Path) -> Vec<PathBuf> {
+    let exts: Vec<&str> = OutputFormat::ALL.iter().map(|f| f.extension()).chain(["meta\\.json"]).collect();
+    let re = Regex::new(&format!(r"^.+\.(?:{})\.tmp\.(\d+)$", exts.join("|"))).unwrap();
+    let me = std::process::id();
+    let Ok(entries) = std::fs::read_dir(outdir) else { return Vec::new() };
+    let mut removed = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name();
+        let Some(pid) = name.to_str().and_then(|n| re.captures(n)).and_then(|c| c[1].parse::<u32>().ok()) else { continue };
+        if pid == me || pid_alive(pid) || !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+        if std::fs::remove_file(entry.path()).is_ok() {
+            removed.push(entry.path());
+        }
+    }
+    removed.sort();
+    removed
+}
+
+/// Atomically write markdown and meta JSON into outdir with doc_id stem.
+pub fn emit_files(markdown: &str, meta: &serde_json::Value, outdir: &str, doc_id: &str) -> Result<EmitPaths, EmitError> {
+    emit_files_as(markdown, meta, outdir, doc_id, OutputFormat::Md)
+}
+
+/// Like `emit_files`, but the document file extension follows `fmt` (`md_path` holds that path).
+pub fn emit_files_as(content: &str, meta: &serde_json::Value, outdir: &str, doc_id: &str, fmt: OutputFormat) -> Result<EmitPaths, EmitError> {
+    std::fs::create_dir_all(outdir).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    let ext = fmt.extension();
+    let md_path = Path::new(outdir).join(format!("{}.{}", doc_id, ext));
+    let meta_path = Path::new(outdir).join(format!("{}.meta.json", doc_id));
+
+    // Write temp files then rename
+    let pid = std::process::id();
+    let md_tmp = md_path.with_extension(format!("{}.tmp.{}", ext, pid));
+    let meta_tmp = meta_path.with_extension(format!("meta.json.tmp.{}", pid));
+
+    // fsync temp files before rename and the directory after, so a crash leaves old or new, never empty
+    write_synced(&md_tmp, content.as_bytes()).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    let meta_bytes = serde_json::to_vec_pretty(meta).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    write_synced(&meta_tmp, &meta_bytes).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+
+    std::fs::rename(&md_tmp, &md_path).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    std::fs::rename(&meta_tmp, &meta_path).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    sync_dir(Path::new(outdir)).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+
+    Ok(EmitPaths { md_path: md_path.to_string_lossy().to_string(), meta_path: meta_path.to_string_lossy().to_string() })
+}
+
+/// Schema version of `EventRecord`; bump on breaking changes to the event layout.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Pipeline event streamed with `--json-events` (one JSON object per line on stdout).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A pipeline step completed; `data` carries the step's log fields.
+    Step { tool: String, data: serde_json::Value },
+    /// A step failed; `error_code` is set when the failure maps to an exit code.
+    Error { tool: String, error: String, error_code: Option<i64>, data: serde_json::Value },
+    /// All enumerated documents were processed.
+    Finished { documents: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventRecord {
+    pub schema: u32,
+    pub ts_ms: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl Event {
+    /// Classify a `{"tool": ..., ...}` log line; lines carrying `error`/`error_code` become `Error`.
+    pub fn from_log(value: &serde_json::Value) -> Event {
+        let mut data = value.as_object().cloned().unwrap_or_default();
+        let tool = data
+            .remove("tool")
+            .and_then(|t| t.as_str().map(|s| s.to_string()))
+            .unwrap_or_default();
+        if !data.contains_key("error") && !data.contains_key("error_code") {
+            return Event::Step { tool, data: serde_json::Value::Object(data) };
+        }
+        let error = match data.remove("error") {
+            Some(serde_json::Value::String(s)) => s,
+            Some(other) => other.to_string(),
+            None => "Failed".to_string(),
+        };
+        let error_code = data.remove("error_code").and_then(|c| c.as_i64());
+        Event::Error { tool, error, error_code, data: serde_json::Value::Object(data) }
+    }
+}
+
+impl EventRecord {
+    /// Wrap an event with the current schema version and wall-clock timestamp.
+    pub fn now(event: Event) -> EventRecord {
+        let ts_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        EventRecord { schema: EVENT_SCHEMA_VERSION, ts_ms, event }
+    }
+}
+
+/// Batch progress for UIs (`--progress` bar) and `process_text_batch` callers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "progress", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// Enumeration is done; `total` documents will be processed.
+    Started { total: usize },
+    /// Document `index` (0-based) of `total` is about to be processed.
+    Document { index: usize, total: usize, doc_id: String },
+    /// The batch is done.
+    Finished { total: usize },
+}
+
+/// Optional progress callback, e.g. `Some(&|ev| eprintln!("{:?}", ev))`.
+pub type ProgressHook<'a> = Option<&'a dyn Fn(ProgressEvent)>;
+
+/// Step durations of one converted document, collected for `--bench`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BenchSample {
+    pub pages: usize,
+    /// Pages sent through OCR.
+    pub ocr_pages: usize,
+    pub extract_ms: u64,
+    pub ocr_ms: u64,
+    /// Suppression, cleanup, heading promotion and metrics.
+    pub cleanup_ms: u64,
+}
+
+/// Mean and nearest-rank 95th percentile, in milliseconds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StepStats {
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+}
+
+/// Total milliseconds spent per pipeline step across the batch.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BenchBreakdown {
+    pub extraction_ms: u64,
+    pub ocr_ms: u64,
+    pub cleanup_ms: u64,
+}
+
+/// Batch throughput printed by `--bench`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BenchReport {
+    pub documents: usize,
+    pub pages: usize,
+    pub wall_ms: u64,
+    pub pages_per_second: f64,
+    /// Per-page extraction time; each document contributes its extraction time spread evenly
+    /// over its pages.
+    pub extract_per_page: StepStats,
+    /// Per-page OCR time over OCR-ed pages only, spread the same way.
+    pub ocr_per_page: StepStats,
+    pub breakdown: BenchBreakdown,
+}
+
+fn step_stats(mut values: Vec<f64>) -> StepStats {
+    if values.is_empty() {
+        return StepStats::default();
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mean_ms = values.iter().sum::<f64>() / values.len() as f64;
+    let rank = ((values.len() as f64) * 0.95).ceil() as usize;
+    StepStats { mean_ms, p95_ms: values[rank.clamp(1, values.len()) - 1] }
+}
+
+/// Aggregate per-document samples into a `BenchReport`; `wall_ms` is the whole batch.
+pub fn bench_report(samples: &[BenchSample], wall_ms: u64) -> BenchReport {
+    let per_page = |ms: u64, pages: usize| std::iter::repeat_n(ms as f64 / pages as f64, pages);
+    let pages: usize = samples.iter().map(|s| s.pages).sum();
+    BenchReport {
+        documents: samples.len(),
+        pages,
+        wall_ms,
+        pages_per_second: if wall_ms > 0 { pages as f64 * 1000.0 / wall_ms as f64 } else { 0.0 },
+        extract_per_page: step_stats(samples.iter().flat_map(|s| per_page(s.extract_ms, s.pages)).collect()),
+        ocr_per_page: step_stats(samples.iter().flat_map(|s| per_page(s.ocr_ms, s.ocr_pages)).collect()),
+        breakdown: BenchBreakdown {
+            extraction_ms: samples.iter().map(|s| s.extract_ms).sum(),
+            ocr_ms: samples.iter().map(|s| s.ocr_ms).sum(),
+            cleanup_ms: samples.iter().map(|s| s.cleanup_ms).sum(),
+        },
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MarkdownPart {
+    /// File name within the document directory, e.g. `bab-01.md`.
+    pub file_name: String,
+    pub title: String,
+    pub content: String,
+}
+
+/// Split promoted markdown at each `## BAB` heading.
+/// Content before the first BAB (if any) becomes `00-preamble.md`; BABs are `bab-01.md`, `bab-02.md`, ...
+pub fn split_by_bab(markdown: &str) -> Vec<MarkdownPart> {
+    let re_bab = Regex::new(r"^##\s+BAB\b").unwrap();
+    let mut parts: Vec<MarkdownPart> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut current_title: Option<String> = None;
+    let mut bab_no = 0usize;
+
+    let mut flush = |title: Option<String>, lines: &mut Vec<&str>, bab_no: usize| {
+        let content = lines.join("\n");
+        lines.clear();
+        match title {
+            Some(t) => parts.push(MarkdownPart { file_name: format!("bab-{:02}.md", bab_no), title: t, content }),
+            None if !content.trim().is_empty() => {
+                parts.push(MarkdownPart { file_name: "00-preamble.md".to_string(), title: "Pembukaan".to_string(), content })
+            }
+            None => {}
+        }
+    };
+
+    for line in markdown.lines() {
+        if re_bab.is_match(line) {
+            flush(current_title.take(), &mut current, bab_no);
+            bab_no += 1;
+            current_title = Some(line.trim_start_matches('#').trim().to_string());
+        }
+        current.push(line);
+    }
+    flush(current_title.take(), &mut current, bab_no);
+    parts
+}
+
+/// Write split parts plus an `index.md` linking them into `dir`. Returns written file paths (index last).
+pub fn emit_parts(parts: &[MarkdownPart], dir: &str, doc_id: &str) -> Result<Vec<String>, EmitError> {
+    std::fs::create_dir_all(dir).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    let mut written = Vec::with_capacity(parts.len() + 1);
+    let mut index = format!("# {}\n\n", doc_id);
+    for part in parts {
+        let path = Path::new(dir).join(&part.file_name);
+        std::fs::write(&path, &part.content).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+        index.push_str(&format!("- [{}]({})\n", part.title, part.file_name));
+        written.push(path.to_string_lossy().to_string());
+    }
+    let index_path = Path::new(dir).join("index.md");
+    std::fs::write(&index_path, index).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    written.push(index_path.to_string_lossy().to_string());
+    Ok(written)
+}
+
+/// True when both outputs exist and the meta records `input_sha256` equal to `sha256`.
+/// Older metas without the field (or unreadable ones) count as stale.
+pub fn is_up_to_date(md_path: &Path, meta_path: &Path, sha256: &str) -> bool {
+    if !md_path.is_file() || !meta_path.is_file() {
+        return false;
+    }
+    std::fs::read(meta_path)
+        .ok()
+        .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok())
+        .and_then(|m| m.get("input_sha256").and_then(|v| v.as_str()).map(|s| s == sha256))
+        .unwrap_or(false)
+}
+
+/// One document of a `--combine` corpus file.
+#[derive(Debug, Clone)]
+pub struct CombinedDoc {
+    pub doc_id: String,
+    pub content: String,
+    pub meta: serde_json::Value,
+}
+
+/// Concatenate documents in the given order; each starts with a `<!-- doc:ID -->` marker
+/// followed by a `# ID` heading.
+pub fn combine_markdown(docs: &[CombinedDoc]) -> String {
+    let mut out = String::new();
+    for (i, doc) in docs.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("<!-- doc:{} -->\n# {}\n\n", doc.doc_id, doc.doc_id));
+        out.push_str(doc.content.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Write the combined markdown to `output` and the metas as a JSON array to
+/// `combined.meta.json` next to it (temp file + rename, like `emit_files`).
+pub fn emit_combined(docs: &[CombinedDoc], output: &Path) -> Result<EmitPaths, EmitError> {
+    let dir = output.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    let meta_path = dir.join("combined.meta.json");
+    let metas: Vec<&serde_json::Value> = docs.iter().map(|d| &d.meta).collect();
+    let meta_bytes = serde_json::to_vec_pretty(&metas).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+
+    let pid = std::process::id();
+    let md_tmp = dir.join(format!(".combined.md.tmp.{}", pid));
+    let meta_tmp = dir.join(format!(".combined.meta.json.tmp.{}", pid));
+    write_synced(&md_tmp, combine_markdown(docs).as_bytes()).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    write_synced(&meta_tmp, &meta_bytes).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    std::fs::rename(&md_tmp, output).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    std::fs::rename(&meta_tmp, &meta_path).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    sync_dir(dir).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+
+    Ok(EmitPaths { md_path: output.to_string_lossy().to_string(), meta_path: meta_path.to_string_lossy().to_string() })
+}
+
+/// One document's line in the `--report` quality overview.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReportRow {
+    pub doc_id: String,
+    pub page_count: usize,
+    /// `Metrics::character_coverage`.
+    pub coverage: f64,
+    pub leak_rate: f64,
+    pub split_violations: usize,
+    pub ocr_ran: bool,
+    pub pasal: usize,
+    pub bab: usize,
+}
+
+const REPORT_COLUMNS: [&str; 8] = ["doc_id", "page_count", "coverage", "leak_rate", "split_violations", "ocr_ran", "pasal", "bab"];
+
+/// CSV with a header row; fields are quoted only when they contain `,`, `"` or a newline.
+pub fn render_report_csv(rows: &[ReportRow]) -> String {
+    let field = |s: String| if s.contains([',', '"', '\n']) { format!("\"{}\"", s.replace('"', "\"\"")) } else { s };
+    let mut out = REPORT_COLUMNS.join(",");
+    out.push('\n');
+    for r in rows {
+        let cells = [
+            field(r.doc_id.clone()),
+            r.page_count.to_string(),
+            r.coverage.to_string(),
+            r.leak_rate.to_string(),
+            r.split_violations.to_string(),
+            r.ocr_ran.to_string(),
+            r.pasal.to_string(),
+            r.bab.to_string(),
+        ];
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Write `<doc_id>.review.md` (see `render_review`) into `outdir`; temp file + rename.
+pub fn emit_review(review: &str, outdir: &str, doc_id: &str) -> Result<PathBuf, EmitError> {
+    let dir = Path::new(outdir);
+    std::fs::create_dir_all(dir).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    let path = dir.join(format!("{}.review.md", doc_id));
+    let tmp = dir.join(format!(".{}.review.md.tmp.{}", doc_id, std::process::id()));
+    write_synced(&tmp, review.as_bytes()).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    std::fs::rename(&tmp, &path).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    Ok(path)
+}
+
+/// Write the batch report to `path`, worst coverage first (ties by `doc_id`): a JSON array when
+/// the extension is `.json`, CSV otherwise. Temp file + rename, like `emit_files`.
+pub fn write_report(rows: &[ReportRow], path: &Path) -> Result<(), EmitError> {
+    let mut rows = rows.to_vec();
+    rows.sort_by(|a, b| a.coverage.total_cmp(&b.coverage).then_with(|| a.doc_id.cmp(&b.doc_id)));
+    let json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let bytes = if json {
+        serde_json::to_vec_pretty(&rows).map_err(|e| EmitError::WriteFailed(e.to_string()))?
+    } else {
+        render_report_csv(&rows).into_bytes()
+    };
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    let tmp = dir.join(format!(".report.tmp.{}", std::process::id()));
+    write_synced(&tmp, &bytes).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    std::fs::rename(&tmp, path).map_err(|e| EmitError::WriteFailed(e.to_string()))?;
+    Ok(())
 }
 
 // Utility to compute sha256 hex
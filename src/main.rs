@@ -1,13 +1,142 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use legalpdf_to_md::{check_deps, compute_metrics, detect_suspect_pages, emit_files, enumerate_pdfs, law_cleanup, merge_pages, nala_help_for, ocr_tesseract, poppler_extract, promote_legal_headings, suppress_repeated_lines, validate_prd, DepsResult, PopplerError, SuppressorConfig, sha256_hex};
+use legalpdf_to_md::{apply_ignore_file, bench_report, check_deps_with, convert_pdf_batch, dedup_by_content, emit_combined, check_quality, djvu_extract, djvu_glob, document_title, expand_name_template, glob_input_root, enumerate_pdfs, exclude_paths, filter_modified_since, is_djvu, is_up_to_date, nala_help_for, pdf_verdict, parse_doc_identifier, parse_since, poppler_extract_info, prepend_title, process_text, read_input_list, render, render_html, slugify, split_text_pages, sweep_temp_files, validate_name_template, validate_prd, version_report, write_report, wrap_markdown, BenchSample, CombinedDoc, ConvertOptions, DepsResult, EmitError, EnumerateError, Event, EventRecord, ExitCode, ExtractOptions, IGNORE_FILE_NAME, LawMode, LogLevel, OcrDpi, OcrImageFormat, OcrOptions, OutputFormat, PdfDocument, PdfOptions, PipelineError, PopplerError, PrdRoot, ProgressEvent, QualityThresholds, ReportRow, SuppressorConfig, sha256_hex, set_ocr_threads};
 use std::fs;
+use std::io::Write;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use std::collections::HashSet;
 use regex::Regex;
 
+/// JSON log sink: records at or above the configured level go to stderr (and the
+/// `--log-file`, if any); with `--json-events` every record is also streamed to stdout
+/// as a versioned `EventRecord`.
+struct Logger {
+    json_events: bool,
+    /// `--quiet` (default) stops at `Info`, per-document start/finish; `--verbose` is `Debug`,
+    /// every pipeline step with timings.
+    level: LogLevel,
+    started: std::time::Instant,
+    log_file: Option<fs::File>,
+    /// `--progress` bar on stderr; log lines are printed through it so neither garbles the other.
+    progress: Option<ProgressBar>,
+}
+
+impl Logger {
+    fn log_event(&self, level: LogLevel, value: serde_json::Value) {
+        if level <= self.level {
+            let line = if self.level == LogLevel::Debug {
+                let mut timed = value.clone();
+                if let Some(obj) = timed.as_object_mut() {
+                    obj.insert("elapsed_ms".to_string(), serde_json::json!(self.started.elapsed().as_millis() as u64));
+                }
+                timed.to_string()
+            } else {
+                value.to_string()
+            };
+            match &self.progress {
+                Some(bar) => bar.suspend(|| eprintln!("{}", line)),
+                None => eprintln!("{}", line),
+            }
+            if let Some(mut f) = self.log_file.as_ref() {
+                // one write + flush per record so a crash keeps everything logged so far
+                let _ = writeln!(f, "{}", line).and_then(|_| f.flush());
+            }
+        }
+        self.event(Event::from_log(&value));
+    }
+
+    /// Log a rejected CLI flag value and return the matching `InvalidArgument` (exit 4).
+    fn cli_error(&self, flag: &str, value: &str, msg: String) -> PipelineError {
+        self.log_event(LogLevel::Error, serde_json::json!({
+            "tool":"cli",
+            "flag": flag,
+            "value": value,
+            "error": msg,
+            "error_code": ExitCode::InvalidArgument.code()
+        }));
+        PipelineError::InvalidArgument(msg)
+    }
+
+    /// A result line for stdout (`--dry-run`, `--validate-only`, `--bench`). With `--json-events`
+    /// stdout is the event stream, so the line goes out as a `step` event for `tool` instead.
+    fn output(&self, tool: &str, mut value: serde_json::Value) {
+        if !self.json_events {
+            println!("{}", value);
+            return;
+        }
+        if let Some(obj) = value.as_object_mut() {
+            obj.remove("tool");
+        }
+        self.event(Event::Step { tool: tool.to_string(), data: value });
+    }
+
+    fn event(&self, event: Event) {
+        if !self.json_events {
+            return;
+        }
+        if let Ok(line) = serde_json::to_string(&EventRecord::now(event)) {
+            println!("{}", line);
+        }
+    }
+}
+
 fn main() {
     // Simple CLI flags parsing
     let args: Vec<String> = std::env::args().collect();
+    // --quiet / --verbose: the later flag wins
+    let flag_pos = |name: &str| args.iter().rposition(|a| a == name);
+    let level = match (flag_pos("--quiet"), flag_pos("--verbose")) {
+        (q, Some(v)) if q.map(|q| v > q).unwrap_or(true) => LogLevel::Debug,
+        _ => LogLevel::Info,
+    };
+    // --log-file PATH: append-mode NDJSON mirror of stderr; an unopenable file only warns
+    let log_file_path = args.iter().position(|a| a == "--log-file").and_then(|i| args.get(i + 1)).cloned();
+    let mut log_file_error: Option<String> = None;
+    let log_file = log_file_path.as_ref().and_then(|p| {
+        fs::OpenOptions::new().create(true).append(true).open(p).map_err(|e| log_file_error = Some(e.to_string())).ok()
+    });
+    let logger = Logger {
+        json_events: args.iter().any(|a| a == "--json-events"),
+        level,
+        started: std::time::Instant::now(),
+        log_file,
+        progress: args.iter().any(|a| a == "--progress").then(|| {
+            let bar = ProgressBar::with_draw_target(Some(0), ProgressDrawTarget::stderr());
+            bar.set_style(
+                ProgressStyle::with_template("{bar:30} {pos}/{len} {msg}").unwrap_or_else(|_| ProgressStyle::default_bar()),
+            );
+            bar
+        }),
+    };
+    if let Some(e) = log_file_error {
+        logger.log_event(LogLevel::Warn, serde_json::json!({
+            "tool":"log_file",
+            "file": log_file_path,
+            "warning": format!("cannot open log file, continuing without it: {}", e)
+        }));
+    }
+    // Each failure is logged where it happens; the exit code is decided only here.
+    if let Err(e) = run(&logger, &args) {
+        std::process::exit(e.exit_code().code());
+    }
+}
+
+fn run(logger: &Logger, args: &[String]) -> Result<(), PipelineError> {
+    if args.iter().any(|a| a == "--print-codes") {
+        for code in ExitCode::ALL {
+            println!("{}\t{}", code.code(), code.meaning());
+        }
+        return Ok(());
+    }
+    if args.iter().any(|a| a == "--version") {
+        let v = version_report();
+        let show = |o: &Option<String>| o.clone().unwrap_or_else(|| "not found".to_string());
+        println!("legalpdf2md {}", v.crate_version);
+        println!("pdftotext {}", show(&v.pdftotext));
+        println!("tesseract {}", show(&v.tesseract));
+        return Ok(());
+    }
     let dump_steps = args.iter().any(|a| a == "--dump-steps");
     // OCR flag supports: --with-ocr, --with-ocr=on, --with-ocr=off
     let mut with_ocr_forced: Option<bool> = None;
@@ -16,15 +145,77 @@ fn main() {
         if val == "--with-ocr" || val == "--with-ocr=on" { with_ocr_forced = Some(true); }
         else if val == "--with-ocr=off" { with_ocr_forced = Some(false); }
     }
+    // --ocr-all: OCR every page, not only the suspect ones
+    let ocr_all = args.iter().any(|a| a == "--ocr-all");
+    if ocr_all && with_ocr_forced == Some(false) {
+        return Err(logger.cli_error("--ocr-all", "--with-ocr=off", "--ocr-all conflicts with --with-ocr=off".to_string()));
+    }
     let strict = args.iter().any(|a| a == "--strict");
-    let mut law_mode = String::from("auto");
+    let verify_idempotent = args.iter().any(|a| a == "--verify-idempotent");
+    let page_markers = args.iter().any(|a| a == "--page-markers");
+    let footnotes_on = args.iter().any(|a| a == "--footnotes");
+    let strip_gibberish_on = args.iter().any(|a| a == "--strip-gibberish");
+    // --djvu: also enumerate `.djvu` next to each `.pdf` glob and extract them with djvutxt/ddjvu
+    let djvu_on = args.iter().any(|a| a == "--djvu");
+    let emit_ast = args.iter().any(|a| a == "--emit-ast");
+    // --review: <doc_id>.review.md with each raw page next to its cleaned markdown
+    let review = args.iter().any(|a| a == "--review");
+    // `# title` on top of the markdown; --combine already opens each document with `# doc_id`
+    let title_on = !args.iter().any(|a| a == "--no-title" || a == "--combine");
+    // Page subset: --pages FIRST-LAST (1-based, inclusive)
+    let mut page_subset: Option<(usize, usize)> = None;
+    if let Some(pos) = args.iter().position(|a| a == "--pages") {
+        let val = args.get(pos + 1).map(|s| s.as_str()).unwrap_or("");
+        let parsed = val.split_once('-').and_then(|(a, b)| Some((a.trim().parse::<usize>().ok()?, b.trim().parse::<usize>().ok()?)));
+        match parsed {
+            Some((first, last)) if first >= 1 && first <= last => page_subset = Some((first, last)),
+            _ => return Err(logger.cli_error("--pages", val, "--pages expects FIRST-LAST with 1 <= FIRST <= LAST".to_string())),
+        }
+    }
+    let extract_opts = ExtractOptions {
+        layout: true,
+        nopgbrk: true,
+        two_column: args.iter().any(|a| a == "--two-column"),
+        pages: page_subset,
+        retry_raw: args.iter().any(|a| a == "--retry-raw"),
+    };
+    // Plan only: enumerate + slugs + output paths, no extraction/OCR/emit
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    // --validate-only: extract + classify each file and print a verdict; no cleanup, OCR or emit
+    let validate_only = args.iter().any(|a| a == "--validate-only");
+    let skip_existing = args.iter().any(|a| a == "--skip-existing");
+    let mut law_mode_arg = LawMode::Auto;
     if let Some(pos) = args.iter().position(|a| a == "--law-mode") {
         if let Some(val) = args.get(pos + 1) {
             if !val.starts_with("--") {
-                law_mode = val.clone();
+                law_mode_arg = LawMode::parse(val).ok_or_else(|| {
+                    let known: Vec<&str> = LawMode::ALL.iter().map(|m| m.as_str()).collect();
+                    logger.cli_error("--law-mode", val, format!("--law-mode expects {}", known.join("|")))
+                })?;
             }
         }
     }
+    // --list-langs: what the installed tesseract can OCR
+    if args.iter().any(|a| a == "--list-langs") {
+        let out = std::process::Command::new("tesseract").arg("--list-langs").output();
+        return match out {
+            Ok(o) => {
+                // older tesseract versions print the list on stderr
+                print!("{}{}", String::from_utf8_lossy(&o.stdout), String::from_utf8_lossy(&o.stderr));
+                Ok(())
+            }
+            Err(_) => {
+                logger.log_event(LogLevel::Error, serde_json::json!({
+                    "tool":"list_langs",
+                    "error":"MissingDependency",
+                    "missing": ["tesseract"],
+                    "error_code": ExitCode::MissingDependency.code()
+                }));
+                Err(PipelineError::MissingDependency { missing: vec!["tesseract".to_string()] })
+            }
+        };
+    }
+    // "auto" samples the first OCR page to choose ind / eng / ind+eng
     let mut ocr_lang = String::from("ind");
     if let Some(pos) = args.iter().position(|a| a == "--ocr-lang") {
         if let Some(val) = args.get(pos + 1) {
@@ -33,11 +224,113 @@ fn main() {
             }
         }
     }
-    // OCR DPI
-    let mut ocr_dpi: u32 = 300;
+    // OCR DPI: number (>=72) or "auto" (per-page from page size)
+    let mut ocr_dpi = OcrDpi::Fixed(300);
     if let Some(pos) = args.iter().position(|a| a == "--ocr-dpi") {
         if let Some(val) = args.get(pos + 1) {
-            if let Ok(n) = val.parse::<u32>() { ocr_dpi = n.max(72); }
+            if val == "auto" { ocr_dpi = OcrDpi::Auto; }
+            else if let Ok(n) = val.parse::<u32>() { ocr_dpi = OcrDpi::Fixed(n.max(72)); }
+        }
+    }
+    // Tesseract page segmentation / engine modes
+    fn u8_flag(logger: &Logger, args: &[String], flag: &str, default: u8, max: u8) -> Result<u8, PipelineError> {
+        let Some(pos) = args.iter().position(|a| a == flag) else { return Ok(default); };
+        let raw = args.get(pos + 1).map(|s| s.as_str()).unwrap_or("");
+        match raw.parse::<u8>() {
+            Ok(n) if n <= max => Ok(n),
+            _ => Err(logger.cli_error(flag, raw, format!("{} expects an integer in 0-{}", flag, max))),
+        }
+    }
+    let ocr_psm = u8_flag(logger, args, "--ocr-psm", 4, 13)?;
+    let ocr_oem = u8_flag(logger, args, "--ocr-oem", 1, 3)?;
+    // OCR render format: png (default) | jpeg, with --ocr-jpeg-quality 1-100
+    let jpeg_quality = u8_flag(logger, args, "--ocr-jpeg-quality", OcrImageFormat::DEFAULT_JPEG_QUALITY, 100)?;
+    let ocr_image_format = match args.iter().position(|a| a == "--ocr-image-format").and_then(|i| args.get(i + 1)).map(|s| s.as_str()) {
+        None | Some("png") => OcrImageFormat::Png,
+        Some("jpeg") | Some("jpg") => OcrImageFormat::Jpeg { quality: jpeg_quality.max(1) },
+        Some(other) => return Err(logger.cli_error("--ocr-image-format", other, format!("--ocr-image-format expects png or jpeg, got {}", other))),
+    };
+    if ocr_image_format == OcrImageFormat::Png && args.iter().any(|a| a == "--ocr-jpeg-quality") {
+        return Err(logger.cli_error("--ocr-jpeg-quality", "", "--ocr-jpeg-quality requires --ocr-image-format jpeg".to_string()));
+    }
+    // Typed flag value; an unparsable value is a CLI error (exit 4)
+    fn parse_flag<T: std::str::FromStr>(logger: &Logger, args: &[String], flag: &str) -> Result<Option<T>, PipelineError> {
+        let Some(pos) = args.iter().position(|a| a == flag) else { return Ok(None); };
+        let raw = args.get(pos + 1).map(|s| s.as_str()).unwrap_or("");
+        match raw.parse::<T>() {
+            Ok(v) => Ok(Some(v)),
+            Err(_) => Err(logger.cli_error(flag, raw, format!("{} expects a number", flag))),
+        }
+    }
+    // Quality gate (absent thresholds = no gate)
+    let quality = QualityThresholds {
+        min_coverage: parse_flag::<f64>(logger, args, "--min-coverage")?,
+        max_leak_rate: parse_flag::<f64>(logger, args, "--max-leak-rate")?,
+        max_split_violations: parse_flag::<usize>(logger, args, "--max-split-violations")?,
+    };
+    let continue_on_error = args.iter().any(|a| a == "--continue-on-error");
+    let fail_on_leak = args.iter().any(|a| a == "--fail-on-leak");
+    // --wrap N: re-wrap body paragraphs at N columns after metrics (0 = off)
+    let wrap = parse_flag::<usize>(logger, args, "--wrap")?.unwrap_or(0);
+    // --suspect-density D: suspect pages are those under D chars per square inch, not under 64 chars
+    let suspect_density = parse_flag::<f64>(logger, args, "--suspect-density")?;
+    if let Some(d) = suspect_density.filter(|d| !(d.is_finite() && *d > 0.0)) {
+        return Err(logger.cli_error("--suspect-density", &d.to_string(), format!("--suspect-density must be > 0, got {}", d)));
+    }
+    // --ocr-threads N: at most N pdftoppm/tesseract processes at once, across all documents
+    if let Some(n) = parse_flag::<usize>(logger, args, "--ocr-threads")? {
+        if n == 0 {
+            return Err(logger.cli_error("--ocr-threads", "0", "--ocr-threads must be at least 1".to_string()));
+        }
+        set_ocr_threads(n);
+    }
+    let max_suppress_per_page = parse_flag::<usize>(logger, args, "--max-suppress-per-page")?
+        .unwrap_or(SuppressorConfig::default().max_removals_per_page);
+    // --watermark-ratio F: drop short all-caps lines found on that fraction of pages, any position
+    let watermark_ratio = parse_flag::<f64>(logger, args, "--watermark-ratio")?;
+    if let Some(r) = watermark_ratio.filter(|r| !(*r > 0.0 && *r <= 1.0)) {
+        return Err(logger.cli_error("--watermark-ratio", &r.to_string(), format!("--watermark-ratio must be in (0, 1], got {}", r)));
+    }
+    let suppress_whitelist = match args.iter().position(|a| a == "--suppress-whitelist").and_then(|i| args.get(i + 1)) {
+        Some(p) => Some(Regex::new(p).map_err(|e| logger.cli_error("--suppress-whitelist", p, format!("--suppress-whitelist: {}", e)))?),
+        None => None,
+    };
+    let watermark_whitelist = match args.iter().position(|a| a == "--watermark-whitelist").and_then(|i| args.get(i + 1)) {
+        Some(p) => Some(Regex::new(p).map_err(|e| logger.cli_error("--watermark-whitelist", p, format!("--watermark-whitelist: {}", e)))?),
+        None => None,
+    };
+    let mut failed_documents: usize = 0;
+    let mut leaked_documents: usize = 0;
+    // --bench: per-step timings, reported as one JSON object after the batch
+    let bench = args.iter().any(|a| a == "--bench");
+    let mut bench_samples: Vec<BenchSample> = Vec::new();
+    // --report FILE.csv|FILE.json: one quality row per converted document, written after the batch
+    let report_path = args.iter().position(|a| a == "--report").and_then(|i| args.get(i + 1)).map(PathBuf::from);
+    let mut report_rows: Vec<ReportRow> = Vec::new();
+    // --combine OUTPUT.md: one corpus file (+ combined.meta.json) in enumeration order
+    let combine_path = args.iter().position(|a| a == "--combine").and_then(|i| args.get(i + 1)).map(PathBuf::from);
+    let mut combined: Vec<CombinedDoc> = Vec::new();
+    // Output document format: md (default) | txt | html
+    let mut output_format = OutputFormat::Md;
+    if let Some(pos) = args.iter().position(|a| a == "--output-format") {
+        let raw = args.get(pos + 1).map(|s| s.as_str()).unwrap_or("");
+        match OutputFormat::parse(raw) {
+            Some(f) => output_format = f,
+            None => return Err(logger.cli_error("--output-format", raw, "--output-format expects md|txt|html".to_string())),
+        }
+    }
+    // --combine concatenates Markdown; other formats would land in the .md corpus file as-is
+    if let (Some(path), true) = (combine_path.as_ref(), output_format != OutputFormat::Md) {
+        return Err(logger.cli_error("--combine", &path.display().to_string(), format!("--combine needs --output-format md, not {}", output_format.extension())));
+    }
+    // Split output per BAB: --split-by bab
+    let mut split_by_bab_on = false;
+    if let Some(pos) = args.iter().position(|a| a == "--split-by") {
+        let raw = args.get(pos + 1).map(|s| s.as_str()).unwrap_or("");
+        if raw == "bab" {
+            split_by_bab_on = true;
+        } else {
+            return Err(logger.cli_error("--split-by", raw, "--split-by expects bab".to_string()));
         }
     }
     // Minor patch flags and helpers
@@ -55,41 +348,19 @@ fn main() {
             per_doc_dir_on = v != "off";
         }
     }
+    if args.get(1).map(|s| s.as_str()) == Some("convert") {
+        per_doc_dir_on = false;
+    }
+
+    // --name-template: output file stem from {doc_id}/{slug}/{nomor}/{tahun}; default {doc_id}
+    let name_template = args.iter().position(|a| a == "--name-template").map(|i| args.get(i + 1).cloned().unwrap_or_default());
+    if let Some(t) = &name_template {
+        validate_name_template(t).map_err(|e| logger.cli_error("--name-template", t, format!("--name-template: {}", e)))?;
+    }
 
     // Track used slugs for uniqueness
     let mut used_doc_ids: HashSet<String> = HashSet::new();
 
-    fn slugify(base: &str) -> String {
-        let lower = base.to_lowercase();
-        let mut s = String::with_capacity(lower.len());
-        for ch in lower.chars() {
-            if ch.is_ascii_alphanumeric() {
-                s.push(ch);
-            } else {
-                s.push('-');
-            }
-        }
-        let trimmed = s.trim_matches('-').to_string();
-        let mut collapsed = String::with_capacity(trimmed.len());
-        let mut prev_dash = false;
-        for ch in trimmed.chars() {
-            if ch == '-' {
-                if !prev_dash {
-                    collapsed.push(ch);
-                }
-                prev_dash = true;
-            } else {
-                prev_dash = false;
-                collapsed.push(ch);
-            }
-        }
-        if collapsed.is_empty() {
-            "doc".to_string()
-        } else {
-            collapsed
-        }
-    }
-
     fn unique_slug(slug_in: String, used: &mut HashSet<String>) -> String {
         if !used.contains(&slug_in) {
             used.insert(slug_in.clone());
@@ -105,60 +376,190 @@ fn main() {
             i += 1;
         }
     }
-    // 1) Read and validate prd.yaml
-    let prd_path = Path::new("prd.yaml");
-    let prd = match validate_prd(prd_path) {
+    // 1) Read and validate the config: --config PATH, else ./prd.yaml. With neither present,
+    //    --input-glob + --output-dir are enough to synthesize one in memory.
+    //    --input GLOB --output DIR is the config-free mode: no PRD is read even if ./prd.yaml exists.
+    let flag_value = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned();
+    // `convert FILE.pdf [--output FILE.md]`: exactly one PDF, no PRD or enumeration. Without
+    // --output the document goes to stdout and its meta to stderr, via a scratch output dir.
+    let convert_file: Option<PathBuf> = match args.get(1).map(|s| s.as_str()) {
+        Some("convert") => match args.get(2).filter(|a| !a.starts_with("--")) {
+            Some(p) => Some(PathBuf::from(p)),
+            None => return Err(logger.cli_error("convert", "", "convert expects a PDF path".to_string())),
+        },
+        _ => None,
+    };
+    // --from-text FILE: already extracted text, pages separated by form feeds; no PRD, Poppler or OCR
+    let from_text = flag_value("--from-text");
+    let convert_output: Option<PathBuf> = convert_file.as_ref().and(flag_value("--output")).map(PathBuf::from);
+    let convert_scratch = if convert_file.is_some() && convert_output.is_none() {
+        Some(tempfile::tempdir().map_err(|e| PipelineError::from(EmitError::WriteFailed(e.to_string())))?)
+    } else {
+        None
+    };
+    let config_arg = flag_value("--config");
+    let input_glob_override = flag_value("--input-glob").or_else(|| flag_value("--input"));
+    let output_dir_override = flag_value("--output-dir").or_else(|| flag_value("--output"));
+    let prd_path = PathBuf::from(config_arg.clone().unwrap_or_else(|| "prd.yaml".to_string()));
+    // --input-list FILE: explicit paths, in list order, instead of the PRD/CLI globs
+    let input_list = flag_value("--input-list").filter(|_| convert_file.is_none()).map(PathBuf::from);
+    let has_input = input_glob_override.is_some() || input_list.is_some();
+    let cli_only = config_arg.is_none() && (flag_value("--input").is_some() || input_list.is_some()) && flag_value("--output").is_some();
+    let synthesize = cli_only
+        || (config_arg.is_none() && !prd_path.exists() && has_input && output_dir_override.is_some());
+    let prd_result = if let Some(file) = &convert_file {
+        let outdir = convert_output.as_ref()
+            .map(|out| out.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_path_buf())
+            .or_else(|| convert_scratch.as_ref().map(|d| d.path().to_path_buf()))
+            .unwrap_or_default();
+        Ok(PrdRoot::from_cli(&file.to_string_lossy(), &outdir.to_string_lossy()))
+    } else if from_text.is_some() && config_arg.is_none() {
+        Ok(PrdRoot::from_cli("", "."))
+    } else if synthesize {
+        Ok(PrdRoot::from_cli(input_glob_override.as_deref().unwrap_or_default(), output_dir_override.as_deref().unwrap_or_default()))
+    } else {
+        validate_prd(&prd_path)
+    };
+    let mut prd = match prd_result {
         Ok(p) => p,
         Err(e) => {
-            eprintln!(
-                "{}",
-                serde_json::json!({
-                    "tool": "validate_prd",
-                    "file": "prd.yaml",
-                    "error": e.to_string()
-                })
-            );
-            std::process::exit(3);
+            logger.log_event(LogLevel::Error, serde_json::json!({
+                "tool": "validate_prd",
+                "file": prd_path,
+                "error": e.to_string()
+            }));
+            return Err(e.into());
         }
     };
+    if convert_file.is_none() {
+        prd.apply_overrides(input_glob_override.as_deref(), output_dir_override.as_deref());
+    }
 
-    eprintln!(
-        "{}",
-        serde_json::json!({
-            "tool":"validate_prd",
-            "file":"prd.yaml",
-            "status":"ok",
-            "input_glob": prd.input_glob(),
-            "output_dir": prd.output_dir()
-        })
-    );
+    logger.log_event(LogLevel::Debug, serde_json::json!({
+        "tool":"validate_prd",
+        "file": if synthesize { None } else { Some(&prd_path) },
+        "synthesized": synthesize,
+        "status":"ok",
+        "input_glob": prd.input_glob(),
+        "output_dir": prd.output_dir()
+    }));
+
+    // PRD cleanup patterns were validated above; compile once for the whole batch
+    let mut cleanup_cfg = prd.cleanup_config().unwrap_or_default();
+    cleanup_cfg.normalize_punctuation = args.iter().any(|a| a == "--normalize-punctuation");
+    cleanup_cfg.preserve_quotes = args.iter().any(|a| a == "--preserve-quotes");
+    cleanup_cfg.no_reflow = args.iter().any(|a| a == "--no-reflow");
+    cleanup_cfg.preserve_formulas = args.iter().any(|a| a == "--preserve-formulas");
+    // --keep-lines protects lines in both the suppressor and cleanup, so a bad pattern is an error
+    cleanup_cfg.keep_lines = match args.iter().position(|a| a == "--keep-lines").and_then(|i| args.get(i + 1)) {
+        Some(p) => Some(Regex::new(p).map_err(|e| logger.cli_error("--keep-lines", p, format!("--keep-lines: {}", e)))?),
+        None => None,
+    };
+
+    // Text pipeline settings shared by PDFs and --from-text; PDFs set `first_page` per document
+    let convert_opts = ConvertOptions {
+        law_mode: law_mode_arg,
+        suppressor: SuppressorConfig {
+            keep_lines: cleanup_cfg.keep_lines.clone(),
+            header_patterns: cleanup_cfg.header_patterns.clone(),
+            footer_patterns: cleanup_cfg.footer_patterns.clone(),
+            max_removals_per_page: max_suppress_per_page,
+            watermark_ratio,
+            watermark_whitelist: watermark_whitelist.clone(),
+            whitelist: suppress_whitelist.clone(),
+            ..SuppressorConfig::default()
+        },
+        cleanup: cleanup_cfg.clone(),
+        page_markers,
+        first_page: 1,
+        footnotes: footnotes_on,
+        strip_gibberish: strip_gibberish_on,
+    };
+
+    // The markdown goes to --output FILE, else stdout; the metrics are logged
+    if let Some(text_file) = &from_text {
+        let text = fs::read_to_string(text_file)
+            .map_err(|e| logger.cli_error("--from-text", text_file, format!("--from-text: {}", e)))?;
+        let pages = split_text_pages(&text);
+        let result = process_text(&pages, &convert_opts);
+        logger.log_event(LogLevel::Info, serde_json::json!({
+            "tool":"from_text",
+            "file": text_file,
+            "pages": pages.len(),
+            "law_mode": result.law_mode,
+            "found": result.promoted.found,
+            "character_coverage": result.metrics.character_coverage,
+            "leak_rate": result.metrics.leak_rate,
+            "split_violations": result.metrics.split_violations,
+            "structure_warnings": if result.structure_warnings.is_empty() { None } else { Some(&result.structure_warnings) }
+        }));
+        let violations = check_quality(&result.metrics, &quality);
+        if !violations.is_empty() {
+            logger.log_event(LogLevel::Error, serde_json::json!({
+                "tool":"quality_gate",
+                "file": text_file,
+                "error":"QualityGateFailed",
+                "violations": violations,
+                "error_code": ExitCode::QualityGateFailed.code()
+            }));
+            return Err(PipelineError::QualityGateFailed { failed_documents: 1 });
+        }
+        let mut promoted = result.promoted;
+        let stem = Path::new(text_file).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let title = document_title(&promoted.found, parse_doc_identifier(text_file).as_ref(), &stem);
+        if title_on {
+            promoted.markdown = prepend_title(&promoted.markdown, &title);
+        }
+        if wrap > 0 {
+            promoted.markdown = wrap_markdown(&promoted.markdown, wrap);
+        }
+        let rendered = if output_format == OutputFormat::Html {
+            let doc_id = slugify(&stem);
+            let meta = serde_json::json!({ "doc_id": doc_id, "source_path": text_file, "title": title, "found": promoted.found, "metrics": result.metrics });
+            render_html(&promoted, &doc_id, &meta)
+        } else {
+            render(&promoted, output_format)
+        };
+        match flag_value("--output") {
+            Some(out) => {
+                let written = Path::new(&out).parent().filter(|p| !p.as_os_str().is_empty()).map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(&out, rendered));
+                if let Err(e) = written {
+                    logger.log_event(LogLevel::Error, serde_json::json!({
+                        "tool":"from_text",
+                        "file": out,
+                        "error": e.to_string(),
+                        "error_code": ExitCode::WriteFailed.code()
+                    }));
+                    return Err(EmitError::WriteFailed(e.to_string()).into());
+                }
+            }
+            None => print!("{}", rendered),
+        }
+        return Ok(());
+    }
 
     // 2) T0: check_deps
-    let deps: DepsResult = check_deps();
-    if !deps.ok {
-        eprintln!(
-            "{}",
-            serde_json::json!({
-                "tool":"check_deps",
-                "missing": deps.missing,
-                "error_code": 2
-            })
-        );
+    let deps: DepsResult = check_deps_with(djvu_on);
+    if !deps.ok && !dry_run {
+        logger.log_event(LogLevel::Error, serde_json::json!({
+            "tool":"check_deps",
+            "error":"MissingDependency",
+            "missing": deps.missing,
+            "error_code": ExitCode::MissingDependency.code()
+        }));
         let help = nala_help_for(&deps.missing);
         if !help.is_empty() {
             eprintln!("{}", help);
         }
-        std::process::exit(2);
+        return Err(PipelineError::MissingDependency { missing: deps.missing });
     } else {
-        eprintln!(
-            "{}",
-            serde_json::json!({
-                "tool":"check_deps",
-                "status":"ok",
-                "missing": deps.missing
-            })
-        );
-        if !deps.missing.is_empty() {
+        logger.log_event(LogLevel::Debug, serde_json::json!({
+            "tool":"check_deps",
+            "status": if deps.ok { "ok" } else { "missing_ignored_for_dry_run" },
+            "missing": deps.missing
+        }));
+        if !deps.missing.is_empty() && logger.level == LogLevel::Debug {
             let help = nala_help_for(&deps.missing);
             if !help.is_empty() {
                 eprintln!("{}", help);
@@ -167,358 +568,399 @@ fn main() {
     }
 
     // 3) T1: enumerate_pdfs on configured glob
-    let input_glob = prd.input_glob();
+    // Enumerate every datasource; a file matched by several datasources is processed once,
+    // tagged with the first one. Only when all datasources are empty is it NoFilesFound.
+    let mut all_files: Vec<(PathBuf, Option<String>)> = Vec::new();
+    let mut last_err: Option<EnumerateError> = None;
+    if let Some(file) = &convert_file {
+        all_files.push((file.clone(), None));
+    }
+    if let Some(list) = &input_list {
+        let entries = read_input_list(list)
+            .map_err(|e| logger.cli_error("--input-list", &list.to_string_lossy(), format!("--input-list: {}", e)))?;
+        if !entries.missing.is_empty() || !entries.not_pdf.is_empty() {
+            let msg = format!("--input-list: {} missing, {} not a PDF", entries.missing.len(), entries.not_pdf.len());
+            if !continue_on_error {
+                logger.log_event(LogLevel::Error, serde_json::json!({
+                    "tool":"input_list",
+                    "file": list,
+                    "missing": entries.missing,
+                    "not_pdf": entries.not_pdf,
+                    "error": msg,
+                    "error_code": ExitCode::InvalidArgument.code()
+                }));
+                return Err(PipelineError::InvalidArgument(msg));
+            }
+            logger.log_event(LogLevel::Warn, serde_json::json!({
+                "tool":"input_list",
+                "file": list,
+                "missing": entries.missing,
+                "not_pdf": entries.not_pdf,
+                "skipped": entries.missing.len() + entries.not_pdf.len()
+            }));
+        }
+        logger.log_event(LogLevel::Debug, serde_json::json!({
+            "tool":"input_list",
+            "file": list,
+            "count": entries.paths.len()
+        }));
+        if entries.paths.is_empty() {
+            last_err = Some(EnumerateError::NoFilesFound { guidance: format!("no usable PDF paths in {}", list.display()) });
+        }
+        all_files.extend(entries.paths.into_iter().map(|p| (p, None)));
+    }
+    for ds in prd.datasources.clone().unwrap_or_default().into_iter().filter(|_| convert_file.is_none() && input_list.is_none()) {
+        let Some(glob) = ds.path.clone() else { continue; };
+        let enumerated = match (enumerate_pdfs(&glob), djvu_glob(&glob).filter(|_| djvu_on).and_then(|g| enumerate_pdfs(&g).ok())) {
+            (Ok(mut found), Some(djvus)) => {
+                found.extend(djvus);
+                found.sort();
+                Ok(found)
+            }
+            (Err(_), Some(djvus)) => Ok(djvus),
+            (found, None) => found,
+        };
+        match enumerated {
+            Ok(found) => {
+                // Optional .lpdfignore in the input root; a broken one is reported and not applied
+                let root = glob_input_root(&glob);
+                let found = match apply_ignore_file(found.clone(), &root) {
+                    Ok((kept, ignored)) => {
+                        if root.join(IGNORE_FILE_NAME).is_file() {
+                            logger.log_event(LogLevel::Warn, serde_json::json!({
+                                "tool":"lpdfignore",
+                                "datasource": ds.name,
+                                "file": root.join(IGNORE_FILE_NAME),
+                                "ignored": ignored
+                            }));
+                        }
+                        kept
+                    }
+                    Err(e) => {
+                        logger.log_event(LogLevel::Warn, serde_json::json!({
+                            "tool":"lpdfignore",
+                            "datasource": ds.name,
+                            "file": root.join(IGNORE_FILE_NAME),
+                            "warning": format!("ignore file not applied: {}", e)
+                        }));
+                        found
+                    }
+                };
+                if found.is_empty() {
+                    last_err = Some(EnumerateError::NoFilesFound { guidance: format!("all enumerated files matched {}", IGNORE_FILE_NAME) });
+                    continue;
+                }
+                logger.log_event(LogLevel::Debug, serde_json::json!({
+                    "tool":"enumerate_pdfs",
+                    "datasource": ds.name,
+                    "glob": glob,
+                    "count": found.len(),
+                }));
+                for f in found {
+                    if !all_files.iter().any(|(p, _)| p == &f) {
+                        all_files.push((f, ds.name.clone()));
+                    }
+                }
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    // --exclude PATTERN (repeatable) filters the enumerated files
+    let excludes: Vec<String> = args.iter().enumerate()
+        .filter(|(_, a)| *a == "--exclude")
+        .filter_map(|(i, _)| args.get(i + 1).cloned())
+        .collect();
+    if !excludes.is_empty() && !all_files.is_empty() {
+        let before = all_files.len();
+        let paths: Vec<PathBuf> = all_files.iter().map(|(p, _)| p.clone()).collect();
+        let kept = exclude_paths(paths, &excludes)
+            .map_err(|e| logger.cli_error("--exclude", &excludes.join(" "), format!("--exclude: {}", e)))?;
+        all_files.retain(|(p, _)| kept.contains(p));
+        logger.log_event(LogLevel::Warn, serde_json::json!({
+            "tool":"exclude",
+            "patterns": excludes,
+            "excluded": before - all_files.len()
+        }));
+        if all_files.is_empty() {
+            last_err = Some(EnumerateError::NoFilesFound { guidance: "all enumerated files matched --exclude".to_string() });
+        }
+    }
+    // --since 24h | 2024-01-01: only files modified after the cutoff; nothing new is not an error
+    if let Some(spec) = flag_value("--since") {
+        let cutoff = parse_since(&spec, std::time::SystemTime::now()).map_err(|e| logger.cli_error("--since", &spec, format!("--since: {}", e)))?;
+        if !all_files.is_empty() {
+            let paths: Vec<PathBuf> = all_files.iter().map(|(p, _)| p.clone()).collect();
+            let (kept, skipped) = filter_modified_since(paths, cutoff);
+            all_files.retain(|(p, _)| kept.contains(p));
+            logger.log_event(LogLevel::Warn, serde_json::json!({
+                "tool":"since",
+                "since": spec,
+                "skipped_up_to_date": skipped
+            }));
+        }
+    }
+    // --dedup: byte-identical copies under other names are processed once, first in order wins
+    if args.iter().any(|a| a == "--dedup") && all_files.len() > 1 {
+        let paths: Vec<PathBuf> = all_files.iter().map(|(p, _)| p.clone()).collect();
+        let (_, duplicates) = dedup_by_content(paths);
+        for (skipped, first) in &duplicates {
+            logger.log_event(LogLevel::Warn, serde_json::json!({
+                "tool":"dedup",
+                "skipped": skipped,
+                "duplicate_of": first
+            }));
+        }
+        all_files.retain(|(p, _)| !duplicates.iter().any(|(d, _)| d == p));
+    }
+    let enumerated: Result<Vec<(PathBuf, Option<String>)>, EnumerateError> = match last_err {
+        Some(e) if all_files.is_empty() => Err(e),
+        _ => Ok(all_files),
+    };
 
-    match enumerate_pdfs(&input_glob) {
+    match enumerated {
         Ok(files) => {
-            eprintln!(
-                "{}",
-                serde_json::json!({
-                    "tool":"enumerate_pdfs",
-                    "count": files.len(),
-                })
-            );
+            logger.log_event(LogLevel::Debug, serde_json::json!({
+                "tool":"enumerate_pdfs",
+                "count": files.len(),
+            }));
+
+            let batch_started = std::time::Instant::now();
+            let on_progress = |ev: ProgressEvent| {
+                let Some(bar) = &logger.progress else { return };
+                match ev {
+                    ProgressEvent::Started { total } => bar.set_length(total as u64),
+                    ProgressEvent::Document { index, doc_id, .. } => {
+                        bar.set_position(index as u64);
+                        bar.set_message(doc_id);
+                    }
+                    ProgressEvent::Finished { total } => {
+                        bar.set_position(total as u64);
+                        bar.finish_and_clear();
+                    }
+                }
+            };
+            let pdf_opts = PdfOptions {
+                convert: convert_opts.clone(),
+                extract: extract_opts.clone(),
+                djvu: djvu_on,
+                ocr: OcrOptions { lang: ocr_lang.clone(), dpi: ocr_dpi, psm: ocr_psm, oem: ocr_oem, image_format: ocr_image_format, ..OcrOptions::default() },
+                with_ocr: with_ocr_forced,
+                ocr_all,
+                suspect_density,
+                strict,
+                verify_idempotent,
+                quality,
+                continue_on_error,
+                fail_on_leak,
+                emit_ast,
+                title: title_on,
+                wrap,
+                split_by_bab: split_by_bab_on,
+                per_doc_dir: per_doc_dir_on,
+                output_format,
+                review,
+            };
 
-            // Process each file: T2 poppler_extract -> T3 detect_suspect_pages -> T4 (optional) OCR -> T5 merge
-            for file in files {
-                let started_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_millis() as i128).unwrap_or(0);
+            // Plan each file (doc_id, output dir, skip checks), then convert the rest as one batch:
+            // extract -> detect suspect pages -> (optional) OCR -> process_text -> emit
+            let documents = files.len();
+            let mut swept_dirs: std::collections::HashSet<String> = std::collections::HashSet::new();
+            let mut unreadable: Vec<PopplerError> = Vec::new();
+            // --combine keeps enumeration order across skipped and converted documents
+            let mut combined_at: Vec<(usize, CombinedDoc)> = Vec::new();
+            let mut jobs: Vec<(usize, PdfDocument)> = Vec::new();
+            for (index, (file, datasource)) in files.into_iter().enumerate() {
                 let fname = file.file_name().and_then(|s| s.to_str()).unwrap_or("doc.pdf").to_string();
-                let base = fname.trim_end_matches(".pdf");
-                let slug = unique_slug(slugify(base), &mut used_doc_ids);
-                let doc_id = slug; // used for directories and filenames
+                let base = fname.trim_end_matches(".pdf").trim_end_matches(".djvu").trim_end_matches(".djv");
+                let stem = match &name_template {
+                    Some(t) => expand_name_template(t, &slugify(base), parse_doc_identifier(&fname).as_ref()).unwrap_or_else(|e| {
+                        logger.log_event(LogLevel::Warn, serde_json::json!({
+                            "tool":"name_template",
+                            "file": file,
+                            "warning": format!("{}; using doc_id", e)
+                        }));
+                        slugify(base)
+                    }),
+                    None => slugify(base),
+                };
+                let slug = unique_slug(stem, &mut used_doc_ids);
+                // convert --output FILE.md names the document after FILE
+                let doc_id = convert_output.as_ref().and_then(|o| o.file_stem()).map(|s| s.to_string_lossy().into_owned()).unwrap_or(slug);
                 let base_output = prd.output_dir();
                 let doc_outdir = if per_doc_dir_on { format!("{}/{}", base_output, doc_id) } else { base_output.clone() };
                 let artifacts_dir = if artifacts_on || dump_steps { Some(format!("{}/artifacts", doc_outdir)) } else { None };
-                match poppler_extract(&file, true, true) {
-                    Ok(pages) => {
-                        eprintln!(
-                            "{}",
-                            serde_json::json!({
-                                "tool":"poppler_extract",
-                                "file": file,
-                                "pages": pages.len()
-                            })
-                        );
-                        if let Some(ad) = &artifacts_dir {
-                            let joined = pages.join("\n");
-                            let _ = std::fs::create_dir_all(ad);
-                            let step_path = format!("{}/step1_extract.txt", ad);
-                            if let Err(e) = fs::write(&step_path, joined) {
-                                eprintln!(
-                                    "{}",
-                                    serde_json::json!({
-                                        "tool":"dump_steps",
-                                        "file": step_path,
-                                        "error": e.to_string()
-                                    })
-                                );
-                            }
-                        }
-                        let page_count = pages.len();
-                        let mut suspects = detect_suspect_pages(&pages, 64);
-                        // CI sampling: restrict suspect pages to first N via env CI_SAMPLE_SUSPECTS
-                        if let Ok(sample_n) = std::env::var("CI_SAMPLE_SUSPECTS").and_then(|v| v.parse::<usize>().map_err(|_| std::env::VarError::NotPresent)) {
-                            if sample_n > 0 && suspects.len() > sample_n { suspects.truncate(sample_n); }
-                        }
-                        eprintln!(
-                            "{}",
-                            serde_json::json!({
-                                "tool":"detect_suspect_pages",
-                                "file": file,
-                                "suspect_pages": suspects
-                            })
-                        );
-
-                        // Enforce OCR for suspect pages when deps available (Minor-Patch-III)
-                        let has_tesseract = which::which("tesseract").is_ok() && which::which("pdftoppm").is_ok();
-                        let ocr_enabled = has_tesseract; // enabled if deps available
-                        let ocr_requested = with_ocr_forced.unwrap_or(!suspects.is_empty()); // auto when suspects exist
-
-                        let mut ocr_ran = false;
-                        let mut ocr_run_pages: Vec<usize> = Vec::new();
-                        let mut ocr_skipped_reason: Option<String> = None;
-                        let ocr_lang_used = ocr_lang.clone();
-                        let ocr_psm: u8 = 4;
-                        let ocr_oem: u8 = 1;
-                        let ocr_dpi: u32 = ocr_dpi;
-                        let mut pages_after_ocr = pages.clone();
-                        if ocr_enabled && ocr_requested && !suspects.is_empty() {
-                            let ad_path = artifacts_dir.as_ref().map(|s| std::path::Path::new(s).to_path_buf());
-                            let ocr = if let Some(p) = &ad_path { ocr_tesseract(&file, &suspects, &ocr_lang_used, ocr_dpi, Some(p.as_path()), ocr_psm, ocr_oem) } else { ocr_tesseract(&file, &suspects, &ocr_lang_used, ocr_dpi, None, ocr_psm, ocr_oem) };
-                            eprintln!(
-                                "{}",
-                                serde_json::json!({
-                                    "tool":"ocr_tesseract",
-                                    "file": file,
-                                    "attempted": suspects.len(),
-                                    "texts": ocr.texts.len(),
-                                    "failed": ocr.failed,
-                                    "skipped_due_to_missing_deps": ocr.skipped_due_to_missing_deps,
-                                    "lang": ocr_lang_used
-                                })
-                            );
-                            if !ocr.skipped_due_to_missing_deps {
-                                for t in &ocr.texts {
-                                    if let Some(slot) = pages_after_ocr.get_mut(t.index) {
-                                        *slot = t.text.clone();
-                                    }
-                                }
-                                ocr_ran = true;
-                                ocr_run_pages = ocr.texts.iter().map(|t| t.index).collect();
-                                // Write OCR summary when artifacts on
-                                if let Some(ad) = &artifacts_dir {
-                                    let ocr_dir = format!("{}/ocr", ad);
-                                    let _ = std::fs::create_dir_all(&ocr_dir);
-                                    let mut summary = String::new();
-                                    summary.push_str(&format!("attempted: {}\n", suspects.len()));
-                                    summary.push_str(&format!("success: {}\n", ocr.texts.len()));
-                                    summary.push_str(&format!("failed: {}\n", ocr.failed.len()));
-                                    if !ocr.failed.is_empty() { summary.push_str(&format!("failed_indices: {:?}\n", ocr.failed)); }
-                                    if !ocr.errors.is_empty() {
-                                        summary.push_str("errors:\n");
-                                        for e in &ocr.errors { summary.push_str(&format!("- page_index={} error={}\n", e.index, e.message)); }
-                                    }
-                                    let _ = std::fs::write(format!("{}/ocr_summary.txt", ocr_dir), summary);
-                                }
-                            } else {
-                                ocr_skipped_reason = Some("tesseract_missing".to_string());
-                            }
-                        } else if !ocr_enabled && !suspects.is_empty() {
-                            ocr_skipped_reason = Some("tesseract_missing".to_string());
-                        } else if with_ocr_forced == Some(false) && !suspects.is_empty() {
-                            ocr_skipped_reason = Some("disabled_by_flag".to_string());
-                        }
-
-                        // Persist step2_merge.txt (OCR overrides merged) if artifacts on
-                        if let Some(ad) = &artifacts_dir {
-                            let _ = std::fs::create_dir_all(ad);
-                            let step2_path = format!("{}/step2_merge.txt", ad);
-                            let merged_preview = pages_after_ocr.join("\n");
-                            let _ = fs::write(&step2_path, merged_preview);
-                        }
-
-                        // Apply repeated-line suppressor on a per-page basis before cleanup
-                        let keep_lines_regex = args.iter().position(|a| a == "--keep-lines").and_then(|i| args.get(i+1)).and_then(|p| Regex::new(p).ok());
-                        let cfg = SuppressorConfig { threshold_ratio: 0.60, keep_lines: keep_lines_regex };
-                        let (suppressed_pages, suppress_stats, removed_candidates) = suppress_repeated_lines(&pages_after_ocr, &cfg);
-                        if let Some(ad) = &artifacts_dir {
-                            // Dump preview
-                            let _ = std::fs::create_dir_all(ad);
-                            let prev = format!("{}/suppressor_preview.txt", ad);
-                            let _ = fs::write(&prev, removed_candidates.join("\n"));
-                        }
-                        // Merge suppressed pages (already contained OCR overrides) for cleanup/metrics
-                        let merged = merge_pages(&suppressed_pages, &[]);
-                        if let Some(ad) = &artifacts_dir {
-                            let _ = std::fs::create_dir_all(ad);
-                            let step2_path = format!("{}/step2_merge.txt", ad);
-                            if let Err(e) = fs::write(&step2_path, &merged) {
-                                eprintln!(
-                                    "{}",
-                                    serde_json::json!({
-                                        "tool":"dump_steps",
-                                        "file": step2_path,
-                                        "error": e.to_string()
-                                    })
-                                );
-                            }
-                        }
-                        eprintln!(
-                            "{}",
-                            serde_json::json!({
-                                "tool":"merge_pages",
-                                "file": file,
-                                "length": merged.len()
-                            })
-                        );
-
-                        // T6: Cleanup
-                        let mut cleaned = law_cleanup(&merged, &law_mode);
-                        // Merge suppressor stats into cleanup stats for meta
-                        cleaned.stats.removed_header += suppress_stats.removed_header;
-                        cleaned.stats.removed_footer += suppress_stats.removed_footer;
-                        cleaned.stats.removed_lines_sample = suppress_stats.removed_lines_sample;
-                        cleaned.stats.suppressor_overrun = suppress_stats.suppressor_overrun;
-                        eprintln!(
-                            "{}",
-                            serde_json::json!({
-                                "tool":"law_cleanup",
-                                "file": file,
-                                "removed_header": cleaned.stats.removed_header,
-                                "removed_footer": cleaned.stats.removed_footer,
-                                "hyphens_fixed": cleaned.stats.hyphens_fixed
-                            })
-                        );
-
-                        // T7: Promote headings
-                        let promoted = promote_legal_headings(&cleaned.cleaned, &law_mode);
-                        if let Some(ad) = &artifacts_dir {
-                            let _ = std::fs::create_dir_all(ad);
-                            let step3_path = format!("{}/step3_md.txt", ad);
-                            if let Err(e) = fs::write(&step3_path, &promoted.markdown) {
-                                eprintln!(
-                                    "{}",
-                                    serde_json::json!({
-                                        "tool":"dump_steps",
-                                        "file": step3_path,
-                                        "error": e.to_string()
-                                    })
-                                );
-                            }
-                        }
-                        eprintln!(
-                            "{}",
-                            serde_json::json!({
-                                "tool":"promote_legal_headings",
-                                "file": file,
-                                "found": promoted.found
-                            })
-                        );
-
-                        // Strict mode enforcement for PP/Permen
-                        if strict {
-                            let lm = law_mode.to_lowercase();
-                            if (lm == "pp" || lm == "permen") && (promoted.found.pasal == 0 || promoted.found.bab == 0) {
-                                eprintln!(
-                                    "{}",
-                                    serde_json::json!({
-                                        "tool":"promote_legal_headings",
-                                        "file": file,
-                                        "error":"StructureNotFound",
-                                        "error_code": 5,
-                                        "found": promoted.found
-                                    })
-                                );
-                                std::process::exit(5);
-                            }
-                        }
-
-                        // T8: Metrics
-                        let metrics = compute_metrics(&merged, &promoted.markdown, &promoted.found);
-                        eprintln!(
-                            "{}",
-                            serde_json::json!({
-                                "tool":"compute_metrics",
-                                "file": file,
-                                "character_coverage": metrics.character_coverage,
-                                "leak_rate": metrics.leak_rate,
-                                "split_violations": metrics.split_violations
-                            })
-                        );
-
-                        // T9: Emit files (atomic)
-                        let finished_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_millis() as i128).unwrap_or(0);
-                        // Timing vector proxy & p95
-                        let total_ms = (finished_ms - started_ms).max(0) as u128;
-                        let per_page = if page_count>0 { (total_ms / (page_count as u128)) as u64 } else { 0 };
-                        let timing_ms_per_page: Vec<u64> = vec![per_page; page_count];
-                        let p95_latency_ms_per_page: u64 = per_page;
-                        // coverage_pages metric
-                        let suspects_len = suspects.len() as i64;
-                        let run_len = ocr_run_pages.len() as i64;
-                        let pages_i = page_count as i64;
-                        let cov_pages = if pages_i > 0 { 1.0 - (((suspects_len - run_len).max(0) as f64) / (pages_i as f64)) } else { 0.0 };
-
-                        let meta = serde_json::json!({
+                if dry_run {
+                    let md_path = Path::new(&doc_outdir).join(format!("{}.{}", doc_id, output_format.extension()));
+                    let meta_path = Path::new(&doc_outdir).join(format!("{}.meta.json", doc_id));
+                    logger.output("dry_run", serde_json::json!({
+                        "tool":"dry_run",
+                        "file": file,
+                        "datasource": datasource,
+                        "doc_id": doc_id,
+                        "md_path": md_path,
+                        "meta_path": meta_path
+                    }));
+                    continue;
+                }
+                if validate_only {
+                    let extracted = if djvu_on && is_djvu(&file) {
+                        djvu_extract(&file, extract_opts.pages)
+                    } else {
+                        poppler_extract_info(&file, &extract_opts)
+                    };
+                    let verdict = pdf_verdict(&extracted);
+                    let mut line = serde_json::json!({ "tool":"validate_only", "file": file, "doc_id": doc_id });
+                    if let (Some(obj), Ok(serde_json::Value::Object(fields))) = (line.as_object_mut(), serde_json::to_value(&verdict)) {
+                        obj.extend(fields);
+                    }
+                    logger.output("validate_only", line);
+                    if let Err(e) = extracted {
+                        unreadable.push(e);
+                    }
+                    continue;
+                }
+                // Once per output dir: drop temp files orphaned by a crashed earlier run
+                if swept_dirs.insert(doc_outdir.clone()) {
+                    let removed = sweep_temp_files(Path::new(&doc_outdir));
+                    if !removed.is_empty() {
+                        logger.log_event(LogLevel::Warn, serde_json::json!({
+                            "tool":"sweep_temp_files",
+                            "dir": doc_outdir,
+                            "removed": removed
+                        }));
+                    }
+                }
+                let input_sha256 = fs::read(&file).ok().map(|b| sha256_hex(&b));
+                if skip_existing {
+                    let md_path = Path::new(&doc_outdir).join(format!("{}.{}", doc_id, output_format.extension()));
+                    let meta_path = Path::new(&doc_outdir).join(format!("{}.meta.json", doc_id));
+                    if matches!(&input_sha256, Some(h) if is_up_to_date(&md_path, &meta_path, h)) {
+                        logger.log_event(LogLevel::Info, serde_json::json!({
+                            "tool":"skip",
+                            "file": file,
                             "doc_id": doc_id,
-                            "engine": "poppler",
-                            "suspect_pages": suspects,
-                            "ocr": {
-                                "enabled": ocr_enabled,
-                                "ran": ocr_ran,
-                                "skipped_reason": ocr_skipped_reason,
-                                "ocr_run_pages": ocr_run_pages,
-                                "lang": ocr_lang_used,
-                                "psm": ocr_psm,
-                                "oem": ocr_oem,
-                                "dpi": ocr_dpi,
-                            },
-                            "found": promoted.found,
-                            "stats": cleaned.stats,
-                            "metrics": {
-                                "character_coverage": metrics.character_coverage,
-                                "leak_rate": metrics.leak_rate,
-                                "split_violations": metrics.split_violations,
-                                "coverage_pages": cov_pages
-                            },
-                            "page_count": page_count,
-                            "timing_ms_per_page": timing_ms_per_page,
-                            "p95_latency_ms_per_page": p95_latency_ms_per_page,
-                            "timestamps": {"started_ms": started_ms, "finished_ms": finished_ms},
-                        });
-                        // Compute meta_fingerprint (normalized meta without timestamps)
-                        let mut meta_norm = meta.clone();
-                        if let Some(obj) = meta_norm.as_object_mut() {
-                            obj.remove("timestamps");
-                        }
-                        let meta_norm_bytes = serde_json::to_vec(&meta_norm).unwrap_or_default();
-                        let fingerprint = sha256_hex(&meta_norm_bytes);
-                        let mut meta_full = meta.as_object().cloned().unwrap_or_default();
-                        meta_full.insert("meta_fingerprint".to_string(), serde_json::json!(fingerprint));
-                        let meta = serde_json::Value::Object(meta_full);
-                        // Ensure doc output directory exists
-                        let _ = std::fs::create_dir_all(&doc_outdir);
-                        match emit_files(&promoted.markdown, &meta, doc_outdir.as_str(), &doc_id) {
-                            Ok(paths) => {
-                                eprintln!(
-                                    "{}",
-                                    serde_json::json!({
-                                        "tool":"emit_files",
-                                        "file": file,
-                                        "md_path": paths.md_path,
-                                        "meta_path": paths.meta_path
-                                    })
-                                );
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "{}",
-                                    serde_json::json!({
-                                        "tool":"emit_files",
-                                        "file": file,
-                                        "error": e.to_string(),
-                                        "error_code": 6
-                                    })
-                                );
-                                std::process::exit(6);
-                            }
+                            "reason":"unchanged"
+                        }));
+                        if combine_path.is_some() {
+                            let content = fs::read_to_string(&md_path).unwrap_or_default();
+                            let meta = fs::read(&meta_path).ok().and_then(|b| serde_json::from_slice(&b).ok()).unwrap_or(serde_json::Value::Null);
+                            combined_at.push((index, CombinedDoc { doc_id: doc_id.clone(), content, meta }));
                         }
+                        continue;
+                    }
+                }
+                jobs.push((index, PdfDocument { path: file, datasource, doc_id, outdir: doc_outdir, artifacts_dir, input_sha256 }));
+            }
+            if validate_only {
+                logger.log_event(LogLevel::Warn, serde_json::json!({
+                    "tool":"validate_only",
+                    "documents": documents,
+                    "unreadable": unreadable.len()
+                }));
+                logger.event(Event::Finished { documents });
+                // the first unreadable file decides the exit code
+                return match unreadable.into_iter().next() {
+                    Some(e) => Err(e.into()),
+                    None => Ok(()),
+                };
+            }
+            let (job_index, docs): (Vec<usize>, Vec<PdfDocument>) = jobs.into_iter().unzip();
+            let log = |level: LogLevel, value: serde_json::Value| logger.log_event(level, value);
+            let converted = convert_pdf_batch(&docs, &pdf_opts, Some(&log), Some(&on_progress))?;
+            for doc in converted {
+                failed_documents += usize::from(doc.quality_failed);
+                leaked_documents += usize::from(doc.leaked);
+                if combine_path.is_some() || convert_scratch.is_some() {
+                    // documents skipped as empty have no result, so look the index up by doc_id
+                    let index = docs.iter().position(|d| d.doc_id == doc.doc_id).map_or(documents, |i| job_index[i]);
+                    combined_at.push((index, CombinedDoc { doc_id: doc.doc_id.clone(), content: doc.rendered, meta: doc.meta }));
+                }
+                bench_samples.push(doc.bench);
+                report_rows.push(doc.report);
+            }
+            combined_at.sort_by_key(|(index, _)| *index);
+            combined.extend(combined_at.into_iter().map(|(_, doc)| doc));
+            if let Some(out) = combine_path.as_ref().filter(|_| !dry_run) {
+                match emit_combined(&combined, out) {
+                    Ok(paths) => {
+                        logger.log_event(LogLevel::Warn, serde_json::json!({
+                            "tool":"combine",
+                            "documents": combined.len(),
+                            "md_path": paths.md_path,
+                            "meta_path": paths.meta_path
+                        }));
                     }
-                    Err(err) => {
-                        let (code, label) = match err {
-                            PopplerError::FileNotFound(_) => (1, "FileNotFound"),
-                            PopplerError::EncryptedPDF(_) => (1, "EncryptedPDF"),
-                            PopplerError::Other(_) => (1, "PopplerError"),
-                        };
-                        eprintln!(
-                            "{}",
-                            serde_json::json!({
-                                "tool":"poppler_extract",
-                                "file": file,
-                                "error": label,
-                                "error_code": code
-                            })
-                        );
-                        std::process::exit(code);
+                    Err(e) => {
+                        logger.log_event(LogLevel::Error, serde_json::json!({
+                            "tool":"combine",
+                            "file": out,
+                            "error": e.to_string(),
+                            "error_code": ExitCode::WriteFailed.code()
+                        }));
+                        return Err(e.into());
                     }
                 }
             }
+            if convert_scratch.is_some() {
+                for doc in &combined {
+                    print!("{}", doc.content);
+                    eprintln!("{}", doc.meta);
+                }
+            }
+            if let Some(path) = report_path.as_ref().filter(|_| !dry_run) {
+                if let Err(e) = write_report(&report_rows, path) {
+                    logger.log_event(LogLevel::Error, serde_json::json!({
+                        "tool":"report",
+                        "file": path,
+                        "error": e.to_string(),
+                        "error_code": ExitCode::WriteFailed.code()
+                    }));
+                    return Err(e.into());
+                }
+                logger.log_event(LogLevel::Warn, serde_json::json!({
+                    "tool":"report",
+                    "file": path,
+                    "documents": report_rows.len()
+                }));
+            }
+            if bench {
+                let bench_json = serde_json::to_value(bench_report(&bench_samples, batch_started.elapsed().as_millis() as u64)).unwrap_or_default();
+                // convert to stdout keeps stdout for the document
+                if convert_scratch.is_some() { eprintln!("{}", bench_json) } else { logger.output("bench", bench_json) }
+            }
+            logger.event(Event::Finished { documents });
+            if failed_documents > 0 {
+                logger.log_event(LogLevel::Error, serde_json::json!({
+                    "tool":"quality_gate",
+                    "error":"QualityGateFailed",
+                    "failed_documents": failed_documents,
+                    "error_code": ExitCode::QualityGateFailed.code()
+                }));
+                return Err(PipelineError::QualityGateFailed { failed_documents });
+            }
+            if leaked_documents > 0 {
+                logger.log_event(LogLevel::Error, serde_json::json!({
+                    "tool":"leak_check",
+                    "error":"LeakDetected",
+                    "failed_documents": leaked_documents,
+                    "error_code": ExitCode::LeakDetected.code()
+                }));
+                return Err(PipelineError::LeakDetected { failed_documents: leaked_documents });
+            }
+            Ok(())
         }
         Err(err) => {
-            let guidance = match err {
-                legalpdf_to_md::EnumerateError::NoFilesFound { guidance } => guidance,
+            let guidance = match &err {
+                EnumerateError::NoFilesFound { guidance } => guidance.clone(),
             };
-            eprintln!(
-                "{}",
-                serde_json::json!({
-                    "tool":"enumerate_pdfs",
-                    "error":"NoFilesFound",
-                    "error_code":1
-                })
-            );
+            logger.log_event(LogLevel::Error, serde_json::json!({
+                "tool":"enumerate_pdfs",
+                "error":"NoFilesFound",
+                "error_code": ExitCode::Failure.code()
+            }));
             // Spec: still print folder guidance
             eprintln!("{}", guidance);
-            std::process::exit(1);
+            Err(err.into())
         }
     }
 }
@@ -0,0 +1,46 @@
+use legalpdf_to_md::{build_ast, promote_legal_headings, NodeKind};
+
+#[test]
+fn ast_nests_bab_bagian_pasal_ayat_points() {
+    let md = promote_legal_headings(
+        "BAB I KETENTUAN UMUM\nBagian Kesatu Umum\nPasal 1\n(1) Ayat satu.\na. huruf a\nb. huruf b\n(2) Ayat dua.\nPasal 2\nIsi pasal dua.\nBAB II PENUTUP\nPasal 3\nSelesai.",
+        "auto",
+    );
+    let ast = build_ast(&md.markdown, &md.found);
+    assert_eq!(ast.kind, NodeKind::Document);
+    assert_eq!(ast.children.len(), 2);
+
+    let bab1 = &ast.children[0];
+    assert_eq!(bab1.kind, NodeKind::Bab);
+    assert_eq!(bab1.label.as_deref(), Some("I"));
+    assert_eq!(bab1.title.as_deref(), Some("KETENTUAN UMUM"));
+    let bagian = &bab1.children[0];
+    assert_eq!(bagian.kind, NodeKind::Bagian);
+    assert_eq!(bagian.label.as_deref(), Some("Kesatu"));
+    assert_eq!(bagian.children.len(), 2);
+
+    let pasal1 = &bagian.children[0];
+    assert_eq!(pasal1.label.as_deref(), Some("1"));
+    assert_eq!(pasal1.children.len(), 2);
+    let ayat1 = &pasal1.children[0];
+    assert_eq!(ayat1.kind, NodeKind::Ayat);
+    assert_eq!(ayat1.text, "Ayat satu.");
+    assert_eq!(ayat1.children.len(), 2);
+    assert_eq!(ayat1.children[1].kind, NodeKind::Point);
+    assert_eq!(ayat1.children[1].label.as_deref(), Some("b"));
+    assert_eq!(bagian.children[1].text, "Isi pasal dua.");
+
+    let bab2 = &ast.children[1];
+    assert_eq!(bab2.children[0].label.as_deref(), Some("3"));
+}
+
+#[test]
+fn ast_attaches_orphans_to_nearest_parent() {
+    let md = promote_legal_headings("Pembukaan.\n(1) Ayat tanpa pasal.\nPasal 7\nIsi.", "auto");
+    let ast = build_ast(&md.markdown, &md.found);
+    assert_eq!(ast.text, "Pembukaan.");
+    assert_eq!(ast.children.len(), 2);
+    assert_eq!(ast.children[0].kind, NodeKind::Ayat);
+    assert_eq!(ast.children[1].kind, NodeKind::Pasal);
+    assert_eq!(ast.children[1].text, "Isi.");
+}
@@ -1,20 +1,30 @@
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
+use std::sync::Mutex;
 
-use legalpdf_to_md::check_deps;
+use legalpdf_to_md::{check_deps, check_deps_with, nala_help_for, version_report};
+
+// PATH is process-global; serialize tests that rewrite it.
+static PATH_LOCK: Mutex<()> = Mutex::new(());
 
 fn set_path(dir: &std::path::Path) {
     std::env::set_var("PATH", dir.display().to_string());
 }
 
+fn fake_bin(dir: &std::path::Path, name: &str) {
+    let bin = dir.join(name);
+    fs::write(&bin, "#!/bin/sh\nexit 0\n").unwrap();
+    let mut perms = fs::metadata(&bin).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&bin, perms).unwrap();
+}
+
 #[test]
 fn check_deps_ok_when_pdftotext_present() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let td = tempfile::tempdir().unwrap();
-    let fake_bin = td.path().join("pdftotext");
-    fs::write(&fake_bin, "#!/bin/sh\nexit 0\n").unwrap();
-    let mut perms = fs::metadata(&fake_bin).unwrap().permissions();
-    perms.set_mode(0o755);
-    fs::set_permissions(&fake_bin, perms).unwrap();
+    fake_bin(td.path(), "pdftotext");
+    fake_bin(td.path(), "pdftoppm");
 
     set_path(td.path());
     let res = check_deps();
@@ -25,6 +35,7 @@ fn check_deps_ok_when_pdftotext_present() {
 
 #[test]
 fn check_deps_missing_required_dep() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let td = tempfile::tempdir().unwrap();
     set_path(td.path()); // empty PATH
     let res = check_deps();
@@ -32,3 +43,38 @@ fn check_deps_missing_required_dep() {
     assert!(res.missing.iter().any(|m| m == "pdftotext"));
 }
 
+#[test]
+fn djvu_tools_are_optional_and_only_reported_when_asked() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    fake_bin(td.path(), "pdftotext");
+    fake_bin(td.path(), "pdftoppm");
+    fake_bin(td.path(), "djvutxt");
+
+    set_path(td.path());
+    let plain = check_deps();
+    let djvu = check_deps_with(true);
+    assert!(plain.ok && djvu.ok);
+    assert!(!plain.missing.iter().any(|m| m.starts_with("djvu") || m == "ddjvu"));
+    assert!(djvu.missing.iter().any(|m| m == "ddjvu"));
+    assert!(!djvu.missing.iter().any(|m| m == "djvutxt"));
+    assert!(nala_help_for(&djvu.missing).contains("djvulibre-bin"));
+}
+
+#[test]
+fn version_report_reads_tool_banners() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("pdftotext");
+    // pdftotext prints its banner on stderr
+    fs::write(&bin, "#!/bin/sh\necho 'pdftotext version 22.02.0' >&2\necho 'Copyright 2005-2022 The Poppler Developers' >&2\n").unwrap();
+    let mut perms = fs::metadata(&bin).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&bin, perms).unwrap();
+
+    set_path(td.path());
+    let v = version_report();
+    assert_eq!(v.crate_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(v.pdftotext.as_deref(), Some("22.02.0"));
+    assert_eq!(v.tesseract, None);
+}
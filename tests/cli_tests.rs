@@ -0,0 +1,307 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+fn stub_bin(dir: &Path, name: &str, script: &str) {
+    let bin = dir.join(name);
+    fs::write(&bin, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    let mut perms = fs::metadata(&bin).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&bin, perms).unwrap();
+}
+
+/// PATH for the binary: the real one when Poppler is installed, otherwise stubs that
+/// print the fixture's text.
+fn poppler_path(stubs: &Path) -> String {
+    let real = std::env::var("PATH").unwrap_or_default();
+    if which::which("pdftotext").is_ok() && which::which("pdftoppm").is_ok() {
+        return real;
+    }
+    stub_bin(stubs, "pdfinfo", "printf 'Pages:          1\\n'");
+    stub_bin(stubs, "pdftoppm", "exit 0");
+    stub_bin(
+        stubs,
+        "pdftotext",
+        "printf 'BAB I\\nKETENTUAN UMUM\\n\\nPasal 1\\nDalam Undang-Undang ini yang dimaksud dengan pekerja adalah setiap orang.\\n'",
+    );
+    format!("{}:/usr/bin:/bin", stubs.display())
+}
+
+#[test]
+fn converts_fixture_with_only_cli_args() {
+    let td = tempfile::tempdir().unwrap();
+    let stubs = td.path().join("bin");
+    fs::create_dir_all(&stubs).unwrap();
+    // an invalid prd.yaml in the working dir must not be read
+    fs::write(td.path().join("prd.yaml"), "not: [valid").unwrap();
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/uu-1-2024.pdf");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+        .current_dir(td.path())
+        .env("PATH", poppler_path(&stubs))
+        .arg("--input")
+        .arg(&fixture)
+        .arg("--output")
+        .arg("out")
+        .status()
+        .expect("run legalpdf2md");
+    assert!(status.success(), "exit {:?}", status.code());
+
+    let md = fs::read_to_string(td.path().join("out/uu-1-2024/uu-1-2024.md")).expect("markdown written");
+    assert!(md.contains("## BAB I"), "{}", md);
+    assert!(md.contains("## Pasal 1"), "{}", md);
+    assert!(td.path().join("out/uu-1-2024/uu-1-2024.meta.json").exists());
+}
+
+/// Every stdout line of a `--json-events` run is a versioned event record.
+fn event_tools(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|l| {
+            let v: serde_json::Value = serde_json::from_str(l).unwrap_or_else(|e| panic!("{}: {}", e, l));
+            assert!(v["schema"].is_u64(), "not an event record: {}", l);
+            v["tool"].as_str().unwrap_or_default().to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn dry_run_and_validate_only_stay_inside_the_json_event_stream() {
+    let td = tempfile::tempdir().unwrap();
+    let stubs = td.path().join("bin");
+    fs::create_dir_all(&stubs).unwrap();
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/uu-1-2024.pdf");
+    for flag in ["--dry-run", "--validate-only"] {
+        let out = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+            .current_dir(td.path())
+            .env("PATH", poppler_path(&stubs))
+            .arg("--input")
+            .arg(&fixture)
+            .args(["--output", "out", "--json-events", flag])
+            .output()
+            .expect("run legalpdf2md");
+        assert!(out.status.success(), "{} exit {:?}", flag, out.status.code());
+        let tool = flag.trim_start_matches("--").replace('-', "_");
+        assert!(event_tools(&out.stdout).contains(&tool), "{}", String::from_utf8_lossy(&out.stdout));
+    }
+}
+
+#[test]
+fn bench_report_is_an_event_under_json_events() {
+    let td = tempfile::tempdir().unwrap();
+    let stubs = td.path().join("bin");
+    fs::create_dir_all(&stubs).unwrap();
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/uu-1-2024.pdf");
+    let run = |extra: &[&str]| {
+        let out = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+            .current_dir(td.path())
+            .env("PATH", poppler_path(&stubs))
+            .arg("--input")
+            .arg(&fixture)
+            .args(["--output", "out", "--bench"])
+            .args(extra)
+            .output()
+            .expect("run legalpdf2md");
+        assert!(out.status.success(), "exit {:?}", out.status.code());
+        out.stdout
+    };
+    assert!(event_tools(&run(&["--json-events"])).contains(&"bench".to_string()));
+    // without --json-events the report is the plain JSON object
+    let plain: serde_json::Value = serde_json::from_slice(&run(&[])).unwrap();
+    assert!(plain.get("schema").is_none() && plain.is_object(), "{}", plain);
+}
+
+#[test]
+fn convert_prints_single_pdf_to_stdout_or_writes_output_file() {
+    let td = tempfile::tempdir().unwrap();
+    let stubs = td.path().join("bin");
+    fs::create_dir_all(&stubs).unwrap();
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/uu-1-2024.pdf");
+    let path = poppler_path(&stubs);
+
+    let out = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+        .current_dir(td.path())
+        .env("PATH", &path)
+        .arg("convert")
+        .arg(&fixture)
+        .output()
+        .expect("run legalpdf2md");
+    assert!(out.status.success(), "exit {:?}", out.status.code());
+    let md = String::from_utf8_lossy(&out.stdout);
+    assert!(md.contains("## Pasal 1"), "{}", md);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.lines().any(|l| l.contains("\"doc_id\":\"uu-1-2024\"") && l.contains("\"found\"")), "{}", stderr);
+    // nothing is left behind in the working directory
+    assert_eq!(fs::read_dir(td.path()).unwrap().count(), 1);
+
+    let status = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+        .current_dir(td.path())
+        .env("PATH", &path)
+        .args(["convert"])
+        .arg(&fixture)
+        .args(["--output", "docs/uu1.md"])
+        .status()
+        .expect("run legalpdf2md");
+    assert!(status.success());
+    assert!(fs::read_to_string(td.path().join("docs/uu1.md")).unwrap().contains("## Pasal 1"));
+    assert!(td.path().join("docs/uu1.meta.json").exists());
+}
+
+#[test]
+fn from_text_converts_form_feed_pages_without_poppler() {
+    let td = tempfile::tempdir().unwrap();
+    fs::write(
+        td.path().join("dump.txt"),
+        "BAB I\nKETENTUAN UMUM\n\nPasal 1\nDalam Undang-Undang ini yang dimaksud dengan\npekerja adalah setiap orang.\n\x0cPasal 2\nCukup jelas.\n\x0c",
+    )
+    .unwrap();
+
+    // empty PATH: nothing from Poppler or Tesseract is needed
+    let out = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+        .current_dir(td.path())
+        .env("PATH", "")
+        .args(["--from-text", "dump.txt"])
+        .output()
+        .expect("run legalpdf2md");
+    assert!(out.status.success(), "exit {:?}", out.status.code());
+    let md = String::from_utf8_lossy(&out.stdout);
+    assert!(md.contains("## Pasal 1\nDalam Undang-Undang ini yang dimaksud dengan pekerja adalah setiap orang."), "{}", md);
+    assert!(md.contains("## Pasal 2"), "{}", md);
+
+    let status = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+        .current_dir(td.path())
+        .env("PATH", "")
+        .args(["--from-text", "dump.txt", "--output", "md/dump.md"])
+        .status()
+        .expect("run legalpdf2md");
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(td.path().join("md/dump.md")).unwrap(), md);
+}
+
+#[test]
+fn invalid_keep_lines_pattern_is_rejected() {
+    let td = tempfile::tempdir().unwrap();
+    fs::write(td.path().join("dump.txt"), "Pasal 1\nCukup jelas.\n").unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+        .current_dir(td.path())
+        .env("PATH", "")
+        .args(["--from-text", "dump.txt", "--keep-lines", "(unclosed"])
+        .output()
+        .expect("run legalpdf2md");
+    assert_eq!(out.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("\"flag\":\"--keep-lines\""), "{}", stderr);
+    assert!(out.stdout.is_empty());
+}
+
+#[test]
+fn out_of_range_ocr_psm_is_rejected() {
+    let td = tempfile::tempdir().unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+        .current_dir(td.path())
+        .env("PATH", "")
+        .args(["--ocr-psm", "14"])
+        .output()
+        .expect("run legalpdf2md");
+    assert_eq!(out.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let line: serde_json::Value = stderr
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .find(|v: &serde_json::Value| v["tool"] == "cli")
+        .expect("cli error line");
+    assert_eq!((line["flag"].as_str(), line["value"].as_str(), line["error_code"].as_i64()), (Some("--ocr-psm"), Some("14"), Some(4)));
+}
+
+#[test]
+fn combine_rejects_non_markdown_output_formats() {
+    let td = tempfile::tempdir().unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+        .current_dir(td.path())
+        .env("PATH", "")
+        .args(["--combine", "korpus.md", "--output-format", "html"])
+        .output()
+        .expect("run legalpdf2md");
+    assert_eq!(out.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("\"tool\":\"cli\"") && stderr.contains("\"flag\":\"--combine\""), "{}", stderr);
+    assert!(!td.path().join("korpus.md").exists());
+}
+
+#[test]
+fn from_text_opens_with_the_menetapkan_title_unless_no_title() {
+    let td = tempfile::tempdir().unwrap();
+    fs::write(
+        td.path().join("uu-13-2003.txt"),
+        "MEMUTUSKAN:\nMenetapkan : UNDANG-UNDANG TENTANG KETENAGAKERJAAN.\n\nPasal 1\nDalam Undang-Undang ini yang dimaksud dengan\npekerja adalah setiap orang.\n",
+    )
+    .unwrap();
+    fs::write(td.path().join("uu-13-2003-lampiran.txt"), "Pasal 1\nCukup jelas.\n").unwrap();
+    let run = |extra: &[&str]| {
+        let out = Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+            .current_dir(td.path())
+            .env("PATH", "")
+            .args(extra)
+            .output()
+            .expect("run legalpdf2md");
+        assert!(out.status.success(), "exit {:?}", out.status.code());
+        String::from_utf8_lossy(&out.stdout).into_owned()
+    };
+
+    let md = run(&["--from-text", "uu-13-2003.txt"]);
+    assert!(md.starts_with("# UNDANG-UNDANG TENTANG KETENAGAKERJAAN\n\n"), "{}", md);
+    assert_eq!(md.matches("\n# ").count(), 0, "{}", md);
+    // no Menetapkan line: nomor/tahun from the file name
+    let md = run(&["--from-text", "uu-13-2003-lampiran.txt"]);
+    assert!(md.starts_with("# Nomor 13 Tahun 2003\n\n## Pasal 1"), "{}", md);
+
+    let md = run(&["--from-text", "uu-13-2003.txt", "--no-title"]);
+    assert!(!md.contains("# UNDANG-UNDANG"), "{}", md);
+    assert!(md.contains("## Pasal 1"), "{}", md);
+}
+
+#[test]
+fn ocr_all_runs_ocr_on_pages_with_a_good_text_layer() {
+    let td = tempfile::tempdir().unwrap();
+    let stubs = td.path().join("bin");
+    fs::create_dir_all(&stubs).unwrap();
+    stub_bin(&stubs, "pdfinfo", "printf 'Pages:          1\\n'");
+    stub_bin(
+        &stubs,
+        "pdftotext",
+        "printf 'Pasal 1\\nDalam Undang-Undang ini yang dimaksud dengan pekerja adalah setiap orang.\\n'",
+    );
+    // pdftoppm: last arg is the output prefix
+    stub_bin(&stubs, "pdftoppm", r#"for a; do last="$a"; done; printf 'PNG' > "$last.png""#);
+    // longer than the text layer, so it replaces the page
+    // TSV output: one line of words at confidence 90
+    stub_bin(
+        &stubs,
+        "tesseract",
+        r"printf 'level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n'; for w in Pasal 1 Dalam Undang-Undang ini yang dimaksud dengan pekerja adalah setiap orang, hasil OCR.; do printf '5\t1\t1\t1\t1\t1\t0\t0\t1\t1\t90\t%s\n' $w; done",
+    );
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/uu-1-2024.pdf");
+    let path = format!("{}:/usr/bin:/bin", stubs.display());
+
+    let run = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_legalpdf2md"))
+            .current_dir(td.path())
+            .env("PATH", &path)
+            .arg("convert")
+            .arg(&fixture)
+            .args(extra)
+            .output()
+            .expect("run legalpdf2md")
+    };
+    let out = run(&["--ocr-all"]);
+    assert!(out.status.success(), "exit {:?}", out.status.code());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("hasil OCR"));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let meta: serde_json::Value = stderr.lines().filter_map(|l| serde_json::from_str(l).ok()).find(|v: &serde_json::Value| v.get("ocr").is_some()).expect("meta");
+    assert_eq!(meta["ocr"]["all_pages"], true);
+    assert_eq!(meta["ocr"]["ocr_run_pages"], serde_json::json!([0]));
+    assert_eq!(meta["pages"][0]["ocr_confidence"], 90.0);
+
+    assert_eq!(run(&["--ocr-all", "--with-ocr=off"]).status.code(), Some(4));
+}
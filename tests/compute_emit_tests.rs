@@ -1,4 +1,4 @@
-use legalpdf_to_md::{compute_metrics, emit_files, law_cleanup, merge_pages, promote_legal_headings, Metrics};
+use legalpdf_to_md::{bench_report, BenchSample, check_quality, combine_markdown, compute_metrics, emit_combined, emit_files, emit_files_as, emit_parts, emit_review, is_up_to_date, law_cleanup, leaked_lines, merge_pages, promote_legal_headings, render, render_html, render_review, split_by_bab, sweep_temp_files, write_report, CombinedDoc, OutputFormat, QualityThresholds, ReportRow};
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::collections::hash_map::DefaultHasher;
@@ -59,3 +59,248 @@ fn idempotent_md_hash_same_runs() {
     let h2 = hash_u64(&promoted2.markdown);
     assert_eq!(h1, h2, "Markdown must be idempotent across runs");
 }
+
+#[test]
+fn render_txt_strips_heading_markup_and_emits_txt() {
+    let promoted = promote_legal_headings("BAB I KETENTUAN UMUM\nPasal 1\nIsi pasal.", "auto");
+    let txt = render(&promoted, OutputFormat::Txt);
+    assert_eq!(txt, "BAB I KETENTUAN UMUM\nPasal 1\nIsi pasal.");
+    assert_eq!(render(&promoted, OutputFormat::Md), promoted.markdown);
+
+    let td = tempfile::tempdir().unwrap();
+    let outdir = td.path().join("out");
+    let meta = serde_json::json!({"doc_id": "doc"});
+    let paths = emit_files_as(&txt, &meta, outdir.to_str().unwrap(), "doc", OutputFormat::Txt).expect("emit ok");
+    assert!(paths.md_path.ends_with("doc.txt"));
+    assert_eq!(fs::read_to_string(&paths.md_path).unwrap(), txt);
+    assert!(outdir.join("doc.meta.json").exists());
+}
+
+#[test]
+fn render_html_maps_headings_and_wraps_meta_panel() {
+    let promoted = promote_legal_headings("BAB I KETENTUAN UMUM\nPasal 1\nIsi pasal <1> & lain.\nII. Sub bagian\n1. butir satu\n2. butir dua", "auto");
+    let meta = serde_json::json!({"doc_id": "uu-1-2024", "page_count": 3, "found": {"pasal": 1}, "timestamps": {"started_ms": 5}});
+    let html = render_html(&promoted, "uu-1-2024", &meta);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>uu-1-2024</title>"));
+    assert!(html.contains("<h2>BAB I KETENTUAN UMUM</h2>"));
+    assert!(html.contains("<h2 id=\"pasal-1\">Pasal 1</h2>"));
+    assert!(html.contains("<h3>II. Sub bagian</h3>"));
+    assert!(html.contains("<p>Isi pasal &lt;1&gt; &amp; lain.</p>"));
+    assert!(html.contains("<ol>\n<li value=\"1\">butir satu</li>\n<li value=\"2\">butir dua</li>\n</ol>"));
+    assert!(html.contains("<tr><th>page_count</th><td>3</td></tr>"));
+    assert!(html.contains("<tr><th>found.pasal</th><td>1</td></tr>"));
+    assert!(!html.contains("started_ms"));
+    assert_eq!(OutputFormat::parse("html").map(|f| f.extension()), Some("html"));
+}
+
+#[test]
+fn split_by_bab_writes_parts_and_index() {
+    let promoted = promote_legal_headings("Menimbang:\nbahwa ...\nBAB I KETENTUAN UMUM\nPasal 1\nIsi.\nBAB II PENUTUP\nPasal 2\nSelesai.", "auto");
+    let parts = split_by_bab(&promoted.markdown);
+    let names: Vec<&str> = parts.iter().map(|p| p.file_name.as_str()).collect();
+    assert_eq!(names, vec!["00-preamble.md", "bab-01.md", "bab-02.md"]);
+    assert!(parts[0].content.starts_with("## Menimbang"));
+    assert!(parts[1].content.starts_with("## BAB I KETENTUAN UMUM"));
+    assert_eq!(parts[2].title, "BAB II PENUTUP");
+
+    let td = tempfile::tempdir().unwrap();
+    let dir = td.path().join("doc");
+    let written = emit_parts(&parts, dir.to_str().unwrap(), "doc").expect("parts ok");
+    assert_eq!(written.len(), 4);
+    let index = fs::read_to_string(dir.join("index.md")).unwrap();
+    assert!(index.contains("- [BAB I KETENTUAN UMUM](bab-01.md)"));
+    assert_eq!(fs::read_to_string(dir.join("bab-02.md")).unwrap(), parts[2].content);
+}
+
+#[test]
+fn up_to_date_requires_matching_input_hash() {
+    let td = tempfile::tempdir().unwrap();
+    let outdir = td.path().to_str().unwrap();
+    let md = td.path().join("doc.md");
+    let meta = td.path().join("doc.meta.json");
+    assert!(!is_up_to_date(&md, &meta, "abc"));
+
+    emit_files("# x", &serde_json::json!({"doc_id": "doc", "input_sha256": "abc"}), outdir, "doc").unwrap();
+    assert!(is_up_to_date(&md, &meta, "abc"));
+    assert!(!is_up_to_date(&md, &meta, "def"));
+
+    // older meta without the field is treated as stale
+    emit_files("# x", &serde_json::json!({"doc_id": "doc"}), outdir, "doc").unwrap();
+    assert!(!is_up_to_date(&md, &meta, "abc"));
+}
+
+#[test]
+fn word_coverage_counts_dropped_words() {
+    let found = promote_legal_headings("", "auto").found;
+    let full = compute_metrics("Pasal 1 Setiap orang berhak", "## Pasal 1\nSetiap orang berhak", &found);
+    assert_eq!(full.word_coverage, 1.0);
+    // dropping one word while duplicating another keeps char ratio high but lowers word coverage
+    let lossy = compute_metrics("Setiap orang berhak bekerja", "Setiap orang orang berhak", &found);
+    assert_eq!(lossy.word_coverage, 0.75);
+    assert_eq!(compute_metrics("", "x", &found).word_coverage, 0.0);
+}
+
+#[test]
+fn split_violation_lines_point_at_offending_lines() {
+    let found = promote_legal_headings("", "auto").found;
+    let md = "## Pasal 1\nIsi (\n2) lanjut\nb.\nteks\n3.\nakhir";
+    let m = compute_metrics(md, md, &found);
+    assert_eq!(m.split_violations, 3);
+    assert_eq!(m.split_violation_lines, vec![2, 4, 6]);
+}
+
+#[test]
+fn leak_rate_is_broken_down_by_header_and_footer() {
+    let found = promote_legal_headings("", "auto").found;
+    let raw = "LEMBARAN NEGARA REPUBLIK INDONESIA\nIsi\n- 1 -\nLEMBARAN NEGARA REPUBLIK INDONESIA\n- 2 -";
+    let md = "Isi\n- 2 -";
+    let m = compute_metrics(raw, md, &found);
+    assert_eq!(m.header_leak_rate, 0.0);
+    assert!((m.footer_leak_rate - 1.0 / 3.0).abs() < 1e-9);
+    assert!((m.leak_rate - 1.0 / 5.0).abs() < 1e-9);
+    assert_eq!((m.leak_counts.headers_detected, m.leak_counts.headers_remaining), (2, 0));
+    assert_eq!((m.leak_counts.footers_detected, m.leak_counts.footers_remaining), (2, 1));
+    let empty = compute_metrics("", "", &found);
+    assert_eq!((empty.leak_rate, empty.header_leak_rate, empty.footer_leak_rate), (0.0, 0.0, 0.0));
+}
+
+#[test]
+fn leaked_lines_lists_surviving_headers_and_footers() {
+    let md = "## Pasal 1\nPRESIDEN REPUBLIK INDONESIA\nIsi.\n- 3 -\nPRESIDEN REPUBLIK INDONESIA,";
+    let leaked = leaked_lines(md);
+    let got: Vec<(usize, &str)> = leaked.iter().map(|l| (l.line, l.text.as_str())).collect();
+    assert_eq!(got, vec![(2, "PRESIDEN REPUBLIK INDONESIA"), (4, "- 3 -")]);
+}
+
+#[test]
+fn quality_gate_reports_each_violated_threshold() {
+    let found = promote_legal_headings("", "auto").found;
+    let m = compute_metrics("abcd\n- 1 -", "a\n- 1 -\nb.", &found);
+    assert!(check_quality(&m, &QualityThresholds::default()).is_empty());
+    let t = QualityThresholds { min_coverage: Some(0.99), max_leak_rate: Some(0.0), max_split_violations: Some(0) };
+    let names: Vec<String> = check_quality(&m, &t).into_iter().map(|v| v.threshold).collect();
+    assert_eq!(names, vec!["min_coverage", "max_leak_rate", "max_split_violations"]);
+}
+
+#[test]
+fn emit_combined_concatenates_in_order_with_markers() {
+    let td = tempfile::tempdir().unwrap();
+    let docs = vec![
+        CombinedDoc { doc_id: "uu-13-2003".into(), content: "## Pasal 1\nIsi.\n".into(), meta: serde_json::json!({"doc_id": "uu-13-2003"}) },
+        CombinedDoc { doc_id: "pp-5-2021".into(), content: "## Pasal 1\nLain.".into(), meta: serde_json::json!({"doc_id": "pp-5-2021"}) },
+    ];
+    let out = td.path().join("corpus/all.md");
+    let paths = emit_combined(&docs, &out).expect("combined written");
+    let md = std::fs::read_to_string(&out).unwrap();
+    assert_eq!(md, "<!-- doc:uu-13-2003 -->\n# uu-13-2003\n\n## Pasal 1\nIsi.\n\n<!-- doc:pp-5-2021 -->\n# pp-5-2021\n\n## Pasal 1\nLain.\n");
+    assert_eq!(md, combine_markdown(&docs));
+    let metas: serde_json::Value = serde_json::from_slice(&std::fs::read(&paths.meta_path).unwrap()).unwrap();
+    assert!(paths.meta_path.ends_with("corpus/combined.meta.json"));
+    assert_eq!(metas[1]["doc_id"], "pp-5-2021");
+}
+
+#[test]
+fn write_report_sorts_by_coverage_and_picks_format_from_extension() {
+    let td = tempfile::tempdir().unwrap();
+    let row = |doc_id: &str, coverage: f64| ReportRow {
+        doc_id: doc_id.into(), page_count: 3, coverage, leak_rate: 0.0, split_violations: 0, ocr_ran: false, pasal: 2, bab: 1,
+    };
+    let rows = vec![row("uu-13-2003", 0.98), row("pp, 5", 0.5)];
+    let csv = td.path().join("report.csv");
+    write_report(&rows, &csv).expect("csv written");
+    let text = fs::read_to_string(&csv).unwrap();
+    assert_eq!(
+        text,
+        "doc_id,page_count,coverage,leak_rate,split_violations,ocr_ran,pasal,bab\n\"pp, 5\",3,0.5,0,0,false,2,1\nuu-13-2003,3,0.98,0,0,false,2,1\n"
+    );
+    let json = td.path().join("report.json");
+    write_report(&rows, &json).expect("json written");
+    let parsed: Vec<ReportRow> = serde_json::from_slice(&fs::read(&json).unwrap()).unwrap();
+    assert_eq!(parsed[0].doc_id, "pp, 5");
+    assert_eq!(parsed[1], rows[0]);
+}
+
+#[test]
+fn review_pairs_each_raw_page_with_its_cleaned_markdown() {
+    let raw = vec!["PRESIDEN REPUBLIK INDONESIA\nPasal 1\nIsi ```kode```.".to_string(), "Pasal 2\nLain.".to_string()];
+    let md = "<!-- page:1 -->\n## Pasal 1\nIsi ```kode```.\n<!-- page:2 -->\n## Pasal 2\nLain.\n";
+    let review = render_review(&raw, md, "uu-1-2024", 1);
+    assert_eq!(
+        review,
+        "# Review: uu-1-2024\n\n## Page 1\n\n````text\nPRESIDEN REPUBLIK INDONESIA\nPasal 1\nIsi ```kode```.\n````\n\n## Pasal 1\nIsi ```kode```.\n\n---\n\n## Page 2\n\n```text\nPasal 2\nLain.\n```\n\n## Pasal 2\nLain.\n\n---\n"
+    );
+    // the title above the first marker stays with page 1
+    let titled = render_review(&raw, &format!("# UU 1/2024\n\n{}", md), "uu-1-2024", 1);
+    assert!(titled.contains("````\n\n# UU 1/2024\n\n## Pasal 1\n"), "{}", titled);
+    // no markers: the whole markdown is one section after the last page
+    let flat = render_review(&raw, "## Pasal 1\nIsi.\n## Pasal 2\nLain.", "uu-1-2024", 1);
+    assert!(flat.contains("```\n\n*(no cleaned text)*\n\n---\n\n## Page 2"), "{}", flat);
+    assert!(flat.ends_with("```\n\n## Pasal 1\nIsi.\n## Pasal 2\nLain.\n\n---\n"), "{}", flat);
+
+    let td = tempfile::tempdir().unwrap();
+    let path = emit_review(&review, td.path().to_str().unwrap(), "uu-1-2024").unwrap();
+    assert!(path.ends_with("uu-1-2024.review.md"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), review);
+    assert_eq!(fs::read_dir(td.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn emit_files_leaves_complete_files_and_no_temps() {
+    let td = tempfile::tempdir().unwrap();
+    let outdir = td.path().to_str().unwrap();
+    let md = "## Pasal 1\n".repeat(2000);
+    let paths = emit_files(&md, &serde_json::json!({"doc_id": "uu-1-2024"}), outdir, "uu-1-2024").unwrap();
+    assert_eq!(fs::read_to_string(&paths.md_path).unwrap(), md);
+    assert!(fs::metadata(&paths.meta_path).unwrap().len() > 0);
+    let leftovers: Vec<_> = fs::read_dir(td.path()).unwrap().filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().contains(".tmp."))
+        .collect();
+    assert!(leftovers.is_empty());
+}
+
+#[test]
+fn sweep_removes_only_temp_files_of_dead_processes() {
+    let td = tempfile::tempdir().unwrap();
+    let me = std::process::id();
+    // PIDs are capped well below u32::MAX - 1 on every supported OS, so this one is never alive
+    let dead = u32::MAX - 1;
+    let stale = [
+        format!("uu-1.md.tmp.{}", dead),
+        format!("uu-1.meta.json.tmp.{}", dead),
+        format!(".combined.md.tmp.{}", dead),
+        format!("uu-3.html.tmp.{}", dead),
+        format!("uu-4.txt.tmp.{}", dead),
+    ];
+    let kept = [format!("uu-2.md.tmp.{}", me), format!("notes.tmp.{}", dead), "uu-1.md".to_string()];
+    for name in stale.iter().chain(kept.iter()) {
+        fs::write(td.path().join(name), b"x").unwrap();
+    }
+
+    let removed = sweep_temp_files(td.path());
+    assert_eq!(removed.len(), stale.len());
+    for name in &stale {
+        assert!(!td.path().join(name).exists(), "{} should be swept", name);
+    }
+    for name in &kept {
+        assert!(td.path().join(name).exists(), "{} should be kept", name);
+    }
+}
+
+#[test]
+fn bench_report_aggregates_step_timings() {
+    let samples = vec![
+        BenchSample { pages: 2, ocr_pages: 0, extract_ms: 20, ocr_ms: 0, cleanup_ms: 4 },
+        BenchSample { pages: 2, ocr_pages: 1, extract_ms: 60, ocr_ms: 500, cleanup_ms: 6 },
+    ];
+    let r = bench_report(&samples, 2000);
+    assert_eq!((r.documents, r.pages, r.wall_ms), (2, 4, 2000));
+    assert_eq!(r.pages_per_second, 2.0);
+    assert_eq!(r.extract_per_page.mean_ms, 20.0);
+    assert_eq!(r.extract_per_page.p95_ms, 30.0);
+    assert_eq!((r.ocr_per_page.mean_ms, r.ocr_per_page.p95_ms), (500.0, 500.0));
+    assert_eq!((r.breakdown.extraction_ms, r.breakdown.ocr_ms, r.breakdown.cleanup_ms), (80, 500, 10));
+
+    let empty = bench_report(&[], 0);
+    assert_eq!((empty.pages_per_second, empty.extract_per_page.p95_ms), (0.0, 0.0));
+}
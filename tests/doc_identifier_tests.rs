@@ -0,0 +1,45 @@
+use legalpdf_to_md::{expand_name_template, parse_doc_identifier, slugify, validate_name_template, DocIdentifier, SLUG_MAX_LEN};
+
+fn id(nomor: &str, tahun: u16) -> Option<DocIdentifier> {
+    Some(DocIdentifier { nomor: nomor.to_string(), tahun })
+}
+
+#[test]
+fn parses_common_filename_variants() {
+    assert_eq!(parse_doc_identifier("13-2003.pdf"), id("13", 2003));
+    assert_eq!(parse_doc_identifier("uu-13-2003.pdf"), id("13", 2003));
+    assert_eq!(parse_doc_identifier("PP_No_5_Tahun_2021.pdf"), id("5", 2021));
+    assert_eq!(parse_doc_identifier("Perwali Nomor 12 Tahun 2019.pdf"), id("12", 2019));
+}
+
+#[test]
+fn unparseable_names_yield_none() {
+    assert_eq!(parse_doc_identifier("lampiran.pdf"), None);
+    assert_eq!(parse_doc_identifier("scan-12345.pdf"), None);
+}
+
+#[test]
+fn slugify_collapses_punctuation_and_transliterates() {
+    assert_eq!(slugify("Perwali No. 5 (2021)"), "perwali-no-5-2021");
+    assert_eq!(slugify("Peraturan Café Über"), "peraturan-cafe-uber");
+    assert_eq!(slugify("--()--"), "doc");
+    let long = "a".repeat(200);
+    assert_eq!(slugify(&long).len(), SLUG_MAX_LEN);
+    assert_eq!(slugify(&format!("{}-b", "a".repeat(79))), "a".repeat(79));
+}
+
+#[test]
+fn name_template_expands_placeholders_and_rejects_unsafe_names() {
+    let ident = parse_doc_identifier("uu-13-2003.pdf");
+    assert_eq!(expand_name_template("UU-{nomor}-{tahun}", "uu-13-2003", ident.as_ref()).unwrap(), "UU-13-2003");
+    assert_eq!(expand_name_template("{doc_id}", "uu-13-2003", None).unwrap(), "uu-13-2003");
+    assert!(expand_name_template("{nomor}", "lampiran", None).is_err());
+
+    assert!(validate_name_template("UU-{nomor}-{tahun}").is_ok());
+    assert!(validate_name_template("{slug}_v2").is_ok());
+    assert!(validate_name_template("{judul}").is_err());
+    assert!(validate_name_template("../{doc_id}").is_err());
+    assert!(validate_name_template("a b {doc_id}").is_err());
+    assert!(validate_name_template(".{doc_id}").is_err());
+    assert!(validate_name_template("").is_err());
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::PathBuf;
 
-use legalpdf_to_md::enumerate_pdfs;
+use legalpdf_to_md::{apply_ignore_file, dedup_by_content, djvu_glob, is_djvu, enumerate_pdfs, exclude_paths, glob_input_root, filter_modified_since, parse_since, read_input_list, split_glob_root, validate_prd};
 
 #[test]
 fn enumerate_pdfs_finds_nested_files() {
@@ -24,8 +24,203 @@ fn enumerate_pdfs_empty_returns_error_with_guidance() {
     let td = tempfile::tempdir().unwrap();
     let base = td.path();
     let pattern = format!("{}/input/**/*.pdf", base.display());
-    let err = enumerate_pdfs(&pattern).err().expect("should be error");
+    let err = enumerate_pdfs(&pattern).expect_err("should be error");
     let msg = format!("{}", err);
     assert_eq!(msg, "NoFilesFound");
 }
 
+
+#[test]
+fn glob_root_walks_from_fixed_prefix_of_absolute_patterns() {
+    let (root, pat) = split_glob_root("/srv/corpus/uu/**/*.pdf");
+    assert_eq!((root, pat.as_str()), (PathBuf::from("/srv/corpus/uu"), "**/*.pdf"));
+    let (root, pat) = split_glob_root("./input/**/*.pdf");
+    assert_eq!((root, pat.as_str()), (PathBuf::from("input"), "**/*.pdf"));
+    let (root, pat) = split_glob_root("**/*.pdf");
+    assert_eq!((root, pat.as_str()), (PathBuf::from("."), "**/*.pdf"));
+    let (root, pat) = split_glob_root("../corpus/*.pdf");
+    assert_eq!((root, pat.as_str()), (PathBuf::from("../corpus"), "*.pdf"));
+    // no wildcard: walk the parent for the file itself
+    let (root, pat) = split_glob_root("/srv/corpus/a.pdf");
+    assert_eq!((root, pat.as_str()), (PathBuf::from("/srv/corpus"), "a.pdf"));
+    let (root, pat) = split_glob_root("input/a.pdf");
+    assert_eq!((root, pat.as_str()), (PathBuf::from("input"), "a.pdf"));
+    // the .lpdfignore root is the walk root
+    for g in ["/srv/corpus/uu/**/*.pdf", "./input/**/*.pdf", "../corpus/*.pdf", "input/a.pdf", "*.pdf"] {
+        assert_eq!(glob_input_root(g), split_glob_root(g).0, "{}", g);
+    }
+}
+
+#[test]
+fn enumerate_pdfs_accepts_literal_absolute_path() {
+    let td = tempfile::tempdir().unwrap();
+    let f = td.path().join("uu-1-2024.pdf");
+    fs::write(&f, b"%PDF-1.4\n").unwrap();
+    fs::write(td.path().join("other.pdf"), b"%PDF-1.4\n").unwrap();
+    assert_eq!(enumerate_pdfs(&f.display().to_string()).unwrap(), vec![f]);
+}
+
+#[test]
+fn exclude_paths_drops_matching_subdir() {
+    let td = tempfile::tempdir().unwrap();
+    let base = td.path();
+    for rel in ["input/uu/A-2020.pdf", "input/uu/lampiran/A-2020-lampiran.pdf", "input/pp/B-2021.pdf"] {
+        let p = base.join(rel);
+        fs::create_dir_all(p.parent().unwrap()).unwrap();
+        fs::write(&p, b"%PDF-1.4\n").unwrap();
+    }
+
+    let files = enumerate_pdfs(&format!("{}/input/**/*.pdf", base.display())).unwrap();
+    assert_eq!(files.len(), 3);
+    let kept = exclude_paths(files, &["**/lampiran/**".to_string()]).unwrap();
+    let kept: Vec<String> = kept.iter().map(|p| p.strip_prefix(base).unwrap().to_string_lossy().into_owned()).collect();
+    assert_eq!(kept, vec!["input/pp/B-2021.pdf", "input/uu/A-2020.pdf"]);
+
+    assert!(exclude_paths(vec![], &["a[".to_string()]).is_err());
+}
+
+#[test]
+fn dedup_by_content_keeps_first_of_identical_files() {
+    let td = tempfile::tempdir().unwrap();
+    let base = td.path().join("input");
+    fs::create_dir_all(&base).unwrap();
+    let fixture = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/uu-1-2024.pdf")).unwrap();
+    fs::write(base.join("a-uu-1-2024.pdf"), &fixture).unwrap();
+    fs::write(base.join("b-salinan-uu-1-2024.pdf"), &fixture).unwrap();
+    fs::write(base.join("c-pp-2-2024.pdf"), b"%PDF-1.4\nlain\n").unwrap();
+
+    let files = enumerate_pdfs(&format!("{}/*.pdf", base.display())).unwrap();
+    let (kept, dups) = dedup_by_content(files);
+    let names = |ps: &[PathBuf]| ps.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect::<Vec<_>>();
+    assert_eq!(names(&kept), vec!["a-uu-1-2024.pdf", "c-pp-2-2024.pdf"]);
+    assert_eq!(dups.len(), 1);
+    assert_eq!(dups[0].0, base.join("b-salinan-uu-1-2024.pdf"));
+    assert_eq!(dups[0].1, base.join("a-uu-1-2024.pdf"));
+}
+
+#[test]
+fn input_list_keeps_file_order_and_reports_bad_entries() {
+    let td = tempfile::tempdir().unwrap();
+    let b = td.path().join("b.pdf");
+    let a = td.path().join("a.pdf");
+    let txt = td.path().join("notes.txt");
+    for p in [&a, &b, &txt] {
+        fs::write(p, b"x").unwrap();
+    }
+    let missing = td.path().join("gone.pdf");
+    let list = td.path().join("list.txt");
+    fs::write(
+        &list,
+        format!("# curated\n{}\n\n{}\n{}\n  {}\n{}\n", b.display(), a.display(), missing.display(), txt.display(), b.display()),
+    )
+    .unwrap();
+
+    let got = read_input_list(&list).unwrap();
+    assert_eq!(got.paths, vec![b, a]);
+    assert_eq!(got.missing, vec![missing]);
+    assert_eq!(got.not_pdf, vec![txt]);
+    assert!(read_input_list(&td.path().join("nope.txt")).is_err());
+}
+
+#[test]
+fn since_parses_durations_and_utc_dates() {
+    use std::time::{Duration, UNIX_EPOCH};
+    let now = UNIX_EPOCH + Duration::from_secs(1_704_153_600); // 2024-01-02T00:00:00Z
+    assert_eq!(parse_since("24h", now).unwrap(), UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+    assert_eq!(parse_since("2w", now).unwrap(), now - Duration::from_secs(14 * 86_400));
+    assert_eq!(parse_since("2024-01-01", now).unwrap(), UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+    assert_eq!(parse_since("2024-01-01T06:30", now).unwrap(), UNIX_EPOCH + Duration::from_secs(1_704_067_200 + 23_400));
+    assert!(parse_since("yesterday", now).is_err());
+    assert!(parse_since("2024-13-01", now).is_err());
+    // day checked against the month, leap years included
+    assert_eq!(parse_since("2024-02-31", now), Err("invalid date: 2024-02-31".to_string()));
+    assert!(parse_since("2023-02-29", now).is_err());
+    assert!(parse_since("2024-04-31", now).is_err());
+    assert!(parse_since("2024-02-29", now).is_ok());
+    assert!(parse_since("2000-02-29", now).is_ok());
+    assert!(parse_since("1900-02-29", now).is_err());
+}
+
+#[test]
+fn since_filter_keeps_newer_and_unreadable_files() {
+    let td = tempfile::tempdir().unwrap();
+    let old = td.path().join("old.pdf");
+    let new = td.path().join("new.pdf");
+    fs::write(&old, b"x").unwrap();
+    fs::write(&new, b"x").unwrap();
+    let cutoff = fs::metadata(&new).unwrap().modified().unwrap() - std::time::Duration::from_secs(60);
+    fs::File::options().write(true).open(&old).unwrap().set_modified(cutoff - std::time::Duration::from_secs(3_600)).unwrap();
+    let gone = td.path().join("gone.pdf");
+
+    let (kept, skipped) = filter_modified_since(vec![old, new.clone(), gone.clone()], cutoff);
+    assert_eq!(kept, vec![new, gone]);
+    assert_eq!(skipped, 1);
+}
+
+#[test]
+fn lpdfignore_in_the_input_root_drops_matching_paths() {
+    let td = tempfile::tempdir().unwrap();
+    let input = td.path().join("input");
+    for rel in ["uu/13-2003.pdf", "uu/13-2003-draft.pdf", "lampiran/a.pdf", "lampiran/keep.pdf"] {
+        let p = input.join(rel);
+        fs::create_dir_all(p.parent().unwrap()).unwrap();
+        fs::write(&p, b"%PDF-1.4\n").unwrap();
+    }
+    let pattern = format!("{}/**/*.pdf", input.display());
+    assert_eq!(glob_input_root(&pattern), input);
+    assert_eq!(glob_input_root("./input/**/*.pdf"), PathBuf::from("input"));
+    assert_eq!(glob_input_root("**/*.pdf"), PathBuf::from("."));
+
+    // absent file: no-op
+    let found = enumerate_pdfs(&pattern).unwrap();
+    let (kept, ignored) = apply_ignore_file(found.clone(), &input).unwrap();
+    assert_eq!((kept.len(), ignored), (4, 0));
+
+    fs::write(input.join(".lpdfignore"), "# drafts and attachments\n*-draft.pdf\nlampiran/*\n!lampiran/keep.pdf\n").unwrap();
+    let (kept, ignored) = apply_ignore_file(found, &input).unwrap();
+    let kept: Vec<String> = kept.iter().map(|p| p.strip_prefix(&input).unwrap().to_string_lossy().into_owned()).collect();
+    assert_eq!(kept, vec!["lampiran/keep.pdf", "uu/13-2003.pdf"]);
+    assert_eq!(ignored, 2);
+}
+
+#[test]
+fn djvu_glob_mirrors_pdf_globs() {
+    assert_eq!(djvu_glob("./input/**/*.pdf").as_deref(), Some("./input/**/*.{djvu,djv}"));
+    assert_eq!(djvu_glob("/srv/arsip/*.PDF").as_deref(), Some("/srv/arsip/*.{djvu,djv}"));
+    assert_eq!(djvu_glob("./input/**/*"), None);
+    assert_eq!(djvu_glob("pdf"), None);
+    assert!(is_djvu(std::path::Path::new("arsip/uu-1-1950.DjVu")));
+    assert!(is_djvu(std::path::Path::new("a.djv")));
+    assert!(!is_djvu(std::path::Path::new("a.pdf")));
+}
+
+#[test]
+fn djvu_glob_enumerates_both_djvu_extensions() {
+    let td = tempfile::tempdir().unwrap();
+    for name in ["a.djvu", "b.djv", "c.pdf"] {
+        fs::write(td.path().join(name), b"x").unwrap();
+    }
+    let glob = djvu_glob(&format!("{}/*.pdf", td.path().display())).unwrap();
+    let found = enumerate_pdfs(&glob).unwrap();
+    assert_eq!(found, vec![td.path().join("a.djvu"), td.path().join("b.djv")]);
+}
+
+#[test]
+fn prd_relative_glob_enumerates_from_another_working_directory() {
+    // the test runs in the crate directory; the PRD and its input live in a temp dir
+    let td = tempfile::tempdir().unwrap();
+    let project = td.path().join("project");
+    fs::create_dir_all(project.join("input/uu")).unwrap();
+    fs::write(project.join("input/uu/13-2003.pdf"), b"%PDF-1.4\n").unwrap();
+    let prd_path = project.join("prd.yaml");
+    fs::write(
+        &prd_path,
+        "id: t\ntools:\n  - name: check_deps\n  - name: enumerate_pdfs\ndatasources:\n  - path: \"./input/**/*.pdf\"\noutputs:\n  dir: ./output\n",
+    )
+    .unwrap();
+    assert_ne!(std::env::current_dir().unwrap(), project);
+
+    let prd = validate_prd(&prd_path).expect("valid prd");
+    let files = enumerate_pdfs(&prd.input_glob()).expect("found via the PRD directory");
+    assert_eq!(files, vec![project.join("input/uu/13-2003.pdf")]);
+}
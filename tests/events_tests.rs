@@ -0,0 +1,122 @@
+use legalpdf_to_md::{convert_pdf_batch, process_text_batch, ConvertOptions, Event, EventRecord, LogLevel, PdfDocument, PdfOptions, ProgressEvent, ProgressHook, TextDocument, EVENT_SCHEMA_VERSION};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Mutex;
+
+// PATH is process-global; serialize tests that stub Poppler.
+static PATH_LOCK: Mutex<()> = Mutex::new(());
+
+fn stub_bin(dir: &Path, name: &str, script: &str) {
+    let bin = dir.join(name);
+    fs::write(&bin, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    let mut perms = fs::metadata(&bin).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&bin, perms).unwrap();
+}
+
+#[test]
+fn log_line_with_error_becomes_error_event() {
+    let log = serde_json::json!({"tool":"poppler_extract","file":"a.pdf","error":"EncryptedPDF","error_code":1});
+    let ev = Event::from_log(&log);
+    assert_eq!(
+        ev,
+        Event::Error {
+            tool: "poppler_extract".into(),
+            error: "EncryptedPDF".into(),
+            error_code: Some(1),
+            data: serde_json::json!({"file":"a.pdf"}),
+        }
+    );
+}
+
+#[test]
+fn event_record_serializes_flat_with_stable_fields() {
+    let log = serde_json::json!({"tool":"enumerate_pdfs","count":3});
+    let rec = EventRecord::now(Event::from_log(&log));
+    let v = serde_json::to_value(&rec).unwrap();
+    assert_eq!(v["schema"], EVENT_SCHEMA_VERSION);
+    assert_eq!(v["event"], "step");
+    assert_eq!(v["tool"], "enumerate_pdfs");
+    assert_eq!(v["data"]["count"], 3);
+    assert!(v["ts_ms"].as_u64().unwrap() > 0);
+    let back: EventRecord = serde_json::from_value(v).unwrap();
+    assert_eq!(back, rec);
+}
+
+#[test]
+fn progress_hook_receives_events_in_order() {
+    let docs: Vec<TextDocument> = ["uu-1-2024", "pp-2-2024"]
+        .iter()
+        .map(|id| TextDocument { doc_id: id.to_string(), pages: vec!["Pasal 1\nCukup jelas.".to_string()] })
+        .collect();
+    let seen = std::cell::RefCell::new(Vec::new());
+    let record = |ev: ProgressEvent| seen.borrow_mut().push(ev);
+    let hook: ProgressHook = Some(&record);
+    let results = process_text_batch(&docs, &ConvertOptions::default(), hook);
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        seen.into_inner(),
+        vec![
+            ProgressEvent::Started { total: 2 },
+            ProgressEvent::Document { index: 0, total: 2, doc_id: "uu-1-2024".into() },
+            ProgressEvent::Document { index: 1, total: 2, doc_id: "pp-2-2024".into() },
+            ProgressEvent::Finished { total: 2 },
+        ]
+    );
+    let v = serde_json::to_value(ProgressEvent::Document { index: 0, total: 2, doc_id: "uu-1-2024".into() }).unwrap();
+    assert_eq!(v["progress"], "document");
+    // no hook: same results, nothing to call
+    assert_eq!(process_text_batch(&docs, &ConvertOptions::default(), None).len(), 2);
+}
+
+#[test]
+fn pdf_batch_reports_progress_and_logs_through_hooks() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "pdfinfo", "printf 'Pages:          1\\n'");
+    stub_bin(&bin, "pdftotext", "printf 'Pasal 1\\nDalam Undang-Undang ini yang dimaksud dengan pekerja adalah setiap orang.\\n'");
+    let docs: Vec<PdfDocument> = ["uu-1-2024", "pp-2-2024"]
+        .iter()
+        .map(|id| {
+            let path = td.path().join(format!("{}.pdf", id));
+            fs::write(&path, b"%PDF-1.4\n").unwrap();
+            let outdir = td.path().join("out").join(id).to_string_lossy().into_owned();
+            PdfDocument { path, datasource: None, doc_id: id.to_string(), outdir, artifacts_dir: None, input_sha256: None }
+        })
+        .collect();
+    let seen = std::cell::RefCell::new(Vec::new());
+    let record = |ev: ProgressEvent| seen.borrow_mut().push(ev);
+    let logs = std::cell::RefCell::new(Vec::new());
+    let log = |level: LogLevel, value: serde_json::Value| logs.borrow_mut().push((level, value));
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let results = convert_pdf_batch(&docs, &PdfOptions::default(), Some(&log), Some(&record));
+    std::env::set_var("PATH", old_path);
+
+    let results = results.unwrap();
+    assert_eq!(results.iter().map(|r| r.doc_id.as_str()).collect::<Vec<_>>(), vec!["uu-1-2024", "pp-2-2024"]);
+    assert!(results[0].rendered.contains("## Pasal 1"), "{}", results[0].rendered);
+    assert_eq!(results[0].meta["doc_id"], "uu-1-2024");
+    assert!(td.path().join("out/pp-2-2024/pp-2-2024.md").exists());
+    assert_eq!(
+        seen.into_inner(),
+        vec![
+            ProgressEvent::Started { total: 2 },
+            ProgressEvent::Document { index: 0, total: 2, doc_id: "uu-1-2024".into() },
+            ProgressEvent::Document { index: 1, total: 2, doc_id: "pp-2-2024".into() },
+            ProgressEvent::Finished { total: 2 },
+        ]
+    );
+    let finished: Vec<_> = logs
+        .into_inner()
+        .into_iter()
+        .filter(|(level, v)| *level == LogLevel::Info && v["status"] == "finished")
+        .map(|(_, v)| v["doc_id"].clone())
+        .collect();
+    assert_eq!(finished, vec!["uu-1-2024", "pp-2-2024"]);
+}
+
@@ -1,4 +1,4 @@
-use legalpdf_to_md::{law_cleanup, promote_legal_headings};
+use legalpdf_to_md::{check_idempotent, check_structure, collapse_letter_spacing, extract_footnotes, is_formula_line, render_footnotes, law_cleanup, law_cleanup_with, parse_roman, process_text, promote_legal_headings, recount_headings, repair_mojibake, resolve_law_mode, is_gibberish_line, strip_gibberish, split_text_pages, strip_toc, to_roman, wrap_markdown, CleanupConfig, ConvertOptions, LawMode};
 
 #[test]
 fn cleanup_removes_headers_and_joins() {
@@ -27,3 +27,439 @@ fn promote_detects_pasal_bab_and_sections() {
     assert!(md.found.penjelasan);
 }
 
+
+#[test]
+fn cleanup_keeps_list_numbers_and_nests_letters() {
+    let input = "1. Satu meliputi:\n   a. sub a;\n   b. sub b;\n2. Dua;\n3. Tiga.";
+    let out = law_cleanup(input, "auto");
+    assert_eq!(out.cleaned, "1. Satu meliputi:\n   - a. sub a;\n   - b. sub b;\n2. Dua;\n3. Tiga.");
+    // fixed point: a second pass leaves the list alone
+    assert_eq!(law_cleanup(&out.cleaned, "auto").cleaned, out.cleaned);
+}
+
+#[test]
+fn cleanup_letters_without_numbered_parent_stay_top_level() {
+    let out = law_cleanup("Pasal 2\na. pertama;\nb. kedua.", "auto");
+    assert_eq!(out.cleaned, "Pasal 2\n- a. pertama;\n- b. kedua.");
+}
+
+#[test]
+fn strip_toc_removes_dotted_block_and_stops_at_body() {
+    let pages = vec![
+        "DAFTAR ISI\nBAB I KETENTUAN UMUM .......... 3\n\nPasal 1 ............ 3".to_string(),
+        "BAB II ASAS ........ 5\nPasal 1\nDalam Undang-Undang ini ... 3 hal".to_string(),
+    ];
+    let (out, removed) = strip_toc(&pages);
+    assert_eq!(removed, 4);
+    assert_eq!(out[0], "");
+    assert_eq!(out[1], "Pasal 1\nDalam Undang-Undang ini ... 3 hal");
+    // pages without a TOC header are untouched
+    let plain = vec!["Pasal 2 .... 4".to_string()];
+    assert_eq!(strip_toc(&plain), (plain.clone(), 0));
+}
+
+#[test]
+fn promote_renders_considerata_and_basis_as_lists() {
+    let input = "Menimbang : a. bahwa negara menjamin hak\n   setiap warga negara;\nb. bahwa berdasarkan pertimbangan\nsebagaimana dimaksud dalam huruf a;\nMengingat :\n1. Pasal 5 ayat (1) dan Pasal 20\nUndang-Undang Dasar 1945;\n2. Undang-Undang Nomor 13 Tahun 2003;\n\nDengan Persetujuan Bersama\nBAB I\nPasal 1";
+    let out = promote_legal_headings(input, "auto");
+    let md = out.markdown;
+    assert!(md.contains("## Menimbang\n- a. bahwa negara menjamin hak setiap warga negara;\n- b. bahwa berdasarkan pertimbangan sebagaimana dimaksud dalam huruf a;\n## Mengingat"));
+    assert!(md.contains("1. Pasal 5 ayat (1) dan Pasal 20 Undang-Undang Dasar 1945;\n2. Undang-Undang Nomor 13 Tahun 2003;\n\nDengan Persetujuan Bersama"));
+    assert!(md.contains("## BAB I\n## Pasal 1"));
+    assert_eq!(out.found.considerata, 2);
+    assert_eq!(out.found.basis, 2);
+}
+
+#[test]
+fn promote_memutuskan_and_captures_menetapkan_title() {
+    let input = "Dengan Persetujuan Bersama\nM E M U T U S K A N :\nMenetapkan : PERATURAN MENTERI KETENAGAKERJAAN TENTANG\nPELAKSANAAN PROGRAM JAMINAN HARI TUA.\n\nBAB I\nKETENTUAN UMUM";
+    let out = promote_legal_headings(input, "auto");
+    assert!(out.markdown.contains("Dengan Persetujuan Bersama\n## MEMUTUSKAN\nMenetapkan : PERATURAN"));
+    assert_eq!(
+        out.found.title.as_deref(),
+        Some("PERATURAN MENTERI KETENAGAKERJAAN TENTANG PELAKSANAAN PROGRAM JAMINAN HARI TUA")
+    );
+    assert!(out.markdown.contains("## BAB I"));
+
+    let lower = promote_legal_headings("memutuskan:\nMenetapkan: UNDANG-UNDANG TENTANG CIPTA KERJA.\nPasal 1", "auto");
+    assert!(lower.markdown.starts_with("## MEMUTUSKAN\n"));
+    assert_eq!(lower.found.title.as_deref(), Some("UNDANG-UNDANG TENTANG CIPTA KERJA"));
+}
+
+#[test]
+fn promote_letter_spaced_headings() {
+    let input = "B A B   I   KETENTUAN UMUM\nP a s a l   1\nDalam Undang-Undang ini yang dimaksud dengan:";
+    let out = promote_legal_headings(input, "auto");
+    assert!(out.markdown.starts_with("## BAB I KETENTUAN UMUM\n## Pasal 1\nDalam"));
+    assert_eq!((out.found.bab, out.found.pasal), (1, 1));
+    // spaced heading is not soft-wrapped into the following line by cleanup
+    let cleaned = law_cleanup("P a s a l   2\nPekerja berhak", "auto").cleaned;
+    assert_eq!(promote_legal_headings(&cleaned, "auto").found.pasal, 1);
+    // ordinary lines and number rows are untouched
+    assert_eq!(collapse_letter_spacing("a. bahwa pekerja"), "a. bahwa pekerja");
+    assert_eq!(collapse_letter_spacing("1 2 3 4"), "1 2 3 4");
+}
+
+#[test]
+fn second_pass_over_output_is_a_fixed_point() {
+    let input = "BAB I\nKETENTUAN UMUM\n\nPasal 1\nDalam Undang-Undang ini yang dimaksud dengan\npekerja adalah setiap orang.";
+    let cleaned = law_cleanup(input, "auto");
+    let md = promote_legal_headings(&cleaned.cleaned, "auto").markdown;
+    assert_eq!(check_idempotent(&md, "auto", &CleanupConfig::default()), None, "{}", md);
+}
+
+#[test]
+fn idempotence_check_reports_first_changed_line() {
+    // Raw, unpromoted heading: a second pass would turn it into `## Pasal 1`
+    let diff = check_idempotent("## BAB I\n\nPasal 1\nIsi.", "auto", &CleanupConfig::default()).expect("diff");
+    assert_eq!(diff.line, 3);
+    assert_eq!(diff.first, "Pasal 1");
+    assert_eq!(diff.second, "## Pasal 1");
+}
+
+#[test]
+fn repair_mojibake_reverses_double_encoding() {
+    assert_eq!(repair_mojibake("Undang-Undang tentang cafÃ©"), "Undang-Undang tentang café");
+    assert_eq!(repair_mojibake("â€œPekerjaâ€\u{9d} adalah"), "\u{201c}Pekerja\u{201d} adalah");
+    assert_eq!(repair_mojibake("Pasal 1 â€“ ayat"), "Pasal 1 \u{2013} ayat");
+    // encoded twice
+    assert_eq!(repair_mojibake("Ã¢â‚¬â€œ"), "\u{2013}");
+}
+
+#[test]
+fn repair_mojibake_leaves_correct_text_and_counts_repairs() {
+    for ok in ["café – “kutipan”", "Ã rea", "Pasal 1 ayat (2)", "ÂBC"] {
+        assert_eq!(repair_mojibake(ok), ok);
+    }
+    let out = law_cleanup("Menteri dapat menunjuk pejabat lain â€“ dengan cafÃ©.", "auto");
+    assert_eq!(out.stats.mojibake_repaired, 2);
+    assert!(out.cleaned.contains("lain \u{2013} dengan café."));
+}
+
+#[test]
+fn normalize_punctuation_is_opt_in_and_runs_after_footer_removal() {
+    let input = "\u{201C}Pekerja\u{201D} adalah orang \u{2014} lihat \u{2018}ayat\u{2019} 1\u{2013}3\u{2026}\n\u{2013} 4 \u{2013}";
+    let plain = law_cleanup(input, "auto");
+    assert!(plain.cleaned.contains("\u{201C}Pekerja\u{201D}"));
+
+    let cfg = CleanupConfig { normalize_punctuation: true, ..CleanupConfig::default() };
+    let out = law_cleanup_with(input, "auto", &cfg);
+    assert_eq!(out.cleaned, "\"Pekerja\" adalah orang - lihat 'ayat' 1\u{2013}3...");
+    assert_eq!(out.stats.removed_footer, 1);
+}
+
+#[test]
+fn roman_numerals_parse_only_canonical_forms() {
+    assert_eq!(parse_roman("XIV"), Some(14));
+    assert_eq!(parse_roman("IX"), Some(9));
+    assert_eq!(parse_roman("IIII"), None);
+    assert_eq!(parse_roman("IL"), None);
+    assert_eq!(to_roman(1994), "MCMXCIV");
+}
+
+#[test]
+fn bab_sequence_gaps_and_jumps_are_reported() {
+    let input = "BAB I\nUMUM\nBAB II\nISI\nBAB Il\nLAIN\nBAB IV\nAKHIR\nBAB II\nULANG\nBAB IIII\nX";
+    let md = promote_legal_headings(input, "auto");
+    let w = check_structure(&md.found);
+    assert_eq!(w.missing_bab, vec!["III"]);
+    assert_eq!(w.unordered_bab, vec!["II"]);
+    assert_eq!(w.invalid_bab, vec!["IIII"]);
+
+    let ok = promote_legal_headings("BAB I\nA\nBAB II\nB\nBAB III\nC", "auto");
+    assert!(check_structure(&ok.found).is_empty());
+}
+
+#[test]
+fn pasal_gaps_and_duplicates_are_reported_outside_penjelasan() {
+    let input = "Pasal 1\nA\nPasal 2\nB\nPasal 4\nC\nPasal 4\nD\nPasal 5\nE\nPENJELASAN\nPasal 1\nCukup jelas.\nPasal 2\nCukup jelas.";
+    let md = promote_legal_headings(input, "auto");
+    let w = check_structure(&md.found);
+    assert_eq!(w.missing_pasal, vec![3]);
+    assert_eq!(w.duplicate_pasal, vec!["4"]);
+    assert!(w.missing_bab.is_empty());
+
+    let json = serde_json::to_value(&w).unwrap();
+    assert_eq!(json, serde_json::json!({"missing_pasal": [3], "duplicate_pasal": ["4"]}));
+}
+
+#[test]
+fn promote_inserted_pasal_with_letter_suffix() {
+    let input = "Pasal 15\nA\nPasal 15A\nB\nP a s a l   1 5 b\nC\nPasal 16\nD";
+    let md = promote_legal_headings(input, "auto");
+    assert!(md.markdown.contains("## Pasal 15A\nB"));
+    assert!(md.markdown.contains("## Pasal 15B\nC"));
+    assert_eq!(md.found.pasal, 4);
+    assert_eq!(md.found.pasal_labels, vec!["15", "15A", "15B", "16"]);
+    // 15A/15B sit between 15 and 16: only 1..=14 are missing, nothing is duplicated
+    let w = check_structure(&md.found);
+    assert_eq!(w.missing_pasal, (1..=14).collect::<Vec<u32>>());
+    assert!(w.duplicate_pasal.is_empty());
+}
+
+#[test]
+fn law_mode_family_parses_resolves_and_strips_headers() {
+    assert_eq!(LawMode::parse("PERDA"), Some(LawMode::Perda));
+    assert_eq!(LawMode::parse("keppres"), None);
+    assert!(LawMode::Perpres.requires_articles());
+    assert!(!LawMode::Kepmen.requires_articles());
+
+    let perda = "GUBERNUR JAWA TIMUR\n\nPERATURAN DAERAH PROVINSI JAWA TIMUR\nNOMOR 1 TAHUN 2020";
+    assert_eq!(resolve_law_mode(LawMode::Auto, perda), LawMode::Perda);
+    assert_eq!(resolve_law_mode(LawMode::Uu, perda), LawMode::Uu);
+    let pp = "PERATURAN PEMERINTAH REPUBLIK INDONESIA\nNOMOR 5 TAHUN 2021\nMengingat : Undang-Undang Nomor 11 Tahun 2020";
+    assert_eq!(resolve_law_mode(LawMode::Auto, pp), LawMode::Pp);
+    assert_eq!(resolve_law_mode(LawMode::Auto, "tanpa judul"), LawMode::Auto);
+
+    let out = law_cleanup("BUPATI SLEMAN\nIsi ketentuan.\nBUPATI SLEMAN,", "perda");
+    assert_eq!(out.stats.removed_header, 1);
+    assert_eq!(out.cleaned, "Isi ketentuan.\nBUPATI SLEMAN,");
+    assert_eq!(law_cleanup("BUPATI SLEMAN\nIsi.", "auto").stats.removed_header, 0);
+}
+
+#[test]
+fn promote_diktum_of_decision_letter() {
+    let input = "MEMUTUSKAN:\nMenetapkan : KEPUTUSAN MENTERI TENTANG TIM PELAKSANA.\nKESATU : Membentuk Tim Pelaksana.\nKEDUA : Tim bertugas menyusun laporan.\nKETIGA :\nKeputusan ini mulai berlaku pada tanggal ditetapkan.";
+    let md = promote_legal_headings(input, "kepmen");
+    assert_eq!(md.found.diktum, 3);
+    assert_eq!(md.found.title.as_deref(), Some("KEPUTUSAN MENTERI TENTANG TIM PELAKSANA"));
+    assert!(md.markdown.contains("## KESATU\nMembentuk Tim Pelaksana."));
+    assert!(md.markdown.contains("## KEDUA\nTim bertugas menyusun laporan."));
+    assert!(md.markdown.contains("## KETIGA\nKeputusan ini mulai berlaku"));
+    assert_eq!(md.found.pasal, 0);
+    assert!(LawMode::Kepmen.uses_diktum());
+}
+
+#[test]
+fn cleanup_does_not_join_onto_diktum_lines() {
+    let out = law_cleanup("KESATU : Membentuk Tim Pelaksana\nKEDUA : Tim bertugas", "kepmen");
+    assert_eq!(out.cleaned, "KESATU : Membentuk Tim Pelaksana\nKEDUA : Tim bertugas");
+}
+
+#[test]
+fn preserve_quotes_renders_indented_blocks_as_blockquotes() {
+    let input = "  Ketentuan Pasal 5 diubah sehingga berbunyi sebagai\n  berikut:\n\n        Pasal 5\n        Setiap pekerja berhak\n        memperoleh upah.\n\n  Paragraf biasa yang\n  menjorok sedikit.\n\n      1. butir daftar\n      2. butir kedua";
+    let cfg = CleanupConfig { preserve_quotes: true, ..CleanupConfig::default() };
+    let out = law_cleanup_with(input, "auto", &cfg);
+    assert_eq!(out.stats.quote_blocks, 1);
+    assert!(out.cleaned.contains("\n> Pasal 5\n> Setiap pekerja berhak\n> memperoleh upah.\n"), "{}", out.cleaned);
+    assert!(out.cleaned.contains("Paragraf biasa yang menjorok sedikit."), "{}", out.cleaned);
+    assert!(!out.cleaned.contains("> 1."), "{}", out.cleaned);
+
+    // off by default: the quoted article is flattened like any other text
+    let plain = law_cleanup(input, "auto");
+    assert_eq!(plain.stats.quote_blocks, 0);
+    assert!(!plain.cleaned.contains("> "));
+}
+
+#[test]
+fn keep_lines_protects_lines_from_footer_removal() {
+    // a lone tariff amount looks exactly like a plain page number
+    let input = "Tarif per hari\n  150\nBerlaku.\n- 3 -";
+    let plain = law_cleanup(input, "auto");
+    assert!(!plain.cleaned.contains("150"));
+
+    let cfg = CleanupConfig { keep_lines: Some(regex::Regex::new(r"^\s{2}\d+$").unwrap()), ..CleanupConfig::default() };
+    let out = law_cleanup_with(input, "auto", &cfg);
+    assert!(out.cleaned.contains("150"), "{}", out.cleaned);
+    assert!(!out.cleaned.contains("- 3 -"));
+    assert_eq!(out.stats.removed_footer, 1);
+}
+
+#[test]
+fn footnotes_are_lifted_from_page_bottoms_and_rendered() {
+    let pages = vec![
+        "Pasal 1\nPekerja adalah setiap orang yang bekerja1 dengan menerima\nupah.\n\n__________\n1 Lihat Pasal 1 angka 3 UU 13/2003,\nsebagaimana telah diubah.\n- 1 -".to_string(),
+        "Pasal 2\nPemberi kerja¹ wajib membayar upah.\n\n¹ Termasuk badan usaha.\n- 2 -".to_string(),
+        "Pasal 3\n(1) Isi ayat.\n2 orang saksi hadir.".to_string(),
+    ];
+    let (out, notes) = extract_footnotes(&pages);
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].text, "Lihat Pasal 1 angka 3 UU 13/2003, sebagaimana telah diubah.");
+    assert_eq!((notes[1].label, notes[1].marker.as_str(), notes[1].page), (2, "¹", 1));
+    assert_eq!(out[0], "Pasal 1\nPekerja adalah setiap orang yang bekerja[^1] dengan menerima\nupah.\n- 1 -");
+    assert_eq!(out[1], "Pasal 2\nPemberi kerja[^2] wajib membayar upah.\n- 2 -");
+    // a marker-like line with no blank or rule line above it is body text
+    assert_eq!(out[2], pages[2]);
+
+    let cleaned = law_cleanup(&out.join("\n"), "auto").cleaned;
+    assert!(cleaned.contains("bekerja[^1] dengan menerima upah."), "{}", cleaned);
+    assert_eq!(render_footnotes(&notes), "[^1]: Lihat Pasal 1 angka 3 UU 13/2003, sebagaimana telah diubah.\n\n[^2]: Termasuk badan usaha.");
+}
+
+#[test]
+fn wrap_markdown_wraps_body_and_keeps_markers() {
+    let md = "## Pasal 1 dengan judul yang sangat panjang sekali\n\nSetiap orang berhak atas pekerjaan yang layak bagi kemanusiaan.\n\n(1) Pengusaha wajib memberikan upah kepada pekerja.\n<!-- page: 2 -->\n";
+    let out = wrap_markdown(md, 24);
+    assert_eq!(
+        out,
+        "## Pasal 1 dengan judul yang sangat panjang sekali\n\nSetiap orang berhak atas\npekerjaan yang layak\nbagi kemanusiaan.\n\n(1) Pengusaha wajib\n    memberikan upah\n    kepada pekerja.\n<!-- page: 2 -->\n"
+    );
+    assert!(out.lines().all(|l| l.starts_with("##") || l.chars().count() <= 24));
+    assert_eq!(wrap_markdown(&out, 24), out);
+    assert_eq!(wrap_markdown(md, 0), md);
+}
+
+#[test]
+fn wrap_markdown_never_starts_a_line_with_a_block_token() {
+    let md = "Jalan nasional yang menghubungkan kota Jakarta - Bandung wajib dipelihara oleh pemerintah.\n";
+    let out = wrap_markdown(md, 37);
+    assert_eq!(out, "Jalan nasional yang menghubungkan\nkota Jakarta - Bandung wajib\ndipelihara oleh pemerintah.\n");
+
+    let md = "Ketentuan sebagaimana dimaksud dalam ayat (2) huruf a. berlaku juga bagi angka 3. dan # lainnya.\n";
+    for width in 10..40 {
+        let out = wrap_markdown(md, width);
+        for line in out.lines().skip(1) {
+            let first = line.split_whitespace().next().unwrap();
+            assert!(!["(2)", "a.", "3.", "#", "-"].contains(&first), "width {}: {:?}", width, out);
+        }
+        assert_eq!(wrap_markdown(&out, width), out);
+    }
+}
+
+#[test]
+fn recounted_headings_match_found_on_a_normal_document() {
+    let input = "Menimbang : a. bahwa negara menjamin hak;\nMengingat : 1. Pasal 27 UUD 1945;\nMEMUTUSKAN:\nMenetapkan : UNDANG-UNDANG TENTANG KETENAGAKERJAAN.\nBAB I\nKETENTUAN UMUM\nPasal 1\nIsi pertama.\nBAB II\nLANDASAN\nPasal 2\nIsi kedua.\nPENJELASAN\nPasal 1\nCukup jelas.";
+    let md = promote_legal_headings(input, "auto");
+    assert_eq!(recount_headings(&md.markdown, &md.found), vec![]);
+
+    // A heading lost after promotion is reported with both counts
+    let dropped = md.markdown.replace("## Pasal 2", "Pasal 2");
+    let w = recount_headings(&dropped, &md.found);
+    assert_eq!(w.len(), 1);
+    assert_eq!((w[0].heading.as_str(), w[0].found, w[0].markdown), ("Pasal", 2, 1));
+}
+
+#[test]
+fn no_reflow_keeps_line_breaks_but_still_cleans() {
+    let input = "PRESIDEN REPUBLIK INDONESIA\nBaris pertama yang ber-\nakhir di sini\nbaris kedua\n- 2 -\nbaris ketiga";
+    let cfg = CleanupConfig { no_reflow: true, ..CleanupConfig::default() };
+    let out = law_cleanup_with(input, "auto", &cfg);
+    assert_eq!(out.cleaned, "Baris pertama yang berakhir di sini\nbaris kedua\nbaris ketiga");
+    assert_eq!(out.stats.removed_header, 1);
+    assert_eq!(out.stats.removed_footer, 1);
+    assert_eq!(out.stats.hyphens_fixed, 1);
+
+    let reflowed = law_cleanup_with(input, "auto", &CleanupConfig::default());
+    assert_eq!(reflowed.cleaned, "Baris pertama yang berakhir di sini baris kedua baris ketiga");
+}
+
+#[test]
+fn layout_padded_lines_still_reflow_into_one_paragraph() {
+    // `pdftotext -layout` pads lines out to the column width
+    let input = "Setiap tenaga kerja berhak memperoleh            \n   perlindungan atas keselamatan dan          \n   kesehatan kerja.      \n\nPasal 2   \nIsi kedua;     \nberikutnya   ";
+    let out = law_cleanup(input, "auto");
+    assert_eq!(
+        out.cleaned,
+        "Setiap tenaga kerja berhak memperoleh perlindungan atas keselamatan dan kesehatan kerja.\n\nPasal 2\nIsi kedua;\nberikutnya"
+    );
+}
+
+#[test]
+fn orphan_marker_collects_every_wrapped_line_of_its_item() {
+    // marker in a narrow left column, text wrapped over three lines that end in commas
+    let input = "Pasal 1\nDalam Undang-Undang ini yang dimaksud dengan:\na.\n     Pemberi kerja adalah orang perseorangan,\n     pengusaha, badan hukum,\n     atau badan lainnya yang mempekerjakan tenaga kerja;\nb.\n     Pekerja adalah setiap orang yang bekerja.\nPenjelasan umum berikut tidak termasuk butir.";
+    let out = law_cleanup(input, "auto");
+    assert_eq!(
+        out.cleaned,
+        "Pasal 1\nDalam Undang-Undang ini yang dimaksud dengan:\n- a. Pemberi kerja adalah orang perseorangan, pengusaha, badan hukum, atau badan lainnya yang mempekerjakan tenaga kerja;\n- b. Pekerja adalah setiap orang yang bekerja.\nPenjelasan umum berikut tidak termasuk butir."
+    );
+    let md = promote_legal_headings(&out.cleaned, "auto").markdown;
+    assert_eq!(check_idempotent(&md, "auto", &CleanupConfig::default()), None);
+}
+
+#[test]
+fn soft_wrap_join_stops_at_the_next_marker() {
+    // wrapped lines ending in letters must not swallow the following item
+    let input = "Pasal 1\nDalam Undang-Undang ini yang dimaksud dengan:\na.\n   teks pertama\n   teks kedua\n   teks ketiga\nb. kedua.";
+    let out = law_cleanup(input, "auto");
+    assert_eq!(
+        out.cleaned,
+        "Pasal 1\nDalam Undang-Undang ini yang dimaksud dengan:\n- a. teks pertama teks kedua teks ketiga\n- b. kedua."
+    );
+    let md = promote_legal_headings(&out.cleaned, "auto").markdown;
+    assert_eq!(check_idempotent(&md, "auto", &CleanupConfig::default()), None);
+}
+
+#[test]
+fn strip_gibberish_drops_qr_noise_and_keeps_legal_text() {
+    let page = "Pasal 5\nSetiap tenaga kerja memiliki kesempatan yang sama tanpa diskriminasi.\nhQ7zKp2W#x@!9LmQ %$&*^~ kXr9PzQw2L\nDokumen ini telah ditandatangani secara elektronik.\nhttps://jdih.kemnaker.go.id/verifikasi-dokumen-elektronik-2024\nBSrE 2024/KP-17 (s.d. 31-12-2024)".to_string();
+    let (pages, removed) = strip_gibberish(&[page]);
+    assert_eq!(removed, 1);
+    assert!(!pages[0].contains("hQ7z"));
+    assert!(pages[0].contains("tanpa diskriminasi."));
+    assert!(pages[0].contains("https://jdih.kemnaker.go.id"));
+    assert!(pages[0].contains("BSrE 2024/KP-17"));
+    assert!(!is_gibberish_line("Lembaran Negara Republik Indonesia Tahun 2003 Nomor 39"));
+    assert!(!is_gibberish_line("a. bahwa xyz; dan"));
+}
+
+#[test]
+fn penjelasan_pasal_entries_are_promoted_one_level_deeper() {
+    let input = "BAB I\nKETENTUAN UMUM\nPasal 5\nSetiap tenaga kerja berhak.\nPENJELASAN\nPasal 5\nCukup jelas.";
+    let md = promote_legal_headings(input, "auto");
+    assert!(md.markdown.contains("## Pasal 5\nSetiap tenaga kerja berhak."));
+    assert!(md.markdown.contains("## PENJELASAN\n### Pasal 5\nCukup jelas."));
+    assert_eq!(md.found.pasal, 1);
+    assert_eq!(md.found.penjelasan_pasal, 1);
+    assert_eq!(md.found.pasal_labels, vec!["5"]);
+    assert_eq!(recount_headings(&md.markdown, &md.found), vec![]);
+}
+
+#[test]
+fn preserve_formulas_keeps_formula_lines_verbatim_inside_pasal() {
+    let input = "Pasal 7\nBesaran tarif dihitung sebagai berikut\nTarif = Dasar x 0,5%\na.   Denda = 2% x Tarif x Bulan\nketerangan lebih lanjut diatur\ndengan Peraturan Menteri.";
+    let cfg = CleanupConfig { preserve_formulas: true, ..CleanupConfig::default() };
+    let out = law_cleanup_with(input, "auto", &cfg);
+    assert_eq!(
+        out.cleaned,
+        "Pasal 7\nBesaran tarif dihitung sebagai berikut\nTarif = Dasar x 0,5%\na.   Denda = 2% x Tarif x Bulan\nketerangan lebih lanjut diatur dengan Peraturan Menteri."
+    );
+    assert_eq!(out.stats.formula_lines, 2);
+
+    // without the flag the formula is joined into the sentence
+    let plain = law_cleanup_with(input, "auto", &CleanupConfig::default());
+    assert!(plain.cleaned.contains("berikut Tarif = Dasar x 0,5%"));
+
+    assert!(is_formula_line("PPh = 5% x (Penghasilan Bruto - PTKP)"));
+    assert!(!is_formula_line("nilai pengganti = harga yang ditetapkan oleh Menteri setelah 30 hari"));
+}
+
+#[test]
+fn process_text_runs_the_text_pipeline_on_form_feed_pages() {
+    let text = "PRESIDEN REPUBLIK INDONESIA\nBAB I\nKETENTUAN UMUM\n\nPasal 1\nDalam Peraturan Pemerintah ini yang\ndimaksud dengan pekerja.\n\x0cPRESIDEN REPUBLIK INDONESIA\nPasal 2\nCukup jelas.\n\x0c";
+    let pages = split_text_pages(text);
+    assert_eq!(pages.len(), 2);
+    assert_eq!(split_text_pages("satu"), vec!["satu".to_string()]);
+
+    let result = process_text(&pages, &ConvertOptions { law_mode: LawMode::Pp, ..ConvertOptions::default() });
+    assert_eq!(result.law_mode, LawMode::Pp);
+    assert!(!result.promoted.markdown.contains("PRESIDEN"), "{}", result.promoted.markdown);
+    assert!(result.promoted.markdown.contains("## Pasal 1\nDalam Peraturan Pemerintah ini yang dimaksud dengan pekerja."), "{}", result.promoted.markdown);
+    assert_eq!((result.promoted.found.bab, result.promoted.found.pasal), (1, 2));
+    assert!(result.structure_warnings.is_empty());
+    assert!(result.stats.removed_header >= 2);
+    assert!(result.metrics.character_coverage > 0.9);
+}
+
+#[test]
+fn process_text_strips_toc_gibberish_and_footnotes_like_the_pdf_path() {
+    let pages = vec![
+        "DAFTAR ISI\nBAB I KETENTUAN UMUM .......... 3\n\nPasal 1 ............ 3".to_string(),
+        "BAB I\nKETENTUAN UMUM\n\nPasal 1\nPekerja adalah setiap orang yang bekerja1 dengan menerima upah.\nhQ7zKp2W#x@!9LmQ %$&*^~ kXr9PzQw2L\n\n__________\n1 Lihat UU 13/2003.\n- 2 -".to_string(),
+    ];
+    let opts = ConvertOptions { footnotes: true, strip_gibberish: true, ..ConvertOptions::default() };
+    let result = process_text(&pages, &opts);
+    let md = &result.promoted.markdown;
+    assert_eq!(result.stats.toc_lines_removed, 3);
+    assert_eq!(result.stats.gibberish_removed, 1);
+    assert_eq!(result.footnotes.len(), 1);
+    assert!(!md.contains("DAFTAR ISI") && !md.contains("hQ7z"), "{}", md);
+    assert!(md.contains("bekerja[^1] dengan menerima upah."), "{}", md);
+    assert!(md.trim_end().ends_with("[^1]: Lihat UU 13/2003."), "{}", md);
+
+    // both switches off: gibberish and the footnote body stay in the text
+    let plain = process_text(&pages, &ConvertOptions::default());
+    assert_eq!((plain.stats.gibberish_removed, plain.footnotes.len()), (0, 0));
+    assert!(plain.promoted.markdown.contains("hQ7z"), "{}", plain.promoted.markdown);
+}
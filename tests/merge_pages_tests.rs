@@ -1,4 +1,4 @@
-use legalpdf_to_md::{merge_pages, OcrText};
+use legalpdf_to_md::{dehyphenate_page_seams, law_cleanup, merge_pages, merge_pages_with_markers, promote_legal_headings, OcrText};
 
 #[test]
 fn merge_overrides_replace_only_target_indices() {
@@ -14,3 +14,64 @@ fn merge_overrides_replace_only_target_indices() {
     assert_eq!(merged, "page1\nOCR_PAGE2\npage3");
 }
 
+
+#[test]
+fn merge_rejoins_word_hyphenated_across_pages() {
+    let pages = vec![
+        "Pasal 1\nDalam peratur-\n\n".to_string(),
+        "\nan ini yang dimaksud dengan:\nhuruf a".to_string(),
+    ];
+    let merged = merge_pages(&pages, &[]);
+    assert_eq!(merged, "Pasal 1\nDalam peraturan\n\n\nini yang dimaksud dengan:\nhuruf a");
+}
+
+#[test]
+fn page_seam_keeps_reduplication_and_skips_capitalized_heads() {
+    let mut pages = vec![
+        "hak anak-".to_string(),
+        "anak, dan".to_string(),
+        "akhir-".to_string(),
+        "Pasal 2".to_string(),
+    ];
+    assert_eq!(dehyphenate_page_seams(&mut pages), 1);
+    assert_eq!(pages[0], "hak anak-anak,");
+    assert_eq!(pages[1], "dan");
+    assert_eq!(pages[2], "akhir-");
+}
+
+#[test]
+fn page_markers_survive_cleanup_at_nearest_line_break() {
+    let pages = vec![
+        "Pasal 1\nPekerja adalah setiap orang yang bekerja dengan".to_string(),
+        "menerima upah.\nPasal 2\nCukup jelas.".to_string(),
+        "Pasal 3\nBerlaku sejak diundangkan.".to_string(),
+    ];
+    let merged = merge_pages_with_markers(&pages, &[], 4);
+    assert!(merged.starts_with("<!-- page:4 -->\nPasal 1"));
+
+    let cleaned = law_cleanup(&merged, "auto").cleaned;
+    let md = promote_legal_headings(&cleaned, "auto").markdown;
+    assert_eq!(
+        md,
+        "<!-- page:4 -->\n## Pasal 1\nPekerja adalah setiap orang yang bekerja dengan menerima upah.\n<!-- page:5 -->\n## Pasal 2\nCukup jelas.\n<!-- page:6 -->\n## Pasal 3\nBerlaku sejak diundangkan."
+    );
+}
+
+#[test]
+fn orphan_ayat_marker_merges_across_page_seam() {
+    let pages = vec![
+        "Pasal 5\n(2) Ayat kedua.\n(3)\n- 4 -\n".to_string(),
+        "PRESIDEN REPUBLIK INDONESIA\n\nKetentuan lebih lanjut diatur dengan Peraturan Pemerintah.".to_string(),
+    ];
+    let out = law_cleanup(&merge_pages(&pages, &[]), "auto");
+    assert!(out.cleaned.contains("(3) Ketentuan lebih lanjut diatur"), "{}", out.cleaned);
+
+    let marked = law_cleanup(&merge_pages_with_markers(&pages, &[], 1), "auto");
+    assert!(marked.cleaned.contains("(3) Ketentuan lebih lanjut diatur dengan Peraturan Pemerintah.\n<!-- page:2 -->"), "{}", marked.cleaned);
+}
+
+#[test]
+fn orphan_marker_does_not_swallow_a_heading_after_the_seam() {
+    let out = law_cleanup("a.\n\nPasal 6\nIsi.", "auto");
+    assert_eq!(out.cleaned, "a.\n\nPasal 6\nIsi.");
+}
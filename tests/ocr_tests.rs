@@ -0,0 +1,335 @@
+use legalpdf_to_md::{apply_ocr_texts, auto_dpi_for_height, detect_ocr_lang, ocr_artifact_name, ocr_tesseract, ocr_threads, parse_page_size_pts, parse_tesseract_tsv, set_ocr_threads, OcrImageFormat, OcrText, OcrOptions};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Mutex;
+
+// PATH is process-global; serialize tests that stub OCR binaries.
+static PATH_LOCK: Mutex<()> = Mutex::new(());
+
+/// Shell that prints `words` as tesseract TSV, one line, every word at confidence 90.
+fn tsv_words(words: &str) -> String {
+    format!(
+        r#"printf 'level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n'; for w in {}; do printf '5\t1\t1\t1\t1\t1\t0\t0\t1\t1\t90\t%s\n' "$w"; done"#,
+        words
+    )
+}
+
+fn stub_bin(dir: &Path, name: &str, script: &str) {
+    let bin = dir.join(name);
+    fs::write(&bin, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    let mut perms = fs::metadata(&bin).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&bin, perms).unwrap();
+}
+
+#[test]
+fn parse_page_size_from_pdfinfo_output() {
+    let out = "Title:          UU\nPage    1 size: 595.276 x 841.89 pts (A4)\nPage    1 rot:  0\n";
+    let (w, h) = parse_page_size_pts(out).expect("size line");
+    assert!((w - 595.276).abs() < 1e-6);
+    assert!((h - 841.89).abs() < 1e-6);
+    assert_eq!(parse_page_size_pts("Pages: 3\n"), None);
+}
+
+#[test]
+fn auto_dpi_targets_height_and_clamps() {
+    // A4 height ~842pt -> ~257 DPI for a 3000px render
+    assert_eq!(auto_dpi_for_height(841.89), 257);
+    // Large-format appendix (A1 ~2384pt) clamps to the floor
+    assert_eq!(auto_dpi_for_height(2384.0), 150);
+    // Tiny scan (~200pt) clamps to the ceiling
+    assert_eq!(auto_dpi_for_height(200.0), 600);
+    assert_eq!(auto_dpi_for_height(0.0), 300);
+}
+
+#[test]
+fn ocr_artifacts_are_named_by_doc_id() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    // pdftoppm: last arg is the output prefix
+    stub_bin(&bin, "pdftoppm", r#"for a; do last="$a"; done; printf 'PNG' > "$last.png""#);
+    stub_bin(&bin, "tesseract", &tsv_words("Pasal 1 hasil OCR"));
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+    let shared = td.path().join("artifacts");
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let outcomes: Vec<_> = ["uu-13-2003", "pp-5-2021"]
+        .iter()
+        .map(|id| {
+            let opts = OcrOptions { artifacts_dir: Some(shared.clone()), doc_id: id.to_string(), ..OcrOptions::default() };
+            ocr_tesseract(&pdf, &[0], &opts)
+        })
+        .collect();
+    std::env::set_var("PATH", old_path);
+
+    assert!(outcomes.iter().all(|o| o.texts.len() == 1 && o.failed.is_empty()));
+    // one 3-byte "PNG" render per document
+    assert!(outcomes.iter().all(|o| o.total_image_bytes == 3));
+    assert_eq!(ocr_artifact_name("uu-13-2003", 1, "png"), "uu-13-2003-page-1.png");
+    for id in ["uu-13-2003", "pp-5-2021"] {
+        assert!(shared.join("ocr").join(ocr_artifact_name(id, 1, "png")).exists());
+        let text = fs::read_to_string(shared.join("ocr").join(ocr_artifact_name(id, 1, "txt"))).unwrap();
+        assert_eq!(text, "Pasal 1 hasil OCR\n");
+    }
+}
+
+#[test]
+fn failed_text_artifact_write_is_recorded_and_keeps_the_ocr_text() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "pdftoppm", r#"for a; do last="$a"; done; printf 'PNG' > "$last.png""#);
+    stub_bin(&bin, "tesseract", &tsv_words("Pasal 1 hasil OCR"));
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+    let artifacts = td.path().join("artifacts");
+    // a directory where the text artifact should go makes the write fail
+    fs::create_dir_all(artifacts.join("ocr").join(ocr_artifact_name("doc", 1, "txt"))).unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let outcome = ocr_tesseract(&pdf, &[0], &OcrOptions { artifacts_dir: Some(artifacts.clone()), ..OcrOptions::default() });
+    std::env::set_var("PATH", old_path);
+
+    assert!(outcome.failed.is_empty());
+    assert_eq!(outcome.texts[0].text, "Pasal 1 hasil OCR\n");
+    assert_eq!(outcome.errors.len(), 1);
+    assert!(outcome.errors[0].message.starts_with("text_artifact_write_failed"), "{:?}", outcome.errors);
+    assert!(artifacts.join("ocr").join(ocr_artifact_name("doc", 1, "png")).exists());
+}
+
+#[test]
+fn dotted_doc_ids_find_their_rendered_page() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "pdftoppm", r#"for a; do last="$a"; done; printf 'PNG' > "$last.png""#);
+    stub_bin(&bin, "tesseract", &tsv_words("Pasal 1 hasil OCR"));
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let opts = OcrOptions { doc_id: "uu.13.2003".to_string(), ..OcrOptions::default() };
+    let outcome = ocr_tesseract(&pdf, &[2], &opts);
+    std::env::set_var("PATH", old_path);
+
+    assert!(outcome.failed.is_empty(), "{:?}", outcome.errors);
+    assert_eq!(outcome.texts.len(), 1);
+    assert_eq!(outcome.total_image_bytes, 3);
+    assert_eq!((outcome.page_confidence[0].index, outcome.page_confidence[0].confidence), (2, 90.0));
+}
+
+#[test]
+fn djvu_pages_render_with_ddjvu() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    // ddjvu: last arg is the output file; only page 2 is asked for
+    stub_bin(&bin, "ddjvu", r#"for a; do last="$a"; [ "$a" = "-page=2" ] && ok=1; done; [ -n "$ok" ] && printf 'TIFF' > "$last""#);
+    stub_bin(&bin, "tesseract", &tsv_words("Pasal 2 hasil OCR"));
+    let djvu = td.path().join("arsip.djvu");
+    fs::write(&djvu, b"AT&TFORM").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let outcome = ocr_tesseract(&djvu, &[1], &OcrOptions { artifacts_dir: Some(td.path().join("artifacts")), ..OcrOptions::default() });
+    std::env::set_var("PATH", old_path);
+
+    assert!(!outcome.skipped_due_to_missing_deps);
+    assert_eq!(outcome.failed, Vec::<usize>::new(), "{:?}", outcome.errors);
+    assert_eq!(outcome.texts[0].text.trim(), "Pasal 2 hasil OCR");
+    assert_eq!(outcome.total_image_bytes, 4);
+    // native resolution: no per-page DPI is chosen
+    assert!(outcome.page_dpi.is_empty());
+    assert!(td.path().join("artifacts/ocr").join(ocr_artifact_name("doc", 2, "tif")).exists());
+}
+
+#[test]
+fn jpeg_renders_use_jpg_extension_for_temp_and_artifacts() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    // writes <prefix>.jpg only when asked for -jpeg with the expected quality
+    stub_bin(
+        &bin,
+        "pdftoppm",
+        r#"fmt=png; for a; do [ "$a" = "-jpeg" ] && fmt=jpg; [ "$a" = "quality=60" ] && q=1; last="$a"; done; [ "$fmt" = jpg ] && [ -z "$q" ] && exit 1; printf 'IMG' > "$last.$fmt""#,
+    );
+    // tesseract must be handed the .jpg render
+    stub_bin(&bin, "tesseract", &format!(r#"case "$1" in *.jpg) {};; *) exit 1;; esac"#, tsv_words("Pasal 1 dari JPEG")));
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+    let artifacts = td.path().join("artifacts");
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let opts = OcrOptions {
+        artifacts_dir: Some(artifacts.clone()),
+        doc_id: "uu-1-2024".to_string(),
+        image_format: OcrImageFormat::Jpeg { quality: 60 },
+        ..OcrOptions::default()
+    };
+    let outcome = ocr_tesseract(&pdf, &[0], &opts);
+    std::env::set_var("PATH", old_path);
+
+    assert_eq!(outcome.texts.len(), 1, "{:?}", outcome.errors);
+    assert!(artifacts.join("ocr").join(ocr_artifact_name("uu-1-2024", 1, "jpg")).exists());
+    assert!(!artifacts.join("ocr").join(ocr_artifact_name("uu-1-2024", 1, "png")).exists());
+    assert_eq!(OcrImageFormat::default(), OcrImageFormat::Png);
+}
+
+#[test]
+fn tesseract_tsv_rebuilds_lines_and_averages_word_confidence() {
+    let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+        1\t1\t0\t0\t0\t0\t0\t0\t100\t100\t-1\t\n\
+        5\t1\t1\t1\t1\t1\t0\t0\t9\t9\t96.5\tPasal\n\
+        5\t1\t1\t1\t1\t2\t0\t0\t9\t9\t93.5\t1\n\
+        5\t1\t1\t2\t1\t1\t0\t0\t9\t9\t80\tDalam\n\
+        5\t1\t1\t2\t2\t1\t0\t0\t9\t9\t70\tini\n\
+        5\t1\t1\t2\t2\t2\t0\t0\t9\t9\t-1\tyang\n";
+    let (text, conf) = parse_tesseract_tsv(tsv);
+    assert_eq!(text, "Pasal 1\n\nDalam\nini yang\n");
+    assert_eq!(conf, Some(85.0));
+    assert_eq!(parse_tesseract_tsv("level\tpage_num\n"), (String::new(), None));
+}
+
+#[test]
+fn detect_ocr_lang_counts_function_words() {
+    assert_eq!(detect_ocr_lang("Setiap orang yang bekerja dengan upah dan untuk kepentingan ini pada dasarnya"), "ind");
+    assert_eq!(detect_ocr_lang("The employer shall pay the wages of the worker in full and on time"), "eng");
+    assert_eq!(detect_ocr_lang("Pekerja yang dan di dalam the contract of the employer and the"), "ind+eng");
+    assert_eq!(detect_ocr_lang("12 34 ---"), "ind+eng");
+}
+
+#[test]
+fn auto_lang_samples_first_page_then_uses_detected_language() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "pdftoppm", r#"for a; do last="$a"; done; printf 'PNG' > "$last.png""#);
+    // echoes the -l value after an Indonesian sentence
+    stub_bin(
+        &bin,
+        "tesseract",
+        &format!(r#"while [ $# -gt 0 ]; do [ "$1" = "-l" ] && l="$2"; shift; done; {}"#, tsv_words("Setiap orang yang bekerja dengan upah dan untuk ini pada lang=$l")),
+    );
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let opts = OcrOptions { lang: "auto".to_string(), ..OcrOptions::default() };
+    let outcome = ocr_tesseract(&pdf, &[0, 1], &opts);
+    std::env::set_var("PATH", old_path);
+
+    assert_eq!(outcome.detected_lang.as_deref(), Some("ind"));
+    assert!(outcome.texts[0].text.contains("lang=ind+eng"));
+    assert!(outcome.texts[1].text.contains("lang=ind\n"), "{}", outcome.texts[1].text);
+}
+
+#[test]
+fn ocr_text_only_replaces_pages_it_improves() {
+    let mut pages = vec![
+        "Pasal 1\nDalam Undang-Undang ini yang dimaksud dengan pekerja adalah setiap orang.".to_string(),
+        "".to_string(),
+    ];
+    let texts = vec![
+        OcrText { index: 0, text: "Pasal 1 Dalam Undang".to_string() },
+        OcrText { index: 1, text: "Pasal 2 hasil OCR".to_string() },
+    ];
+    let kept = apply_ocr_texts(&mut pages, &texts);
+    assert_eq!(kept, vec![0]);
+    assert!(pages[0].contains("setiap orang."));
+    assert_eq!(pages[1], "Pasal 2 hasil OCR");
+}
+
+#[test]
+fn ocr_threads_caps_concurrent_tesseract_runs_across_callers() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "pdftoppm", r#"for a; do last="$a"; done; printf 'PNG' > "$last.png""#);
+    // noclobber creation is atomic: a second concurrent run finds `busy` and records the overlap
+    let busy = td.path().join("busy");
+    let overlap = td.path().join("overlap");
+    stub_bin(
+        &bin,
+        "tesseract",
+        &format!(
+            r#"if ( set -C; : > "{busy}" ) 2>/dev/null; then /bin/sleep 0.05; /bin/rm -f "{busy}"; else : > "{overlap}"; fi; {tsv}"#,
+            busy = busy.display(),
+            overlap = overlap.display(),
+            tsv = tsv_words("Pasal 1")
+        ),
+    );
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    set_ocr_threads(1);
+    assert_eq!(ocr_threads(), 1);
+    let outcomes: Vec<_> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let pdf = &pdf;
+                s.spawn(move || ocr_tesseract(pdf, &[0, 1], &OcrOptions { doc_id: format!("doc{}", i), ..OcrOptions::default() }))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    set_ocr_threads(0);
+    std::env::set_var("PATH", old_path);
+
+    assert!(outcomes.iter().all(|o| o.texts.len() == 2), "{:?}", outcomes.iter().map(|o| &o.errors).collect::<Vec<_>>());
+    assert!(!overlap.exists(), "two tesseract processes ran at once");
+    assert!(ocr_threads() >= 1);
+}
+
+#[test]
+fn pages_of_one_document_are_ocred_in_parallel_and_kept_in_order() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "pdftoppm", r#"for a; do last="$a"; done; printf 'PNG' > "$last.png""#);
+    // same overlap probe as above; each page reads back its own image name
+    let busy = td.path().join("busy");
+    let overlap = td.path().join("overlap");
+    stub_bin(
+        &bin,
+        "tesseract",
+        &format!(
+            r#"if ( set -C; : > "{busy}" ) 2>/dev/null; then /bin/sleep 0.2; /bin/rm -f "{busy}"; else : > "{overlap}"; fi; img="${{1##*/}}"; {tsv}"#,
+            busy = busy.display(),
+            overlap = overlap.display(),
+            tsv = tsv_words("Halaman ${img%.png}")
+        ),
+    );
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    set_ocr_threads(2);
+    let outcome = ocr_tesseract(&pdf, &[3, 0, 2, 1], &OcrOptions::default());
+    set_ocr_threads(0);
+    std::env::set_var("PATH", old_path);
+
+    assert!(overlap.exists(), "pages were OCR-ed one at a time");
+    let got: Vec<(usize, &str)> = outcome.texts.iter().map(|t| (t.index, t.text.trim())).collect();
+    assert_eq!(got, vec![(3, "Halaman doc-p4"), (0, "Halaman doc-p1"), (2, "Halaman doc-p3"), (1, "Halaman doc-p2")]);
+}
+
@@ -0,0 +1,41 @@
+use legalpdf_to_md::{enumerate_pdfs, poppler_extract, validate_prd, EmitError, ExitCode, PipelineError, PopplerError};
+use std::path::Path;
+
+#[test]
+fn library_errors_map_to_exit_codes() {
+    let err: PipelineError = poppler_extract(Path::new("./does/not/exist.pdf"), true, true).unwrap_err().into();
+    assert!(matches!(err, PipelineError::Poppler(_)));
+    assert_eq!((err.code(), err.label()), (1, "FileNotFound"));
+
+    let err: PipelineError = enumerate_pdfs("./does/not/exist/**/*.pdf").unwrap_err().into();
+    assert_eq!((err.code(), err.label()), (1, "NoFilesFound"));
+
+    let err: PipelineError = validate_prd(Path::new("./does/not/exist.yaml")).unwrap_err().into();
+    assert!(matches!(err, PipelineError::PrdInvalid(_)));
+    assert_eq!(err.code(), 3);
+
+    let err: PipelineError = EmitError::WriteFailed("disk full".into()).into();
+    assert_eq!((err.code(), err.label()), (6, "WriteFailed"));
+
+    let err: PipelineError = PopplerError::EmptyDocument("empty.pdf".into()).into();
+    assert_eq!((err.code(), err.label()), (1, "EmptyDocument"));
+}
+
+#[test]
+fn run_level_errors_have_fixed_codes() {
+    assert_eq!(PipelineError::MissingDependency { missing: vec!["pdftotext".into()] }.code(), 2);
+    assert_eq!(PipelineError::InvalidArgument("--ocr-psm".into()).code(), 4);
+    assert_eq!(PipelineError::StructureNotFound.code(), 5);
+    assert_eq!(PipelineError::QualityGateFailed { failed_documents: 2 }.code(), 7);
+    assert_eq!(PipelineError::NotIdempotent { line: 3 }.code(), 8);
+    assert_eq!(PipelineError::LeakDetected { failed_documents: 1 }.code(), 9);
+}
+
+#[test]
+fn exit_code_table_is_contiguous_and_matches_errors() {
+    let codes: Vec<i32> = ExitCode::ALL.iter().map(|c| c.code()).collect();
+    assert_eq!(codes, (0..=9).collect::<Vec<_>>());
+    assert!(ExitCode::ALL.iter().all(|c| !c.meaning().is_empty()));
+    assert_eq!(PipelineError::StructureNotFound.exit_code(), ExitCode::StructureNotFound);
+    assert_eq!(PipelineError::LeakDetected { failed_documents: 1 }.exit_code(), ExitCode::LeakDetected);
+}
@@ -1,5 +1,19 @@
-use legalpdf_to_md::{detect_suspect_pages, poppler_extract, PopplerError};
-use std::path::PathBuf;
+use legalpdf_to_md::{classify_pdf, detect_suspect_pages, detect_suspect_pages_by_density, djvu_extract, detect_two_column, page_reports, page_sizes_pts, parse_bbox_page, parse_page_sizes_pts, parse_pdfinfo, pdf_verdict, poppler_extract, poppler_extract_info, poppler_extract_with, Extraction, ExtractOptions, OcrPageConfidence, PdfInfo, PdfKind, PopplerError};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// PATH is process-global; serialize tests that stub Poppler binaries.
+static PATH_LOCK: Mutex<()> = Mutex::new(());
+
+fn stub_bin(dir: &Path, name: &str, script: &str) {
+    let bin = dir.join(name);
+    fs::write(&bin, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    let mut perms = fs::metadata(&bin).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&bin, perms).unwrap();
+}
 
 #[test]
 fn detect_suspect_pages_flags_short_pages() {
@@ -12,6 +26,19 @@ fn detect_suspect_pages_flags_short_pages() {
     assert_eq!(suspects, vec![0, 1]);
 }
 
+#[test]
+fn page_reports_mark_suspect_and_ocr_pages() {
+    let pages = vec!["Hasil OCR halaman".to_string(), "x".repeat(100)];
+    let confidence = vec![OcrPageConfidence { index: 0, confidence: 87.5 }];
+    let reports = page_reports(&pages, &[0], &[0], &confidence);
+    assert_eq!(reports.len(), 2);
+    assert_eq!((reports[0].index, reports[0].chars, reports[0].suspect, reports[0].ocr_ran), (0, 15, true, true));
+    assert_eq!(reports[0].ocr_confidence, Some(87.5));
+    assert_eq!((reports[1].chars, reports[1].suspect, reports[1].ocr_ran), (100, false, false));
+    let v = serde_json::to_value(&reports[1]).unwrap();
+    assert_eq!(v, serde_json::json!({"index": 1, "chars": 100, "suspect": false, "ocr_ran": false}));
+}
+
 #[test]
 fn poppler_extract_file_not_found() {
     let p = PathBuf::from("./this/does/not/exist.pdf");
@@ -22,3 +49,352 @@ fn poppler_extract_file_not_found() {
     }
 }
 
+#[test]
+fn fallback_split_keeps_interior_empty_pages() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    // no pdfinfo on PATH -> single-pass fallback
+    stub_bin(td.path(), "pdftotext", r"printf 'A\fB\f\fC\f'");
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", td.path());
+    let res = poppler_extract(&pdf, true, true);
+    std::env::set_var("PATH", old_path);
+
+    let pages = res.expect("fallback extraction");
+    assert_eq!(pages, vec!["A", "B", "", "C"]);
+}
+
+#[test]
+fn zero_page_pdf_is_an_empty_document() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    stub_bin(td.path(), "pdfinfo", "echo 'Pages:          0'");
+    stub_bin(td.path(), "pdftotext", "exit 0");
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", td.path());
+    let with_pdfinfo = poppler_extract(&pdf, true, true);
+    fs::remove_file(td.path().join("pdfinfo")).unwrap();
+    let fallback = poppler_extract(&pdf, true, true);
+    std::env::set_var("PATH", old_path);
+
+    assert!(matches!(with_pdfinfo, Err(PopplerError::EmptyDocument(_))), "{:?}", with_pdfinfo);
+    assert!(matches!(fallback, Err(PopplerError::EmptyDocument(_))), "{:?}", fallback);
+}
+
+#[test]
+fn page_range_bounds_per_page_extraction() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    stub_bin(td.path(), "pdfinfo", "echo 'Pages:          5'");
+    // prints the page number passed via -f
+    stub_bin(td.path(), "pdftotext", r#"while [ $# -gt 0 ]; do [ "$1" = "-f" ] && p="$2"; shift; done; printf "page %s" "$p""#);
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", td.path());
+    let opts = |pages| ExtractOptions { layout: true, nopgbrk: true, pages, ..ExtractOptions::default() };
+    let subset = poppler_extract_with(&pdf, &opts(Some((2, 4))));
+    let outside = poppler_extract_with(&pdf, &opts(Some((4, 9))));
+    std::env::set_var("PATH", old_path);
+
+    assert_eq!(subset.expect("subset extraction"), vec!["page 2", "page 3", "page 4"]);
+    match outside.unwrap_err() {
+        PopplerError::Other(msg) => assert!(msg.contains("4-9") && msg.contains("5 pages"), "{}", msg),
+        e => panic!("expected range error, got {:?}", e),
+    }
+}
+
+#[test]
+fn pdfinfo_metadata_is_parsed_and_returned() {
+    let out = "Title:          UU 13 2003\nAuthor:         \nProducer:       Acrobat Distiller 5.0\nCreationDate:   Tue Mar 25 10:00:00 2003 WIB\nPages:          2\n";
+    let info = parse_pdfinfo(out);
+    assert_eq!(info.title.as_deref(), Some("UU 13 2003"));
+    assert_eq!(info.author, None);
+    assert_eq!(info.mod_date, None);
+    assert_eq!(
+        serde_json::to_value(&info).unwrap(),
+        serde_json::json!({"title": "UU 13 2003", "producer": "Acrobat Distiller 5.0", "creation_date": "Tue Mar 25 10:00:00 2003 WIB"})
+    );
+    assert_eq!(parse_pdfinfo("Pages: 1\n"), PdfInfo::default());
+
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    stub_bin(td.path(), "pdfinfo", "printf 'Title: Peraturan\\nPages: 1\\n'");
+    stub_bin(td.path(), "pdftotext", "printf 'isi'");
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", td.path());
+    let res = poppler_extract_info(&pdf, &ExtractOptions { layout: true, nopgbrk: true, ..ExtractOptions::default() });
+    std::env::set_var("PATH", old_path);
+    let x = res.expect("extraction");
+    assert_eq!(x.pages, vec!["isi"]);
+    assert_eq!(x.pdf_info.title.as_deref(), Some("Peraturan"));
+}
+
+#[test]
+fn retry_raw_keeps_richer_raw_text_for_near_empty_pages() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    stub_bin(td.path(), "pdfinfo", "echo 'Pages: 2'");
+    // page 1 is fine in layout mode; page 2 only yields text without -layout
+    stub_bin(
+        td.path(),
+        "pdftotext",
+        r#"layout=0; while [ $# -gt 0 ]; do case "$1" in -layout) layout=1;; -f) p="$2";; esac; shift; done
+if [ "$p" = 1 ]; then printf 'Pasal 1 Dalam Peraturan ini yang dimaksud dengan pekerja adalah setiap orang.'; elif [ $layout = 1 ]; then printf ' '; else printf 'Teks mentah halaman dua yang cukup panjang untuk melewati ambang batas.'; fi"#,
+    );
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", td.path());
+    let opts = |retry_raw| ExtractOptions { layout: true, nopgbrk: true, retry_raw, ..ExtractOptions::default() };
+    let off = poppler_extract_info(&pdf, &opts(false));
+    let on = poppler_extract_info(&pdf, &opts(true));
+    std::env::set_var("PATH", old_path);
+
+    let off = off.expect("extraction");
+    assert_eq!(off.pages[1], " ");
+    assert!(off.raw_fallback_pages.is_empty());
+    let on = on.expect("extraction");
+    assert!(on.pages[1].starts_with("Teks mentah"));
+    assert_eq!(on.raw_fallback_pages, vec![1]);
+    assert_eq!(on.pages[0], off.pages[0]);
+}
+
+#[test]
+fn pdftotext_warnings_with_output_are_not_failures() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    stub_bin(td.path(), "pdfinfo", "echo 'Pages: 3'");
+    // page 1: warnings but exit 0; page 2: warnings, exit 1, text still written; page 3: fine
+    stub_bin(
+        td.path(),
+        "pdftotext",
+        r#"while [ $# -gt 0 ]; do [ "$1" = "-f" ] && p="$2"; shift; done
+echo "Syntax Warning: Invalid Font Weight" >&2
+printf "page %s" "$p"
+[ "$p" = 2 ] && { echo "Syntax Error: Unknown operator" >&2; exit 1; }
+exit 0"#,
+    );
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", td.path());
+    let ok = poppler_extract(&pdf, true, true);
+    stub_bin(td.path(), "pdftotext", "echo 'Syntax Error: xref table damaged' >&2; exit 1");
+    let failed = poppler_extract(&pdf, true, true);
+    std::env::set_var("PATH", old_path);
+
+    assert_eq!(ok.expect("warnings are not fatal"), vec!["page 1", "page 2", "page 3"]);
+    assert!(matches!(failed, Err(PopplerError::Other(_))));
+}
+
+// Synthetic `pdftotext -bbox` page: `rows` lines of words placed at the given x ranges.
+fn bbox_fixture(rows: usize, spans: &[(f64, f64)]) -> String {
+    let mut xml = String::from("<doc>\n  <page width=\"612.000000\" height=\"792.000000\">\n");
+    for r in 0..rows {
+        let y = 72.0 + r as f64 * 14.0;
+        for (x0, x1) in spans {
+            xml.push_str(&format!(
+                "    <word xMin=\"{:.6}\" yMin=\"{:.6}\" xMax=\"{:.6}\" yMax=\"{:.6}\">kata</word>\n",
+                x0, y, x1, y + 10.0
+            ));
+        }
+    }
+    xml.push_str("  </page>\n</doc>\n");
+    xml
+}
+
+#[test]
+fn detect_two_column_finds_gutter() {
+    // two words per column per line; gutter between 290 and 322
+    let xml = bbox_fixture(12, &[(50.0, 150.0), (160.0, 290.0), (322.0, 420.0), (430.0, 560.0)]);
+    let page = parse_bbox_page(&xml).expect("page");
+    assert_eq!(page.words.len(), 48);
+    assert_eq!(page.width, 612.0);
+    let split = detect_two_column(&page).expect("two columns");
+    assert!(split > 290.0 && split < 322.0, "split at {}", split);
+}
+
+#[test]
+fn detect_two_column_rejects_single_column() {
+    // words cross the page middle on every line
+    let xml = bbox_fixture(12, &[(50.0, 200.0), (210.0, 400.0), (410.0, 560.0)]);
+    let page = parse_bbox_page(&xml).expect("page");
+    assert_eq!(detect_two_column(&page), None);
+    // too few words to decide
+    let sparse = bbox_fixture(2, &[(50.0, 150.0), (400.0, 560.0)]);
+    assert_eq!(detect_two_column(&parse_bbox_page(&sparse).unwrap()), None);
+}
+
+#[test]
+fn classify_pdf_by_low_text_fraction() {
+    let text = "Pasal 1 ".repeat(20);
+    let blank = " \n".to_string();
+    let text_pages: Vec<String> = (0..10).map(|_| text.clone()).collect();
+    assert_eq!(classify_pdf(&text_pages), PdfKind::TextBased);
+
+    let scanned: Vec<String> = (0..10).map(|_| blank.clone()).collect();
+    assert_eq!(classify_pdf(&scanned), PdfKind::Scanned);
+    assert_eq!(classify_pdf(&[]), PdfKind::Scanned);
+
+    let mut mixed = text_pages.clone();
+    mixed[3] = blank.clone();
+    mixed[7] = blank;
+    assert_eq!(classify_pdf(&mixed), PdfKind::Mixed);
+}
+
+#[test]
+fn failed_page_is_empty_and_reported_instead_of_failing_the_document() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    stub_bin(td.path(), "pdfinfo", "printf 'Pages:          3\\n'");
+    stub_bin(
+        td.path(),
+        "pdftotext",
+        r#"while [ $# -gt 0 ]; do [ "$1" = "-f" ] && p="$2"; shift; done
+[ "$p" = 2 ] && { echo "Syntax Error: broken content stream" >&2; exit 1; }
+echo "Halaman $p""#,
+    );
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", td.path());
+    let got = poppler_extract_info(&pdf, &ExtractOptions::default());
+    std::env::set_var("PATH", old_path);
+
+    let ex = got.expect("document survives a failed page");
+    assert_eq!(ex.pages, vec!["Halaman 1\n", "", "Halaman 3\n"]);
+    assert_eq!(ex.failed_pages, vec![1]);
+    assert_eq!(ex.poppler_warnings, vec!["Syntax Error: broken content stream"]);
+}
+
+#[test]
+fn pdftotext_stderr_warnings_are_kept_distinct_and_without_offsets() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    stub_bin(td.path(), "pdfinfo", "printf 'Pages:          2\\n'");
+    stub_bin(
+        td.path(),
+        "pdftotext",
+        r#"echo "Syntax Warning (1024): Invalid Font Weight" >&2
+echo "Syntax Warning (2048): Invalid Font Weight" >&2
+echo "Syntax Error: Couldn't read xref table" >&2
+echo "progress note without a level" >&2
+echo "Pasal 1""#,
+    );
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", td.path());
+    let got = poppler_extract_info(&pdf, &ExtractOptions::default());
+    std::env::set_var("PATH", old_path);
+
+    let ex = got.expect("warnings do not fail extraction");
+    assert_eq!(ex.pages.len(), 2);
+    assert_eq!(ex.poppler_warnings, vec!["Syntax Warning: Invalid Font Weight", "Syntax Error: Couldn't read xref table"]);
+}
+
+#[test]
+fn djvu_extract_splits_djvutxt_output_on_form_feeds() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "djvused", "echo 3");
+    stub_bin(&bin, "djvutxt", r"printf 'BAB I\nPasal 1\n\014\014Pasal 2\n\014'");
+    let djvu = td.path().join("arsip.djvu");
+    fs::write(&djvu, b"AT&TFORM").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let all = djvu_extract(&djvu, None);
+    let ranged = djvu_extract(&djvu, Some((3, 3)));
+    let out_of_range = djvu_extract(&djvu, Some((2, 4)));
+    let missing = djvu_extract(&td.path().join("nope.djvu"), None);
+    std::env::set_var("PATH", old_path);
+
+    // the blank middle page is kept so indices match the DjVu pages
+    assert_eq!(all.unwrap().pages, vec!["BAB I\nPasal 1\n".to_string(), String::new(), "Pasal 2\n".to_string()]);
+    assert_eq!(ranged.unwrap().pages, vec!["Pasal 2\n".to_string()]);
+    assert!(matches!(out_of_range, Err(PopplerError::Other(_))));
+    assert!(matches!(missing, Err(PopplerError::FileNotFound(_))));
+}
+
+#[test]
+fn djvu_without_a_text_layer_yields_empty_pages_for_ocr() {
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "djvused", "echo 2");
+    stub_bin(&bin, "djvutxt", "exit 0");
+    let djvu = td.path().join("pindaian.djvu");
+    fs::write(&djvu, b"AT&TFORM").unwrap();
+
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let extraction = djvu_extract(&djvu, None);
+    std::env::set_var("PATH", old_path);
+
+    let pages = extraction.unwrap().pages;
+    assert_eq!(pages, vec![String::new(), String::new()]);
+    assert_eq!(detect_suspect_pages(&pages, 50), vec![0, 1]);
+}
+
+#[test]
+fn pdf_verdict_reports_kind_suspects_and_unreadable_files() {
+    let body = "Pasal 1 Dalam Undang-Undang ini yang dimaksud dengan pekerja adalah setiap orang.".to_string();
+    let mixed: Result<Extraction, PopplerError> = Ok(Extraction { pages: vec![body.clone(), String::new(), body], ..Extraction::default() });
+    let v = pdf_verdict(&mixed);
+    assert!(v.readable());
+    assert_eq!((v.kind, v.page_count, v.suspect_count, v.needs_ocr, v.encrypted), (Some(PdfKind::Mixed), 3, 1, true, false));
+
+    let encrypted = pdf_verdict(&Err(PopplerError::EncryptedPDF("x.pdf".into())));
+    assert!(!encrypted.readable() && encrypted.encrypted && encrypted.kind.is_none());
+    let empty = pdf_verdict(&Err(PopplerError::EmptyDocument("x.pdf".into())));
+    assert!(!empty.readable() && !empty.encrypted);
+    let json = serde_json::to_value(&v).unwrap();
+    assert_eq!(json["kind"], "mixed");
+    assert!(json.get("error").is_none());
+}
+
+#[test]
+fn density_suspects_scale_with_page_area() {
+    let text = |n: usize| "x".repeat(n);
+    // 100 chars: dense enough on A5 (~48 sq in), too sparse on A1 (~774 sq in)
+    let pages = vec![text(100), text(100), text(40)];
+    let sizes = vec![Some((420.0, 595.0)), Some((1684.0, 2384.0)), None];
+    assert_eq!(detect_suspect_pages_by_density(&pages, &sizes, 1.0), vec![1, 2]);
+    // missing sizes count as A4 (~97 sq in)
+    assert_eq!(detect_suspect_pages_by_density(&pages, &[], 0.5), vec![2]);
+}
+
+#[test]
+fn page_sizes_come_from_one_pdfinfo_call() {
+    let out = "Pages:          3\nPage    2 size: 595.276 x 841.89 pts (A4)\nPage    2 rot:  0\nPage    3 size: 1684 x 2384 pts (A1)\n";
+    assert_eq!(parse_page_sizes_pts(out), vec![(2, (595.276, 841.89)), (3, (1684.0, 2384.0))]);
+
+    let _guard = PATH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let td = tempfile::tempdir().unwrap();
+    let bin = td.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    stub_bin(&bin, "pdfinfo", &format!("printf '{}'", out.replace('\n', "\\n")));
+    let pdf = td.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+    let old_path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", &bin);
+    let sizes = page_sizes_pts(&pdf, 2, 3);
+    std::env::set_var("PATH", old_path);
+    assert_eq!(sizes, vec![Some((595.276, 841.89)), Some((1684.0, 2384.0)), None]);
+}
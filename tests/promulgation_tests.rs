@@ -0,0 +1,65 @@
+use legalpdf_to_md::{check_idempotent, extract_promulgation, law_cleanup, CleanupConfig, Promulgation};
+
+const CLOSING: &str = "Agar setiap orang mengetahuinya, memerintahkan pengundangan Undang-Undang ini.
+
+Disahkan di Jakarta
+pada tanggal 25 Maret 2003
+PRESIDEN REPUBLIK INDONESIA,
+
+ttd.
+
+MEGAWATI SOEKARNOPUTRI
+
+Diundangkan di Jakarta
+pada tanggal 25 Maret 2003
+SEKRETARIS NEGARA REPUBLIK INDONESIA,
+
+ttd.
+
+BAMBANG KESOWO
+
+LEMBARAN NEGARA REPUBLIK INDONESIA TAHUN 2003 NOMOR 39";
+
+#[test]
+fn extract_promulgation_reads_closing_block() {
+    let p = extract_promulgation(CLOSING);
+    assert_eq!(p.enacted_place.as_deref(), Some("Jakarta"));
+    assert_eq!(p.enacted_date.as_deref(), Some("2003-03-25"));
+    assert_eq!(p.promulgated_place.as_deref(), Some("Jakarta"));
+    assert_eq!(p.promulgated_date.as_deref(), Some("2003-03-25"));
+    assert_eq!(p.signatory.as_deref(), Some("MEGAWATI SOEKARNOPUTRI"));
+    assert_eq!(p.gazette.as_deref(), Some("LEMBARAN NEGARA REPUBLIK INDONESIA TAHUN 2003 NOMOR 39"));
+}
+
+#[test]
+fn extract_promulgation_handles_joined_text_and_old_spelling() {
+    // soft-wrap joined, as after law_cleanup; Permen wording
+    let text = "Ditetapkan di Jakarta pada tanggal 3 Nopember 2015 MENTERI KETENAGAKERJAAN REPUBLIK INDONESIA,\nttd.\nM. HANIF DHAKIRI Diundangkan di Jakarta pada tanggal 9 Nopember 2015\nBERITA NEGARA REPUBLIK INDONESIA TAHUN 2015 NOMOR 1688";
+    let p = extract_promulgation(text);
+    assert_eq!(p.enacted_date.as_deref(), Some("2015-11-03"));
+    assert_eq!(p.signatory.as_deref(), Some("M. HANIF DHAKIRI"));
+    assert_eq!(p.promulgated_date.as_deref(), Some("2015-11-09"));
+    assert_eq!(p.gazette.as_deref(), Some("BERITA NEGARA REPUBLIK INDONESIA TAHUN 2015 NOMOR 1688"));
+}
+
+#[test]
+fn extract_promulgation_leaves_absent_fields_null() {
+    let p = extract_promulgation("Pasal 1\nIsi.\nTAMBAHAN LEMBARAN NEGARA REPUBLIK INDONESIA TAHUN 2003 NOMOR 4279\nDisahkan di Jakarta");
+    assert_eq!(
+        p,
+        Promulgation { enacted_place: Some("Jakarta".into()), ..Promulgation::default() }
+    );
+}
+
+#[test]
+fn ttd_placeholders_become_signature_lines_and_feed_the_signatory() {
+    let raw = "Disahkan di Jakarta\npada tanggal 25 Maret 1974\nPRESIDEN REPUBLIK INDONESIA,\nttd\nSOEHARTO\n\nDiundangkan di Jakarta\npada tanggal 25 Maret 1974\nMENTERI/SEKRETARIS NEGARA\nTTD. SUDHARMONO";
+    let out = law_cleanup(raw, "uu");
+    assert_eq!(out.stats.signatures_marked, 2);
+    assert!(!out.cleaned.to_lowercase().contains("ttd"), "{}", out.cleaned);
+    assert!(out.cleaned.contains("PRESIDEN REPUBLIK INDONESIA,\n(signature)\nSOEHARTO"), "{}", out.cleaned);
+    // a name on the ttd line itself moves below the marker, which never joins the line above
+    assert!(out.cleaned.contains("MENTERI/SEKRETARIS NEGARA\n(signature)\nSUDHARMONO"), "{}", out.cleaned);
+    assert_eq!(extract_promulgation(&out.cleaned).signatory.as_deref(), Some("SOEHARTO"));
+    assert_eq!(check_idempotent(&out.cleaned, "uu", &CleanupConfig::default()), None);
+}
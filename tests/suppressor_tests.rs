@@ -0,0 +1,114 @@
+use legalpdf_to_md::{suppress_repeated_lines, SuppressorConfig};
+
+fn page(body: &str) -> String {
+    format!("DINAS TENAGA KERJA PROVINSI\nJl. Merdeka No. 1 Jakarta\n{}\nSalinan sesuai aslinya\nKepala Biro Hukum", body)
+}
+
+#[test]
+fn repeated_two_line_blocks_removed_as_unit() {
+    let pages = vec![
+        page("Pasal 1\nIsi pertama."),
+        page("Pasal 2\nIsi kedua."),
+        page("Pasal 3\nIsi ketiga."),
+    ];
+    let (out, stats, _) = suppress_repeated_lines(&pages, &SuppressorConfig::default());
+    assert_eq!(out[0], "Pasal 1\nIsi pertama.");
+    assert_eq!(out[2], "Pasal 3\nIsi ketiga.");
+    // one top and one bottom block per page
+    assert_eq!(stats.removed_blocks, 6);
+}
+
+#[test]
+fn blocks_containing_whitelisted_lines_are_kept() {
+    let pages: Vec<String> = (0..3)
+        .map(|i| format!("BAB I\nKETENTUAN UMUM\nIsi halaman {}.", i))
+        .collect();
+    let (out, stats, _) = suppress_repeated_lines(&pages, &SuppressorConfig::default());
+    assert_eq!(stats.removed_blocks, 0);
+    assert!(out.iter().all(|p| p.starts_with("BAB I\n")));
+}
+
+fn dense_footer_pages() -> (Vec<String>, SuppressorConfig) {
+    // footnote numbers differ per page, so only the pattern (not block repetition) applies
+    let pages = (0..3)
+        .map(|p| {
+            let footer: Vec<String> = (1..=8).map(|i| format!("Catatan kaki {}{}", p, i)).collect();
+            format!("Isi halaman {}.\n{}", p, footer.join("\n"))
+        })
+        .collect();
+    let cfg = SuppressorConfig {
+        footer_patterns: vec![regex::Regex::new(r"^Catatan kaki \d+$").unwrap()],
+        ..SuppressorConfig::default()
+    };
+    (pages, cfg)
+}
+
+#[test]
+fn removal_cap_defaults_to_five_and_counts_overrun() {
+    let (pages, cfg) = dense_footer_pages();
+    let (out, stats, _) = suppress_repeated_lines(&pages, &cfg);
+    assert_eq!(out[0], "Isi halaman 0.\nCatatan kaki 06\nCatatan kaki 07\nCatatan kaki 08");
+    assert_eq!(stats.suppressor_overrun, 9);
+}
+
+#[test]
+fn raised_removal_cap_cleans_dense_footers() {
+    let (pages, mut cfg) = dense_footer_pages();
+    cfg.max_removals_per_page = 8;
+    let (out, stats, _) = suppress_repeated_lines(&pages, &cfg);
+    assert!(out.iter().enumerate().all(|(i, p)| *p == format!("Isi halaman {}.", i)));
+    assert_eq!(stats.suppressor_overrun, 0);
+}
+
+fn three_line_footer_pages() -> Vec<String> {
+    (0..3)
+        .map(|p| format!("Isi halaman {}.\nDokumen ini telah ditandatangani secara elektronik\nKode verifikasi: X{}\njdih.kemnaker.go.id", p, p))
+        .collect()
+}
+
+#[test]
+fn footer_window_catches_lines_above_the_last() {
+    let pages = three_line_footer_pages();
+    // default window: only the last line is positional
+    let (out, _, _) = suppress_repeated_lines(&pages, &SuppressorConfig::default());
+    assert_eq!(out[1], "Isi halaman 1.\nDokumen ini telah ditandatangani secara elektronik\nKode verifikasi: X1");
+
+    let cfg = SuppressorConfig { footer_lines: 3, ..SuppressorConfig::default() };
+    let (out, _, _) = suppress_repeated_lines(&pages, &cfg);
+    assert_eq!(out[1], "Isi halaman 1.\nKode verifikasi: X1");
+}
+
+#[test]
+fn mid_page_watermarks_removed_only_when_enabled() {
+    let pages = vec![
+        "Pasal 1\nIsi pertama\nSALINAN RESMI\nHAK ASASI\nlanjutan pertama.".to_string(),
+        "Pasal 2\nSALINAN RESMI\nHAK ASASI\nIsi kedua.".to_string(),
+        "Pasal 3\nIsi ketiga\nlanjutan\nSALINAN RESMI\nakhir ketiga.".to_string(),
+    ];
+    let (out, stats, _) = suppress_repeated_lines(&pages, &SuppressorConfig::default());
+    assert_eq!(stats.removed_watermark, 0);
+    assert!(out[0].contains("SALINAN RESMI"));
+
+    let cfg = SuppressorConfig {
+        watermark_ratio: Some(0.6),
+        watermark_whitelist: Some(regex::Regex::new(r"^HAK ASASI$").unwrap()),
+        ..SuppressorConfig::default()
+    };
+    let (out, stats, _) = suppress_repeated_lines(&pages, &cfg);
+    assert_eq!(stats.removed_watermark, 3);
+    assert!(out.iter().all(|p| !p.contains("SALINAN RESMI")));
+    assert!(out[0].contains("HAK ASASI"), "{}", out[0]);
+    assert_eq!(out[1], "Pasal 2\nHAK ASASI\nIsi kedua.");
+}
+
+#[test]
+fn custom_whitelist_keeps_repeated_line() {
+    let pages: Vec<String> = (1..=3).map(|i| format!("DIKTUM\nIsi halaman {}.", i)).collect();
+    let (out, _, _) = suppress_repeated_lines(&pages, &SuppressorConfig::default());
+    assert!(out.iter().all(|p| !p.contains("DIKTUM")));
+
+    let cfg = SuppressorConfig { whitelist: Some(regex::Regex::new(r"^DIKTUM$").unwrap()), ..SuppressorConfig::default() };
+    let (out, _, candidates) = suppress_repeated_lines(&pages, &cfg);
+    assert!(out.iter().all(|p| p.starts_with("DIKTUM\n")));
+    assert!(!candidates.contains(&"DIKTUM".to_string()));
+}
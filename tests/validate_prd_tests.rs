@@ -0,0 +1,139 @@
+use std::fs;
+
+use legalpdf_to_md::{law_cleanup_with, validate_prd, PrdError, PrdRoot};
+
+const BASE: &str = r#"id: "t"
+tools:
+  - name: check_deps
+  - name: enumerate_pdfs
+datasources:
+  - path: "./input/**/*.pdf"
+outputs:
+  dir: "./output"
+"#;
+
+fn write_prd(extra: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+    let td = tempfile::tempdir().unwrap();
+    let p = td.path().join("prd.yaml");
+    fs::write(&p, format!("{}{}", BASE, extra)).unwrap();
+    (td, p)
+}
+
+#[test]
+fn custom_cleanup_patterns_are_merged_into_cleanup() {
+    let (_td, p) = write_prd("cleanup:\n  header_patterns: ['^\\s*PEMERINTAH KOTA SURABAYA\\s*$']\n  footer_patterns: ['^\\s*www\\.surabaya\\.go\\.id\\s*$']\n");
+    let prd = validate_prd(&p).expect("valid prd");
+    let cfg = prd.cleanup_config().unwrap();
+    let out = law_cleanup_with("PEMERINTAH KOTA SURABAYA\nIsi.\nwww.surabaya.go.id\n- 3 -", "auto", &cfg);
+    assert_eq!(out.cleaned, "Isi.");
+    assert_eq!(out.stats.removed_header, 1);
+    assert_eq!(out.stats.removed_footer, 2);
+}
+
+#[test]
+fn invalid_cleanup_pattern_names_the_pattern() {
+    let (_td, p) = write_prd("cleanup:\n  footer_patterns: ['(unclosed']\n");
+    match validate_prd(&p) {
+        Err(PrdError::Invalid(msg)) => assert!(msg.contains("(unclosed"), "{}", msg),
+        other => panic!("expected Invalid, got {:?}", other.map(|p| p.id)),
+    }
+}
+
+#[test]
+fn every_datasource_needs_a_path() {
+    let td = tempfile::tempdir().unwrap();
+    let p = td.path().join("prd.yaml");
+    let yaml = BASE.replace(
+        "  - path: \"./input/**/*.pdf\"\n",
+        "  - name: a\n    path: \"./a/**/*.pdf\"\n  - name: b\n  - name: c\n    path: \"./c/**/*.pdf\"\n",
+    );
+    fs::write(&p, yaml).unwrap();
+    match validate_prd(&p) {
+        Err(PrdError::Invalid(msg)) => assert!(msg.contains("index 1"), "{}", msg),
+        other => panic!("expected Invalid, got {:?}", other.map(|p| p.id)),
+    }
+}
+
+#[test]
+fn input_globs_lists_all_datasources() {
+    let td = tempfile::tempdir().unwrap();
+    let p = td.path().join("prd.yaml");
+    let yaml = BASE.replace(
+        "  - path: \"./input/**/*.pdf\"\n",
+        "  - name: uu\n    path: \"./uu/**/*.pdf\"\n  - name: pp\n    path: \"./pp/**/*.pdf\"\n",
+    );
+    fs::write(&p, yaml).unwrap();
+    let prd = validate_prd(&p).expect("valid prd");
+    let under = |g: &str| td.path().join(g).to_string_lossy().into_owned();
+    assert_eq!(prd.input_glob(), under("uu/**/*.pdf"));
+    assert_eq!(prd.input_globs(), vec![under("uu/**/*.pdf"), under("pp/**/*.pdf")]);
+}
+
+#[test]
+fn env_vars_are_expanded_in_paths() {
+    std::env::set_var("LPDF_TEST_CORPUS_ROOT", "/srv/corpus");
+    let td = tempfile::tempdir().unwrap();
+    let p = td.path().join("prd.yaml");
+    let yaml = BASE
+        .replace("\"./input/**/*.pdf\"", "\"$LPDF_TEST_CORPUS_ROOT/input/**/*.pdf\"")
+        .replace("\"./output\"", "\"${LPDF_TEST_CORPUS_ROOT}/out\"");
+    fs::write(&p, yaml).unwrap();
+    let prd = validate_prd(&p).expect("valid prd");
+    assert_eq!(prd.input_glob(), "/srv/corpus/input/**/*.pdf");
+    assert_eq!(prd.output_dir(), "/srv/corpus/out");
+}
+
+#[test]
+fn unset_env_var_is_invalid() {
+    let td = tempfile::tempdir().unwrap();
+    let p = td.path().join("prd.yaml");
+    fs::write(&p, BASE.replace("\"./output\"", "\"${LPDF_TEST_SURELY_UNSET_VAR}/out\"")).unwrap();
+    match validate_prd(&p) {
+        Err(PrdError::Invalid(msg)) => assert!(msg.contains("LPDF_TEST_SURELY_UNSET_VAR"), "{}", msg),
+        other => panic!("expected Invalid, got {:?}", other.map(|p| p.id)),
+    }
+}
+
+#[test]
+fn config_at_arbitrary_path_with_cli_overrides() {
+    let td = tempfile::tempdir().unwrap();
+    let p = td.path().join("corpus-a.yml");
+    fs::write(&p, BASE).unwrap();
+    let mut prd = validate_prd(&p).expect("valid config");
+    prd.apply_overrides(Some("./other/**/*.pdf"), Some("./out-a"));
+    assert_eq!(prd.input_globs(), vec!["./other/**/*.pdf".to_string()]);
+    assert_eq!(prd.output_dir(), "./out-a");
+    // output override alone keeps the configured datasources
+    let mut prd = validate_prd(&p).unwrap();
+    prd.apply_overrides(None, Some("./out-b"));
+    assert_eq!(prd.input_glob(), td.path().join("input/**/*.pdf").to_string_lossy());
+}
+
+#[test]
+fn synthesized_cli_config_passes_validation() {
+    let prd = PrdRoot::from_cli("./scans/*.pdf", "./md");
+    let td = tempfile::tempdir().unwrap();
+    let p = td.path().join("prd.yaml");
+    fs::write(&p, serde_yaml::to_string(&prd).unwrap()).unwrap();
+    let round = validate_prd(&p).expect("synthesized config is valid");
+    assert_eq!(round.input_glob(), td.path().join("scans/*.pdf").to_string_lossy());
+    assert_eq!(round.output_dir(), "./md");
+}
+
+#[test]
+fn relative_globs_resolve_against_the_prd_directory_or_base_dir() {
+    let td = tempfile::tempdir().unwrap();
+    let p = td.path().join("prd.yaml");
+    let yaml = BASE.replace(
+        "  - path: \"./input/**/*.pdf\"\n",
+        "  - path: \"./input/**/*.pdf\"\n  - path: \"/srv/corpus/**/*.pdf\"\n",
+    );
+    fs::write(&p, &yaml).unwrap();
+    let prd = validate_prd(&p).expect("valid prd");
+    // absolute globs stay as written
+    assert_eq!(prd.input_globs(), vec![td.path().join("input/**/*.pdf").to_string_lossy().into_owned(), "/srv/corpus/**/*.pdf".to_string()]);
+
+    fs::write(&p, format!("{}base_dir: ../corpus\n", yaml)).unwrap();
+    let prd = validate_prd(&p).expect("valid prd");
+    assert_eq!(prd.input_glob(), td.path().join("../corpus/input/**/*.pdf").to_string_lossy());
+}